@@ -0,0 +1,33 @@
+CREATE TABLE user_identities (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    provider_id VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE (provider, provider_id)
+);
+
+CREATE INDEX idx_user_identities_user_id ON user_identities(user_id);
+
+INSERT INTO user_identities (user_id, provider, provider_id, email)
+SELECT id, provider, provider_id, email
+FROM users
+WHERE provider <> 'local' AND provider_id IS NOT NULL;
+
+CREATE TABLE account_link_requests (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    link_token VARCHAR(255) UNIQUE NOT NULL,
+    email_token VARCHAR(255) UNIQUE,
+    provider VARCHAR(50) NOT NULL,
+    provider_id VARCHAR(255) NOT NULL,
+    provider_email VARCHAR(255) NOT NULL,
+    provider_email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    display_name VARCHAR(255),
+    avatar_url TEXT,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_account_link_requests_user_id ON account_link_requests(user_id);
@@ -0,0 +1,8 @@
+CREATE TABLE oauth_exchange_codes (
+    code VARCHAR(255) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_exchange_codes_expires_at ON oauth_exchange_codes(expires_at);
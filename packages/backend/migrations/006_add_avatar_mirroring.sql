@@ -0,0 +1,17 @@
+ALTER TABLE users ADD COLUMN avatar_upstream_url TEXT;
+ALTER TABLE users ADD COLUMN avatar_provider VARCHAR(50);
+ALTER TABLE users ADD COLUMN avatar_synced_at TIMESTAMPTZ;
+
+-- Existing avatars are hotlinked provider URLs. Keep them as the upstream
+-- source so the server mirrors them at startup, attributed to the user's
+-- first linked provider.
+UPDATE users
+SET avatar_upstream_url = avatar_url,
+    avatar_provider = (
+        SELECT provider FROM user_identities
+        WHERE user_identities.user_id = users.id
+        ORDER BY created_at
+        LIMIT 1
+    ),
+    avatar_url = NULL
+WHERE avatar_url IS NOT NULL;
@@ -0,0 +1,22 @@
+CREATE TYPE email_category AS ENUM ('security', 'product_news', 'new_chapters', 'replies', 'digests');
+
+CREATE TABLE email_preferences (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    category email_category NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, category),
+    CHECK (category <> 'security' OR enabled)
+);
+
+CREATE TABLE email_suppressions (
+    email VARCHAR(255) PRIMARY KEY,
+    reason VARCHAR(50) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE email_outbox
+    ADD COLUMN category email_category NOT NULL DEFAULT 'security',
+    ADD COLUMN unsubscribe_url TEXT;
+
+ALTER TYPE email_status ADD VALUE 'suppressed';
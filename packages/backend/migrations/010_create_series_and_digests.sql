@@ -0,0 +1,42 @@
+CREATE TABLE series (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    title VARCHAR(255) NOT NULL,
+    slug VARCHAR(255) UNIQUE NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_series_updated_at BEFORE UPDATE ON series
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TABLE chapters (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    series_id UUID NOT NULL REFERENCES series(id) ON DELETE CASCADE,
+    number DOUBLE PRECISION NOT NULL,
+    title VARCHAR(255),
+    published_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (series_id, number)
+);
+
+CREATE INDEX idx_chapters_series_published ON chapters(series_id, published_at);
+
+CREATE TABLE series_follows (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    series_id UUID NOT NULL REFERENCES series(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, series_id)
+);
+
+CREATE INDEX idx_series_follows_series_id ON series_follows(series_id);
+
+-- IANA zone name and ISO weekday (1 = Monday) the weekly digest goes out on.
+ALTER TABLE users
+    ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    ADD COLUMN digest_day SMALLINT NOT NULL DEFAULT 1 CHECK (digest_day BETWEEN 1 AND 7);
+
+-- When each user's digest last ran, whether or not anything was sent.
+CREATE TABLE email_digests (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    last_run_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,5 @@
+-- The X-Request-Id of the request that created the row, so security events
+-- and outgoing mail can be traced back to their log lines.
+ALTER TABLE email_outbox ADD COLUMN request_id VARCHAR(128);
+ALTER TABLE user_identities ADD COLUMN request_id VARCHAR(128);
+ALTER TABLE account_link_requests ADD COLUMN request_id VARCHAR(128);
@@ -0,0 +1,10 @@
+-- One-time codes the link-account page trades for a pending link request,
+-- so the link token never appears in a redirect URL.
+CREATE TABLE oauth_link_codes (
+    code VARCHAR(255) PRIMARY KEY,
+    link_token VARCHAR(255) NOT NULL REFERENCES account_link_requests(link_token) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_link_codes_expires_at ON oauth_link_codes(expires_at);
@@ -0,0 +1,935 @@
+//! The config file and environment, before validation. Every field is
+//! optional here; `Layer::build` applies defaults and reports what is
+//! missing or invalid.
+
+use super::*;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::str::FromStr;
+
+const MIN_JWT_SECRET_LEN: usize = 32;
+const DEFAULT_APP_NAME: &str = "ForMangaReaders";
+const UNSUBSCRIBE_KEY_LABEL: &[u8] = b"formangareaders unsubscribe-token v1";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct Layer {
+    port: Option<u16>,
+    shutdown_timeout_secs: Option<u64>,
+    database_url: Option<String>,
+    app_name: Option<String>,
+    jwt_secret: Option<String>,
+    frontend_url: Option<String>,
+    backend_url: Option<String>,
+    google: GoogleLayer,
+    discord: DiscordLayer,
+    mail: MailLayer,
+    storage: StorageLayer,
+    i18n: I18nLayer,
+    metrics: MetricsLayer,
+    health: HealthLayer,
+    cors: CorsLayer,
+    rate_limit: RateLimitLayer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GoogleLayer {
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    auth_url: Option<String>,
+    token_url: Option<String>,
+    jwks_url: Option<String>,
+    issuers: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DiscordLayer {
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    auth_url: Option<String>,
+    token_url: Option<String>,
+    userinfo_url: Option<String>,
+    cdn_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MailLayer {
+    transport: Option<String>,
+    dir: Option<String>,
+    from_email: Option<String>,
+    from_name: Option<String>,
+    unsubscribe_secret: Option<String>,
+    unsubscribe_url: Option<String>,
+    smtp: SmtpLayer,
+    dkim: DkimLayer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SmtpLayer {
+    host: Option<String>,
+    port: Option<u16>,
+    security: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DkimLayer {
+    domain: Option<String>,
+    selector: Option<String>,
+    private_key_file: Option<String>,
+    algorithm: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StorageLayer {
+    media_dir: Option<String>,
+    public_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct I18nLayer {
+    dir: Option<String>,
+    hot_reload: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MetricsLayer {
+    token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HealthLayer {
+    check_mail: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CorsLayer {
+    policy: CorsPolicyLayer,
+    overrides: Vec<CorsOverrideLayer>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CorsPolicyLayer {
+    allowed_origins: Option<Vec<String>>,
+    allowed_methods: Option<Vec<String>>,
+    allowed_headers: Option<Vec<String>>,
+    allow_credentials: Option<bool>,
+    max_age_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CorsOverrideLayer {
+    path: String,
+    #[serde(default)]
+    policy: CorsPolicyLayer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RateLimitLayer {
+    enabled: Option<bool>,
+    store: Option<String>,
+    trust_forwarded_for: Option<bool>,
+    policies: BTreeMap<String, RateLimitPolicyLayer>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RateLimitPolicyLayer {
+    requests: Option<u32>,
+    per_secs: Option<u64>,
+    key: Option<String>,
+}
+
+/// Reads environment variables into a layer. Empty variables count as
+/// unset.
+struct EnvReader<'a> {
+    vars: &'a HashMap<String, String>,
+    problems: &'a mut Vec<ConfigProblem>,
+}
+
+impl EnvReader<'_> {
+    fn get(&self, env: &str) -> Option<String> {
+        self.vars
+            .get(env)
+            .filter(|value| !value.is_empty())
+            .cloned()
+    }
+
+    fn string(&self, target: &mut Option<String>, env: &str) {
+        if let Some(value) = self.get(env) {
+            *target = Some(value);
+        }
+    }
+
+    fn parse<T>(&mut self, target: &mut Option<T>, key: &'static str, env: &'static str)
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let Some(value) = self.get(env) else {
+            return;
+        };
+
+        match value.parse() {
+            Ok(value) => *target = Some(value),
+            Err(e) => self.problems.push(ConfigProblem {
+                key,
+                env,
+                message: format!("{value:?} is not valid: {e}"),
+            }),
+        }
+    }
+
+    fn flag(&mut self, target: &mut Option<bool>, key: &'static str, env: &'static str) {
+        let Some(value) = self.get(env) else {
+            return;
+        };
+
+        match value.to_lowercase().as_str() {
+            "true" | "1" | "yes" => *target = Some(true),
+            "false" | "0" | "no" => *target = Some(false),
+            _ => self.problems.push(ConfigProblem {
+                key,
+                env,
+                message: format!("{value:?} is not valid, expected true or false"),
+            }),
+        }
+    }
+
+    fn list(&self, target: &mut Option<Vec<String>>, env: &str) {
+        if let Some(value) = self.get(env) {
+            *target = Some(
+                value
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .collect(),
+            );
+        }
+    }
+}
+
+impl Layer {
+    /// Environment variables override the file.
+    pub(super) fn overlay_env(
+        &mut self,
+        vars: &HashMap<String, String>,
+        problems: &mut Vec<ConfigProblem>,
+    ) {
+        let mut env = EnvReader { vars, problems };
+
+        env.parse(&mut self.port, "port", "PORT");
+        env.parse(
+            &mut self.shutdown_timeout_secs,
+            "shutdown_timeout_secs",
+            "SHUTDOWN_TIMEOUT_SECS",
+        );
+        env.string(&mut self.database_url, "DATABASE_URL");
+        env.string(&mut self.app_name, "APP_NAME");
+        env.string(&mut self.jwt_secret, "JWT_SECRET_KEY");
+        env.string(&mut self.frontend_url, "FRONTEND_URL");
+        env.string(&mut self.backend_url, "BACKEND_URL");
+
+        let google = &mut self.google;
+        env.string(&mut google.client_id, "GOOGLE_CLIENT_ID");
+        env.string(&mut google.client_secret, "GOOGLE_CLIENT_SECRET");
+        env.string(&mut google.auth_url, "GOOGLE_AUTH_URL");
+        env.string(&mut google.token_url, "GOOGLE_TOKEN_URL");
+        env.string(&mut google.jwks_url, "GOOGLE_JWKS_URL");
+        env.list(&mut google.issuers, "GOOGLE_ISSUERS");
+
+        let discord = &mut self.discord;
+        env.string(&mut discord.client_id, "DISCORD_CLIENT_ID");
+        env.string(&mut discord.client_secret, "DISCORD_CLIENT_SECRET");
+        env.string(&mut discord.auth_url, "DISCORD_AUTH_URL");
+        env.string(&mut discord.token_url, "DISCORD_TOKEN_URL");
+        env.string(&mut discord.userinfo_url, "DISCORD_USERINFO_URL");
+        env.string(&mut discord.cdn_url, "DISCORD_CDN_URL");
+
+        let mail = &mut self.mail;
+        env.string(&mut mail.transport, "MAIL_TRANSPORT");
+        env.string(&mut mail.dir, "MAIL_DIR");
+        env.string(&mut mail.from_email, "SMTP_FROM_EMAIL");
+        env.string(&mut mail.from_email, "MAIL_FROM_EMAIL");
+        env.string(&mut mail.from_name, "SMTP_FROM_NAME");
+        env.string(&mut mail.from_name, "MAIL_FROM_NAME");
+        env.string(&mut mail.unsubscribe_secret, "UNSUBSCRIBE_SECRET");
+        env.string(&mut mail.unsubscribe_url, "UNSUBSCRIBE_URL");
+
+        let smtp = &mut mail.smtp;
+        env.string(&mut smtp.host, "SMTP_HOST");
+        env.parse(&mut smtp.port, "mail.smtp.port", "SMTP_PORT");
+        env.string(&mut smtp.security, "SMTP_SECURITY");
+        env.string(&mut smtp.username, "SMTP_USERNAME");
+        env.string(&mut smtp.password, "SMTP_PASSWORD");
+
+        let dkim = &mut mail.dkim;
+        env.string(&mut dkim.domain, "DKIM_DOMAIN");
+        env.string(&mut dkim.selector, "DKIM_SELECTOR");
+        env.string(&mut dkim.private_key_file, "DKIM_PRIVATE_KEY_FILE");
+        env.string(&mut dkim.algorithm, "DKIM_ALGORITHM");
+
+        env.string(&mut self.storage.media_dir, "MEDIA_DIR");
+        env.string(&mut self.storage.public_url, "MEDIA_PUBLIC_URL");
+
+        env.string(&mut self.i18n.dir, "I18N_DIR");
+        env.flag(
+            &mut self.i18n.hot_reload,
+            "i18n.hot_reload",
+            "I18N_HOT_RELOAD",
+        );
+
+        env.string(&mut self.metrics.token, "METRICS_TOKEN");
+        env.flag(
+            &mut self.health.check_mail,
+            "health.check_mail",
+            "READYZ_CHECK_MAIL",
+        );
+
+        let cors = &mut self.cors.policy;
+        env.list(&mut cors.allowed_origins, "CORS_ALLOWED_ORIGINS");
+        env.list(&mut cors.allowed_methods, "CORS_ALLOWED_METHODS");
+        env.list(&mut cors.allowed_headers, "CORS_ALLOWED_HEADERS");
+        env.flag(
+            &mut cors.allow_credentials,
+            "cors.policy.allow_credentials",
+            "CORS_ALLOW_CREDENTIALS",
+        );
+        env.parse(
+            &mut cors.max_age_secs,
+            "cors.policy.max_age_secs",
+            "CORS_MAX_AGE_SECS",
+        );
+
+        let rate_limit = &mut self.rate_limit;
+        env.flag(
+            &mut rate_limit.enabled,
+            "rate_limit.enabled",
+            "RATE_LIMIT_ENABLED",
+        );
+        env.string(&mut rate_limit.store, "RATE_LIMIT_STORE");
+        env.flag(
+            &mut rate_limit.trust_forwarded_for,
+            "rate_limit.trust_forwarded_for",
+            "RATE_LIMIT_TRUST_FORWARDED_FOR",
+        );
+    }
+
+    pub(super) fn i18n(self) -> I18nConfig {
+        self.i18n.build()
+    }
+
+    /// Applies defaults and validates. Problems are collected rather than
+    /// returned early, so one run reports everything that needs fixing; the
+    /// returned config is only meaningful when there are none.
+    pub(super) fn build(self, problems: &mut Vec<ConfigProblem>) -> Config {
+        let mut check = Checker { problems };
+
+        let jwt_secret = check.required(self.jwt_secret, "jwt_secret", "JWT_SECRET_KEY");
+        if !jwt_secret.is_empty() && jwt_secret.len() < MIN_JWT_SECRET_LEN {
+            check.problem(
+                "jwt_secret",
+                "JWT_SECRET_KEY",
+                format!(
+                    "must be at least {MIN_JWT_SECRET_LEN} characters; generate one with `openssl rand -base64 48`"
+                ),
+            );
+        }
+
+        let database_url = check.required(self.database_url, "database_url", "DATABASE_URL");
+        if !database_url.is_empty()
+            && !database_url.starts_with("postgres://")
+            && !database_url.starts_with("postgresql://")
+        {
+            check.problem("database_url", "DATABASE_URL", "must be a postgres:// URL");
+        }
+
+        let frontend_url = check.required(self.frontend_url, "frontend_url", "FRONTEND_URL");
+        let frontend_url = check.url(frontend_url, "frontend_url", "FRONTEND_URL");
+        let backend_url = check.required(self.backend_url, "backend_url", "BACKEND_URL");
+        let backend_url = check.url(backend_url, "backend_url", "BACKEND_URL");
+
+        let app_name = self
+            .app_name
+            .unwrap_or_else(|| DEFAULT_APP_NAME.to_string());
+
+        let google = check.google(self.google);
+        let discord = check.discord(self.discord);
+        let mail = check.mail(self.mail, &app_name, &jwt_secret, &backend_url);
+
+        let storage = self.storage.media_dir.map(|media_dir| StorageConfig {
+            media_dir,
+            public_url: match self.storage.public_url {
+                Some(url) => check.url(url, "storage.public_url", "MEDIA_PUBLIC_URL"),
+                None => format!("{backend_url}/media"),
+            },
+        });
+
+        Config {
+            port: self.port.unwrap_or(8000),
+            shutdown_timeout_secs: self.shutdown_timeout_secs.unwrap_or(30),
+            database_url,
+            app_name,
+            jwt_secret,
+            google,
+            discord,
+            mail,
+            storage,
+            i18n: self.i18n.build(),
+            metrics: MetricsConfig {
+                token: self.metrics.token,
+            },
+            health: HealthConfig {
+                check_mail: self.health.check_mail.unwrap_or(false),
+            },
+            cors: check.cors(self.cors, &frontend_url),
+            rate_limit: check.rate_limit(self.rate_limit),
+            frontend_url,
+            backend_url,
+        }
+    }
+}
+
+struct Checker<'a> {
+    problems: &'a mut Vec<ConfigProblem>,
+}
+
+impl Checker<'_> {
+    fn problem(&mut self, key: &'static str, env: &'static str, message: impl Into<String>) {
+        self.problems.push(ConfigProblem {
+            key,
+            env,
+            message: message.into(),
+        });
+    }
+
+    fn required(&mut self, value: Option<String>, key: &'static str, env: &'static str) -> String {
+        value.unwrap_or_else(|| {
+            self.problem(key, env, "is required");
+            String::new()
+        })
+    }
+
+    /// Accepts absolute http(s) URLs and drops a trailing slash, since
+    /// paths are appended with `format!("{url}/...")`.
+    fn url(&mut self, value: String, key: &'static str, env: &'static str) -> String {
+        if value.is_empty() {
+            return value;
+        }
+
+        match reqwest::Url::parse(&value) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            Ok(_) => self.problem(key, env, format!("{value:?} must be an http(s) URL")),
+            Err(e) => self.problem(key, env, format!("{value:?} is not a valid URL: {e}")),
+        }
+
+        value.trim_end_matches('/').to_string()
+    }
+
+    /// A provider is enabled by setting its client id and secret.
+    fn google(&mut self, layer: GoogleLayer) -> Option<GoogleConfig> {
+        if layer.client_id.is_none() && layer.client_secret.is_none() {
+            return None;
+        }
+
+        let defaults = GoogleEndpoints::default();
+        let mut url = |value: Option<String>, default: String, key, env| {
+            self.url(value.unwrap_or(default), key, env)
+        };
+        let endpoints = GoogleEndpoints {
+            auth_url: url(
+                layer.auth_url,
+                defaults.auth_url,
+                "google.auth_url",
+                "GOOGLE_AUTH_URL",
+            ),
+            token_url: url(
+                layer.token_url,
+                defaults.token_url,
+                "google.token_url",
+                "GOOGLE_TOKEN_URL",
+            ),
+            jwks_url: url(
+                layer.jwks_url,
+                defaults.jwks_url,
+                "google.jwks_url",
+                "GOOGLE_JWKS_URL",
+            ),
+            issuers: layer.issuers.unwrap_or(defaults.issuers),
+        };
+
+        Some(GoogleConfig {
+            client_id: self.required(layer.client_id, "google.client_id", "GOOGLE_CLIENT_ID"),
+            client_secret: self.required(
+                layer.client_secret,
+                "google.client_secret",
+                "GOOGLE_CLIENT_SECRET",
+            ),
+            endpoints,
+        })
+    }
+
+    fn discord(&mut self, layer: DiscordLayer) -> Option<DiscordConfig> {
+        if layer.client_id.is_none() && layer.client_secret.is_none() {
+            return None;
+        }
+
+        let defaults = DiscordEndpoints::default();
+        let mut url = |value: Option<String>, default: String, key, env| {
+            self.url(value.unwrap_or(default), key, env)
+        };
+        let endpoints = DiscordEndpoints {
+            auth_url: url(
+                layer.auth_url,
+                defaults.auth_url,
+                "discord.auth_url",
+                "DISCORD_AUTH_URL",
+            ),
+            token_url: url(
+                layer.token_url,
+                defaults.token_url,
+                "discord.token_url",
+                "DISCORD_TOKEN_URL",
+            ),
+            userinfo_url: url(
+                layer.userinfo_url,
+                defaults.userinfo_url,
+                "discord.userinfo_url",
+                "DISCORD_USERINFO_URL",
+            ),
+            cdn_url: url(
+                layer.cdn_url,
+                defaults.cdn_url,
+                "discord.cdn_url",
+                "DISCORD_CDN_URL",
+            ),
+        };
+
+        Some(DiscordConfig {
+            client_id: self.required(layer.client_id, "discord.client_id", "DISCORD_CLIENT_ID"),
+            client_secret: self.required(
+                layer.client_secret,
+                "discord.client_secret",
+                "DISCORD_CLIENT_SECRET",
+            ),
+            endpoints,
+        })
+    }
+
+    fn mail(
+        &mut self,
+        layer: MailLayer,
+        app_name: &str,
+        jwt_secret: &str,
+        backend_url: &str,
+    ) -> MailConfig {
+        let dir = || layer.dir.clone().unwrap_or_else(|| "mail".to_string());
+
+        let transport = match layer.transport.as_deref().map(str::to_lowercase).as_deref() {
+            Some("smtp") => MailTransportConfig::Smtp(self.smtp(layer.smtp)),
+            None if layer.smtp.host.is_some() => MailTransportConfig::Smtp(self.smtp(layer.smtp)),
+            // Printing mail must be asked for, so a deployment can't lose
+            // it by forgetting to configure a transport.
+            None => {
+                self.problem(
+                    "mail.transport",
+                    "MAIL_TRANSPORT",
+                    "is required without mail.smtp.host; set it to stdout to print mail instead",
+                );
+                MailTransportConfig::Stdout
+            }
+            Some("stdout") => MailTransportConfig::Stdout,
+            Some("file") => MailTransportConfig::File { dir: dir() },
+            Some("maildir") => MailTransportConfig::Maildir { dir: dir() },
+            Some("memory") => MailTransportConfig::Memory,
+            Some(other) => {
+                self.problem(
+                    "mail.transport",
+                    "MAIL_TRANSPORT",
+                    format!(
+                        "unknown transport {other:?}, expected smtp, file, maildir, stdout or memory"
+                    ),
+                );
+                MailTransportConfig::Stdout
+            }
+        };
+
+        let from_email = match layer.from_email {
+            Some(from_email) => from_email,
+            None if matches!(transport, MailTransportConfig::Smtp(_)) => {
+                self.required(None, "mail.from_email", "MAIL_FROM_EMAIL")
+            }
+            None => "noreply@localhost".to_string(),
+        };
+        if !from_email.is_empty() && from_email.parse::<lettre::Address>().is_err() {
+            self.problem(
+                "mail.from_email",
+                "MAIL_FROM_EMAIL",
+                format!("{from_email:?} is not an email address"),
+            );
+        }
+
+        // Defaults derived from `backend_url` are only as valid as it is, so
+        // only explicit values are checked here.
+        let unsubscribe_url = match layer.unsubscribe_url {
+            Some(url) => self.url(url, "mail.unsubscribe_url", "UNSUBSCRIBE_URL"),
+            None => format!("{backend_url}/api/v1/email/unsubscribe"),
+        };
+
+        MailConfig {
+            from_email,
+            from_name: layer.from_name.unwrap_or_else(|| app_name.to_string()),
+            transport,
+            dkim: self.dkim(layer.dkim),
+            unsubscribe_secret: self.unsubscribe_secret(layer.unsubscribe_secret, jwt_secret),
+            unsubscribe_url,
+        }
+    }
+
+    /// Unsubscribe links are signed with their own key so one never
+    /// verifies as the other. Without an explicit secret, the key is derived
+    /// from `jwt_secret` under a distinct label.
+    fn unsubscribe_secret(&mut self, secret: Option<String>, jwt_secret: &str) -> String {
+        match secret {
+            Some(secret) if secret == jwt_secret => {
+                self.problem(
+                    "mail.unsubscribe_secret",
+                    "UNSUBSCRIBE_SECRET",
+                    "must differ from jwt_secret; leave it unset to derive one",
+                );
+                secret
+            }
+            Some(secret) => secret,
+            None => derive_secret(jwt_secret, UNSUBSCRIBE_KEY_LABEL),
+        }
+    }
+
+    fn smtp(&mut self, layer: SmtpLayer) -> SmtpConfig {
+        let security = match layer.security.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("starttls") => SmtpSecurity::StartTls,
+            Some("tls") => SmtpSecurity::Tls,
+            Some("none") => SmtpSecurity::None,
+            Some(other) => {
+                self.problem(
+                    "mail.smtp.security",
+                    "SMTP_SECURITY",
+                    format!("unknown mode {other:?}, expected starttls, tls or none"),
+                );
+                SmtpSecurity::StartTls
+            }
+        };
+
+        let default_port = match security {
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        };
+
+        if layer.username.is_some() != layer.password.is_some() {
+            self.problem(
+                "mail.smtp.username",
+                "SMTP_USERNAME",
+                "username and password must be set together",
+            );
+        }
+
+        SmtpConfig {
+            host: self.required(layer.host, "mail.smtp.host", "SMTP_HOST"),
+            port: layer.port.unwrap_or(default_port),
+            security,
+            username: layer.username,
+            password: layer.password,
+        }
+    }
+
+    /// Signing is enabled by setting the domain.
+    fn dkim(&mut self, layer: DkimLayer) -> Option<DkimConfig> {
+        let domain = layer.domain?;
+
+        let algorithm = match layer.algorithm.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("rsa") => DkimAlgorithm::Rsa,
+            Some("ed25519") => DkimAlgorithm::Ed25519,
+            Some(other) => {
+                self.problem(
+                    "mail.dkim.algorithm",
+                    "DKIM_ALGORITHM",
+                    format!("unknown algorithm {other:?}, expected rsa or ed25519"),
+                );
+                DkimAlgorithm::Rsa
+            }
+        };
+
+        Some(DkimConfig {
+            domain,
+            selector: self.required(layer.selector, "mail.dkim.selector", "DKIM_SELECTOR"),
+            private_key_file: self.required(
+                layer.private_key_file,
+                "mail.dkim.private_key_file",
+                "DKIM_PRIVATE_KEY_FILE",
+            ),
+            algorithm,
+        })
+    }
+
+    /// The default policy admits only the frontend. Overrides start from the
+    /// default policy and replace what they set.
+    fn cors(&mut self, layer: CorsLayer, frontend_url: &str) -> CorsConfig {
+        let frontend_origin = reqwest::Url::parse(frontend_url)
+            .map(|url| vec![url.origin().ascii_serialization()])
+            .unwrap_or_default();
+        let defaults = CorsPolicy::for_origins(frontend_origin);
+
+        let mut errors = Vec::new();
+        let policy = layer.policy.apply(&defaults, &mut errors);
+        for (field, message) in errors {
+            let (key, env) = match field {
+                "allowed_origins" => ("cors.policy.allowed_origins", "CORS_ALLOWED_ORIGINS"),
+                "allowed_methods" => ("cors.policy.allowed_methods", "CORS_ALLOWED_METHODS"),
+                "allowed_headers" => ("cors.policy.allowed_headers", "CORS_ALLOWED_HEADERS"),
+                _ => ("cors.policy.allow_credentials", "CORS_ALLOW_CREDENTIALS"),
+            };
+            self.problem(key, env, message);
+        }
+
+        let mut overrides: Vec<CorsOverride> = Vec::new();
+        for layer in layer.overrides {
+            let path = layer.path.trim_end_matches('/').to_string();
+            if !path.starts_with('/') {
+                self.problem(
+                    "cors.overrides",
+                    "",
+                    format!("path {:?} must start with /", layer.path),
+                );
+            } else if overrides.iter().any(|existing| existing.path == path) {
+                self.problem(
+                    "cors.overrides",
+                    "",
+                    format!("path {path:?} is listed twice"),
+                );
+            }
+
+            let mut errors = Vec::new();
+            let override_policy = layer.policy.apply(&policy, &mut errors);
+            for (field, message) in errors {
+                self.problem("cors.overrides", "", format!("{path}: {field} {message}"));
+            }
+
+            overrides.push(CorsOverride {
+                path,
+                policy: override_policy,
+            });
+        }
+
+        CorsConfig { policy, overrides }
+    }
+
+    /// Policies in the file start from the built-in ones and replace what
+    /// they set. Only the built-in names exist, since routes refer to them.
+    fn rate_limit(&mut self, layer: RateLimitLayer) -> RateLimitConfig {
+        let store = match layer.store.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("memory") => RateLimitStoreKind::Memory,
+            Some("postgres") => RateLimitStoreKind::Postgres,
+            Some(other) => {
+                self.problem(
+                    "rate_limit.store",
+                    "RATE_LIMIT_STORE",
+                    format!("unknown store {other:?}, expected memory or postgres"),
+                );
+                RateLimitStoreKind::Memory
+            }
+        };
+
+        let mut config = RateLimitConfig {
+            enabled: layer.enabled.unwrap_or(true),
+            store,
+            trust_forwarded_for: layer.trust_forwarded_for.unwrap_or(false),
+            ..RateLimitConfig::default()
+        };
+
+        for (name, layer) in layer.policies {
+            let Some(policy) = RateLimitPolicyName::ALL
+                .into_iter()
+                .find(|known| known.as_str() == name)
+                .and_then(|known| config.policies.get_mut(&known))
+            else {
+                self.problem(
+                    "rate_limit.policies",
+                    "",
+                    format!(
+                        "unknown policy {name:?}, expected auth-strict, read-default or upload"
+                    ),
+                );
+                continue;
+            };
+
+            if let Some(requests) = layer.requests {
+                if requests == 0 {
+                    self.problem(
+                        "rate_limit.policies",
+                        "",
+                        format!("{name}: requests must be at least 1"),
+                    );
+                }
+                policy.requests = requests;
+            }
+
+            if let Some(per_secs) = layer.per_secs {
+                if per_secs == 0 {
+                    self.problem(
+                        "rate_limit.policies",
+                        "",
+                        format!("{name}: per_secs must be at least 1"),
+                    );
+                }
+                policy.per_secs = per_secs;
+            }
+
+            match layer.key.as_deref().map(str::to_lowercase).as_deref() {
+                None => {}
+                Some("ip") => policy.key = RateLimitKey::Ip,
+                Some("user") => policy.key = RateLimitKey::User,
+                Some("token") => policy.key = RateLimitKey::Token,
+                Some(other) => self.problem(
+                    "rate_limit.policies",
+                    "",
+                    format!("{name}: unknown key {other:?}, expected ip, user or token"),
+                ),
+            }
+        }
+
+        config
+    }
+}
+
+impl I18nLayer {
+    fn build(self) -> I18nConfig {
+        I18nConfig {
+            dir: self.dir,
+            hot_reload: self.hot_reload.unwrap_or(false),
+        }
+    }
+}
+
+impl CorsPolicyLayer {
+    /// Fills unset fields from `base`. Problems are returned per field.
+    fn apply(self, base: &CorsPolicy, errors: &mut Vec<(&'static str, String)>) -> CorsPolicy {
+        let allowed_origins = match self.allowed_origins {
+            Some(origins) => cors_origins(origins, errors),
+            None => base.allowed_origins.clone(),
+        };
+
+        let allowed_methods = match self.allowed_methods {
+            Some(methods) => methods
+                .into_iter()
+                .filter_map(|method| {
+                    let method = method.to_uppercase();
+                    match axum::http::Method::from_bytes(method.as_bytes()) {
+                        Ok(_) => Some(method),
+                        Err(_) => {
+                            errors.push((
+                                "allowed_methods",
+                                format!("{method:?} is not an HTTP method"),
+                            ));
+                            None
+                        }
+                    }
+                })
+                .collect(),
+            None => base.allowed_methods.clone(),
+        };
+
+        let allowed_headers = match self.allowed_headers {
+            Some(headers) => headers
+                .into_iter()
+                .filter_map(|header| {
+                    let header = header.to_lowercase();
+                    match axum::http::HeaderName::from_bytes(header.as_bytes()) {
+                        Ok(_) => Some(header),
+                        Err(_) => {
+                            errors.push((
+                                "allowed_headers",
+                                format!("{header:?} is not a header name"),
+                            ));
+                            None
+                        }
+                    }
+                })
+                .collect(),
+            None => base.allowed_headers.clone(),
+        };
+
+        let allow_credentials = self.allow_credentials.unwrap_or(base.allow_credentials);
+        if allow_credentials && allowed_origins.iter().any(|origin| origin == "*") {
+            errors.push((
+                "allow_credentials",
+                "can't be combined with the \"*\" origin; list the origins instead".to_string(),
+            ));
+        }
+
+        CorsPolicy {
+            allowed_origins,
+            allowed_methods,
+            allowed_headers,
+            allow_credentials,
+            max_age_secs: self.max_age_secs.unwrap_or(base.max_age_secs),
+        }
+    }
+}
+
+/// HKDF-SHA256 subkey of `secret` for one purpose, named by `label`.
+fn derive_secret(secret: &str, label: &[u8]) -> String {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, secret.as_bytes())
+        .expand(label, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+
+    URL_SAFE_NO_PAD.encode(key)
+}
+
+/// Origins are compared verbatim by browsers, so they are reduced to
+/// `scheme://host[:port]`. `*` admits any origin and stands alone.
+fn cors_origins(origins: Vec<String>, errors: &mut Vec<(&'static str, String)>) -> Vec<String> {
+    if origins.iter().any(|origin| origin == "*") {
+        if origins.len() > 1 {
+            errors.push((
+                "allowed_origins",
+                "\"*\" can't be combined with other origins".to_string(),
+            ));
+        }
+        return vec!["*".to_string()];
+    }
+
+    origins
+        .into_iter()
+        .filter_map(|origin| match reqwest::Url::parse(&origin) {
+            Ok(url)
+                if matches!(url.scheme(), "http" | "https")
+                    && url.path() == "/"
+                    && url.query().is_none() =>
+            {
+                Some(url.origin().ascii_serialization())
+            }
+            _ => {
+                errors.push((
+                    "allowed_origins",
+                    format!("{origin:?} is not an origin like https://example.com"),
+                ));
+                None
+            }
+        })
+        .collect()
+}
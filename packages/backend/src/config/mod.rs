@@ -0,0 +1,464 @@
+mod layer;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Application configuration. Loaded from an optional TOML file and then
+/// environment variables, which take precedence; see `docs/configuration.md`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    pub port: u16,
+    /// How long shutdown waits for in-flight requests and background work.
+    pub shutdown_timeout_secs: u64,
+    pub database_url: String,
+    pub app_name: String,
+    pub jwt_secret: String,
+    /// Sign-in with Google; disabled when not configured.
+    pub google: Option<GoogleConfig>,
+    /// Sign-in with Discord; disabled when not configured.
+    pub discord: Option<DiscordConfig>,
+    pub mail: MailConfig,
+    /// Avatar mirroring; disabled when not configured.
+    pub storage: Option<StorageConfig>,
+    pub i18n: I18nConfig,
+    pub metrics: MetricsConfig,
+    pub health: HealthConfig,
+    pub cors: CorsConfig,
+    pub rate_limit: RateLimitConfig,
+    pub frontend_url: String,
+    pub backend_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoogleConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub endpoints: GoogleEndpoints,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscordConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub endpoints: DiscordEndpoints,
+}
+
+/// Google OpenID Connect endpoints. Overridable so the OAuth flow can run
+/// against a mock identity provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoogleEndpoints {
+    pub auth_url: String,
+    pub token_url: String,
+    pub jwks_url: String,
+    pub issuers: Vec<String>,
+}
+
+impl Default for GoogleEndpoints {
+    fn default() -> Self {
+        Self {
+            auth_url: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+            token_url: "https://www.googleapis.com/oauth2/v4/token".to_string(),
+            jwks_url: "https://www.googleapis.com/oauth2/v3/certs".to_string(),
+            issuers: vec![
+                "https://accounts.google.com".to_string(),
+                "accounts.google.com".to_string(),
+            ],
+        }
+    }
+}
+
+/// Discord OAuth2 endpoints. Overridable so the OAuth flow can run against a
+/// mock identity provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscordEndpoints {
+    pub auth_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    pub cdn_url: String,
+}
+
+impl Default for DiscordEndpoints {
+    fn default() -> Self {
+        Self {
+            auth_url: "https://discord.com/api/oauth2/authorize".to_string(),
+            token_url: "https://discord.com/api/oauth2/token".to_string(),
+            userinfo_url: "https://discord.com/api/users/@me".to_string(),
+            cdn_url: "https://cdn.discordapp.com".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid config file {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Invalid configuration:{}", ProblemList(.0))]
+    Invalid(Vec<ConfigProblem>),
+}
+
+/// One invalid or missing setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// Dotted key in the config file, e.g. `google.client_secret`.
+    pub key: &'static str,
+    /// The environment variable that sets the same value, or empty for
+    /// settings only the file can hold.
+    pub env: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.env.is_empty() {
+            write!(f, "{}: {}", self.key, self.message)
+        } else {
+            write!(f, "{} ({}): {}", self.key, self.env, self.message)
+        }
+    }
+}
+
+struct ProblemList<'a>(&'a [ConfigProblem]);
+
+impl fmt::Display for ProblemList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for problem in self.0 {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailConfig {
+    pub from_email: String,
+    pub from_name: String,
+    pub transport: MailTransportConfig,
+    pub dkim: Option<DkimConfig>,
+    /// Key for signing unsubscribe links.
+    pub unsubscribe_secret: String,
+    /// One-click unsubscribe endpoint; the signed token is appended.
+    pub unsubscribe_url: String,
+}
+
+/// DKIM signing of outgoing mail. The public key has to be published at
+/// `{selector}._domainkey.{domain}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DkimConfig {
+    pub domain: String,
+    pub selector: String,
+    /// PEM file with a PKCS#1 RSA key, or a base64 Ed25519 key.
+    pub private_key_file: String,
+    pub algorithm: DkimAlgorithm,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DkimAlgorithm {
+    Rsa,
+    Ed25519,
+}
+
+/// How outgoing mail leaves the process, selected with `mail.transport`, or
+/// SMTP when only `mail.smtp.host` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MailTransportConfig {
+    Smtp(SmtpConfig),
+    /// One `.eml` file per message in `dir`.
+    File {
+        dir: String,
+    },
+    /// A maildir at `dir`, readable by most mail clients.
+    Maildir {
+        dir: String,
+    },
+    Stdout,
+    /// Keeps messages in memory; for tests.
+    Memory,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS, usually on port 587.
+    StartTls,
+    /// Implicit TLS, usually on port 465.
+    Tls,
+    /// Unencrypted, for local mail catchers only.
+    None,
+}
+
+/// Translation catalogs. The bundled `.ftl` files are always loaded; files in
+/// `dir` add locales or override bundled messages.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct I18nConfig {
+    pub dir: Option<String>,
+    /// Reload `dir` when a file changes. Meant for translators working
+    /// locally.
+    pub hot_reload: bool,
+}
+
+/// Local media storage. Files under `media_dir` are served at `public_url`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    pub media_dir: String,
+    pub public_url: String,
+}
+
+/// The Prometheus endpoint at `/metrics`. It is only served when a token is
+/// set, and scrapers must send it as `Authorization: Bearer <token>`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    pub token: Option<String>,
+}
+
+/// Extra checks run by `/readyz`. The database and migrations are always
+/// checked.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthConfig {
+    /// Also connect to the mail transport, e.g. the SMTP server.
+    pub check_mail: bool,
+}
+
+/// Which browser origins may call the API. `policy` applies to every path
+/// without a matching override.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsConfig {
+    pub policy: CorsPolicy,
+    pub overrides: Vec<CorsOverride>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsPolicy {
+    /// Origins such as `https://example.com`, or just `*` for any.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    /// Lets browsers include cookies and read credentialed responses. Never
+    /// combined with `*`.
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response.
+    pub max_age_secs: u64,
+}
+
+impl CorsPolicy {
+    /// The default policy for the given origins.
+    pub fn for_origins(allowed_origins: Vec<String>) -> Self {
+        Self {
+            allowed_origins,
+            allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE"]
+                .map(String::from)
+                .to_vec(),
+            allowed_headers: [
+                "authorization",
+                "content-type",
+                "accept-language",
+                "x-request-id",
+            ]
+            .map(String::from)
+            .to_vec(),
+            allow_credentials: false,
+            max_age_secs: 3600,
+        }
+    }
+}
+
+/// A different policy for `path` and everything below it, e.g. public
+/// feeds that any site may embed. The longest matching path wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsOverride {
+    pub path: String,
+    pub policy: CorsPolicy,
+}
+
+/// Request limits. Routes pick one of the named policies in `routes/`; the
+/// config can change their limits but not add names.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Off lets every request through, e.g. for load tests.
+    pub enabled: bool,
+    pub store: RateLimitStoreKind,
+    /// Take the client address from the last `X-Forwarded-For` entry. Only
+    /// safe behind a proxy that appends it.
+    pub trust_forwarded_for: bool,
+    /// Every name has an entry; see `RateLimitConfig::policy`.
+    pub policies: BTreeMap<RateLimitPolicyName, RateLimitPolicy>,
+}
+
+impl RateLimitConfig {
+    pub fn policy(&self, name: RateLimitPolicyName) -> &RateLimitPolicy {
+        &self.policies[&name]
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            store: RateLimitStoreKind::Memory,
+            trust_forwarded_for: false,
+            policies: RateLimitPolicyName::ALL
+                .into_iter()
+                .map(|name| (name, name.default_policy()))
+                .collect(),
+        }
+    }
+}
+
+/// Where buckets are kept. `postgres` shares them between instances.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitStoreKind {
+    Memory,
+    Postgres,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum RateLimitPolicyName {
+    /// Sign-in, registration and anything that sends mail or checks a
+    /// password.
+    AuthStrict,
+    /// Everything else.
+    ReadDefault,
+    /// Endpoints that accept files.
+    Upload,
+}
+
+impl RateLimitPolicyName {
+    pub const ALL: [Self; 3] = [Self::AuthStrict, Self::ReadDefault, Self::Upload];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AuthStrict => "auth-strict",
+            Self::ReadDefault => "read-default",
+            Self::Upload => "upload",
+        }
+    }
+
+    pub fn default_policy(self) -> RateLimitPolicy {
+        let (requests, per_secs, key) = match self {
+            Self::AuthStrict => (10, 60, RateLimitKey::Ip),
+            Self::ReadDefault => (300, 60, RateLimitKey::User),
+            Self::Upload => (20, 3600, RateLimitKey::User),
+        };
+
+        RateLimitPolicy {
+            requests,
+            per_secs,
+            key,
+        }
+    }
+}
+
+/// A token bucket: bursts of up to `requests`, refilled evenly over
+/// `per_secs`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimitPolicy {
+    pub requests: u32,
+    pub per_secs: u64,
+    pub key: RateLimitKey,
+}
+
+/// Who a bucket belongs to. `user` and `token` fall back to the client
+/// address for anonymous requests and invalid tokens.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitKey {
+    Ip,
+    User,
+    /// A valid bearer token, so each session or API token has its own
+    /// bucket.
+    Token,
+}
+
+impl Config {
+    /// Loads `CONFIG_FILE`, or `config.toml` if it exists, and overlays the
+    /// process environment.
+    pub fn load() -> Result<Self, ConfigError> {
+        let file = read_config_file()?;
+
+        Self::from_sources(
+            file.as_ref()
+                .map(|(path, contents)| (path.as_path(), contents.as_str())),
+            &std::env::vars().collect(),
+        )
+    }
+
+    /// Only the `[i18n]` section, from the same sources as `load`, for tools
+    /// that run without the rest of the configuration.
+    pub fn load_i18n() -> Result<I18nConfig, ConfigError> {
+        let file = read_config_file()?;
+        let mut layer = parse_layer(
+            file.as_ref()
+                .map(|(path, contents)| (path.as_path(), contents.as_str())),
+        )?;
+
+        // Problems elsewhere in the configuration don't concern the caller.
+        layer.overlay_env(&std::env::vars().collect(), &mut Vec::new());
+        Ok(layer.i18n())
+    }
+
+    /// Builds the configuration from a config file's contents and a set of
+    /// environment variables. Every problem is reported, not just the first.
+    pub fn from_sources(
+        file: Option<(&Path, &str)>,
+        env: &HashMap<String, String>,
+    ) -> Result<Self, ConfigError> {
+        let mut layer = parse_layer(file)?;
+
+        let mut problems = Vec::new();
+        layer.overlay_env(env, &mut problems);
+        let config = layer.build(&mut problems);
+
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+fn read_config_file() -> Result<Option<(PathBuf, String)>, ConfigError> {
+    let path = match std::env::var_os("CONFIG_FILE") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(PathBuf::from("config.toml")).filter(|path| path.exists()),
+    };
+
+    path.map(|path| match std::fs::read_to_string(&path) {
+        Ok(contents) => Ok((path, contents)),
+        Err(source) => Err(ConfigError::Read { path, source }),
+    })
+    .transpose()
+}
+
+fn parse_layer(file: Option<(&Path, &str)>) -> Result<layer::Layer, ConfigError> {
+    match file {
+        Some((path, contents)) => toml::from_str(contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        }),
+        None => Ok(layer::Layer::default()),
+    }
+}
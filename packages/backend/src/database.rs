@@ -1,6 +1,10 @@
+use sqlx::migrate::Migrator;
 use sqlx::{postgres::PgPoolOptions, PgPool};
+use std::collections::HashSet;
 use std::time::Duration;
 
+static MIGRATOR: Migrator = sqlx::migrate!();
+
 #[derive(Debug, Clone)]
 pub struct Database {
     pub pool: PgPool,
@@ -14,7 +18,7 @@ impl Database {
             .connect(database_url)
             .await?;
 
-        sqlx::migrate!().run(&pool).await?;
+        MIGRATOR.run(&pool).await?;
 
         Ok(Database { pool })
     }
@@ -22,4 +26,22 @@ impl Database {
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
+
+    /// Versions of migrations built into this binary that the database has
+    /// not applied successfully.
+    pub async fn pending_migrations(&self) -> Result<Vec<i64>, sqlx::Error> {
+        let applied: HashSet<i64> =
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .collect();
+
+        Ok(MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| migration.version)
+            .filter(|version| !applied.contains(version))
+            .collect())
+    }
 }
@@ -1,32 +1,133 @@
+use crate::i18n::{I18n, LocaleScope};
+use crate::middleware::RequestId;
 use axum::{
-    http::StatusCode,
+    http::{
+        header::{CONTENT_TYPE, RETRY_AFTER},
+        StatusCode,
+    },
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use fluent_bundle::{FluentArgs, FluentValue};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt;
 use thiserror::Error;
+use utoipa::ToSchema;
 
 pub type Result<T> = std::result::Result<T, AppError>;
 
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// A user-facing message: a Fluent message id plus its arguments. It is
+/// rendered when the response is built, in the request's negotiated locale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub id: Cow<'static, str>,
+    pub args: Vec<(Cow<'static, str>, MessageArg)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageArg {
+    Text(String),
+    Number(f64),
+}
+
+impl Message {
+    pub fn new(id: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            id: id.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, name: impl Into<Cow<'static, str>>, value: impl Into<MessageArg>) -> Self {
+        self.args.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn render(&self, i18n: &I18n, locale: &str) -> String {
+        let mut args = FluentArgs::new();
+        for (name, value) in &self.args {
+            match value {
+                MessageArg::Text(text) => args.set(name.clone(), FluentValue::from(text.clone())),
+                MessageArg::Number(number) => args.set(name.clone(), FluentValue::from(*number)),
+            }
+        }
+
+        i18n.get_message(locale, &self.id, Some(&args))
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(id: &'static str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<String> for MessageArg {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for MessageArg {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<i64> for MessageArg {
+    fn from(value: i64) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl From<f64> for MessageArg {
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+/// A validation failure for one request field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: Message,
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
     #[error("Validation error: {0}")]
-    Validation(String),
+    Validation(Message),
+
+    #[error("Invalid fields: {}", field_names(.0))]
+    InvalidFields(Vec<FieldError>),
 
     #[error("Authentication error: {0}")]
-    Authentication(String),
+    Authentication(Message),
 
     #[error("Authorization error: {0}")]
-    Authorization(String),
+    Authorization(Message),
 
     #[error("Not found: {0}")]
-    NotFound(String),
+    NotFound(Message),
 
     #[error("Conflict: {0}")]
-    Conflict(String),
+    Conflict(Message),
+
+    /// Carries how many seconds until the request would be allowed.
+    #[error("Rate limited for {0}s")]
+    RateLimited(u64),
 
     #[error("JWT error: {0}")]
     Jwt(#[from] jsonwebtoken::errors::Error),
@@ -40,6 +141,7 @@ pub enum AppError {
     #[error("HTTP client error: {0}")]
     HttpClient(#[from] reqwest::Error),
 
+    /// Details are for the logs; users only see that the login failed.
     #[error("OAuth error: {0}")]
     OAuth(String),
 
@@ -47,6 +149,14 @@ pub enum AppError {
     Internal(#[from] anyhow::Error),
 }
 
+fn field_names(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{} ({})", error.field, error.message))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl From<lettre::transport::smtp::Error> for AppError {
     fn from(err: lettre::transport::smtp::Error) -> Self {
         AppError::Email(lettre::error::Error::from(std::io::Error::other(
@@ -55,34 +165,191 @@ impl From<lettre::transport::smtp::Error> for AppError {
     }
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Database(ref err) => {
+impl AppError {
+    /// The stable code clients branch on. See `docs/errors.md`.
+    pub fn code(&self) -> &'static str {
+        let message = match self {
+            AppError::Validation(message)
+            | AppError::Authentication(message)
+            | AppError::Authorization(message)
+            | AppError::NotFound(message)
+            | AppError::Conflict(message) => Some(message),
+            _ => None,
+        };
+
+        if let Some(code) = message.and_then(|message| message_code(&message.id)) {
+            return code;
+        }
+
+        match self {
+            AppError::Validation(_) => "request.invalid",
+            AppError::InvalidFields(_) => "request.invalid_fields",
+            AppError::Authentication(_) => "auth.unauthenticated",
+            AppError::Authorization(_) => "auth.forbidden",
+            AppError::NotFound(_) => "request.not_found",
+            AppError::Conflict(_) => "request.conflict",
+            AppError::RateLimited(_) => "request.rate_limited",
+            AppError::Jwt(_) => "auth.invalid_token",
+            AppError::OAuth(_) => "oauth.failed",
+            AppError::Email(_) => "server.email_delivery",
+            AppError::HttpClient(_) => "server.upstream",
+            AppError::Database(_) | AppError::Bcrypt(_) | AppError::Internal(_) => {
+                "server.internal"
+            }
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) | AppError::InvalidFields(_) | AppError::OAuth(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            AppError::Authentication(_) | AppError::Jwt(_) => StatusCode::UNAUTHORIZED,
+            AppError::Authorization(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Database(_)
+            | AppError::Bcrypt(_)
+            | AppError::Email(_)
+            | AppError::HttpClient(_)
+            | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// What users are told. Server-side failures are logged here and only
+    /// described generically.
+    fn message(&self) -> Message {
+        match self {
+            AppError::Validation(message)
+            | AppError::Authentication(message)
+            | AppError::Authorization(message)
+            | AppError::NotFound(message)
+            | AppError::Conflict(message) => message.clone(),
+            AppError::InvalidFields(_) => Message::new("validation-failed"),
+            AppError::RateLimited(retry_after) => {
+                Message::new("rate-limited").arg("seconds", *retry_after as i64)
+            }
+            AppError::Jwt(_) => Message::new("invalid-token"),
+            AppError::OAuth(detail) => {
+                tracing::warn!("OAuth error: {}", detail);
+                Message::new("oauth-failed")
+            }
+            AppError::Database(err) => {
                 tracing::error!("Database error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+                Message::new("error-database")
             }
-            AppError::Validation(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
-            AppError::Authentication(ref msg) => (StatusCode::UNAUTHORIZED, msg.as_str()),
-            AppError::Authorization(ref msg) => (StatusCode::FORBIDDEN, msg.as_str()),
-            AppError::NotFound(ref msg) => (StatusCode::NOT_FOUND, msg.as_str()),
-            AppError::Conflict(ref msg) => (StatusCode::CONFLICT, msg.as_str()),
-            AppError::Jwt(_) => (StatusCode::UNAUTHORIZED, "Invalid token"),
-            AppError::Bcrypt(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Password hashing error"),
-            AppError::Email(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Email sending error"),
-            AppError::HttpClient(_) => (StatusCode::INTERNAL_SERVER_ERROR, "HTTP client error"),
-            AppError::OAuth(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
-            AppError::Internal(ref err) => {
+            AppError::Bcrypt(_) => Message::new("error-password-hashing"),
+            AppError::Email(_) => Message::new("error-email-delivery"),
+            AppError::HttpClient(_) => Message::new("error-http-client"),
+            AppError::Internal(err) => {
                 tracing::error!("Internal error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                Message::new("error-internal")
             }
+        }
+    }
+}
+
+/// Stable codes for messages that identify one specific failure. Keep
+/// `docs/errors.md` in sync when adding one; codes must never change.
+fn message_code(id: &str) -> Option<&'static str> {
+    Some(match id {
+        "missing-authorization" => "auth.missing_token",
+        "invalid-token" => "auth.invalid_token",
+        "invalid-credentials" => "auth.invalid_credentials",
+        "account-not-verified" => "auth.email_not_verified",
+        "oauth-login-required" => "auth.oauth_account",
+        "oauth-password-reset-not-allowed" => "auth.oauth_password_reset",
+        "admin-required" => "auth.admin_required",
+        "account-has-no-password" => "oauth.no_password",
+        "oauth-account-conflict" => "oauth.identity_taken",
+        "oauth-provider-disabled" => "oauth.provider_disabled",
+        "email-already-exists" => "user.email_taken",
+        "username-already-exists" => "user.username_taken",
+        "user-not-found" => "user.not_found",
+        "email-already-verified" => "user.already_verified",
+        "unsupported-locale" => "user.unsupported_locale",
+        "invalid-timezone" => "user.invalid_timezone",
+        "email-category-locked" => "email.category_locked",
+        "invalid-unsubscribe-link" => "email.invalid_unsubscribe_link",
+        "suppression-not-found" => "email.suppression_not_found",
+        "rate-limit-exemption-not-found" => "rate_limit.exemption_not_found",
+        "outbox-email-not-found" => "email.not_found",
+        "outbox-retry-not-failed" => "email.not_retryable",
+        "password-required" => "validation.password_required",
+        "password-too-short" => "validation.password_too_short",
+        "invalid-email" | "validation-email" => "validation.invalid_email",
+        "username-length" => "validation.username_length",
+        "digest-day-range" => "validation.day_out_of_range",
+        "suppression-reason-length" | "exemption-reason-length" => "validation.reason_length",
+        "validation-length" => "validation.length",
+        "validation-range" => "validation.range",
+        "validation-required" => "validation.required",
+        "validation-type" => "validation.invalid_type",
+        "validation-invalid" => "validation.invalid",
+        "malformed-json" => "request.malformed_json",
+        "json-content-type-required" => "request.json_required",
+        _ => return None,
+    })
+}
+
+/// An RFC 9457 (formerly 7807) problem document. See `docs/errors.md`.
+#[derive(Serialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: &'static str,
+    pub status: u16,
+    pub code: &'static str,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldProblem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FieldProblem {
+    pub field: String,
+    pub code: &'static str,
+    pub detail: String,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let (i18n, locale) = LocaleScope::current();
+        let render = |message: &Message| message.render(&i18n, locale.as_str());
+
+        let errors = match &self {
+            AppError::InvalidFields(errors) => errors
+                .iter()
+                .map(|error| FieldProblem {
+                    field: error.field.clone(),
+                    code: message_code(&error.message.id).unwrap_or("validation.invalid"),
+                    detail: render(&error.message),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let problem = ProblemDetails {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            code: self.code(),
+            detail: render(&self.message()),
+            errors,
+            request_id: RequestId::current().map(|id| id.to_string()),
         };
 
-        let body = Json(json!({
-            "error": error_message,
-            "status": status.as_u16()
-        }));
+        let mut response = (status, [(CONTENT_TYPE, PROBLEM_JSON)], Json(problem)).into_response();
+        if let AppError::RateLimited(retry_after) = self {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after.to_string().parse().unwrap());
+        }
 
-        (status, body).into_response()
+        response
     }
 }
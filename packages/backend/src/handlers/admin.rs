@@ -0,0 +1,258 @@
+use crate::error::{AppError, Message, ProblemDetails, Result};
+use crate::models::{
+    CreateRateLimitExemptionRequest, CreateSuppressionRequest, EmailSuppression, OutboxEmail,
+    OutboxQuery, RateLimitExemption, RateLimitExemptionQuery, SuppressionQuery, User,
+};
+use crate::state::AppState;
+use crate::utils::{validate_request, Json};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension,
+};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/emails",
+    tag = "admin",
+    params(OutboxQuery),
+    responses(
+        (status = 200, description = "Outbox emails, newest first", body = Vec<OutboxEmail>),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_emails(
+    State(app_state): State<AppState>,
+    Query(query): Query<OutboxQuery>,
+) -> Result<impl IntoResponse> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let emails = app_state
+        .email_outbox
+        .list(query.status, limit, offset)
+        .await?;
+
+    Ok(Json(emails))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/emails/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Outbox email id")),
+    responses(
+        (status = 200, description = "The outbox email", body = OutboxEmail),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such outbox email", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_email(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    let email = app_state
+        .email_outbox
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(Message::new("outbox-email-not-found")))?;
+
+    Ok(Json(email))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/emails/{id}/retry",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Outbox email id")),
+    responses(
+        (status = 200, description = "The email, queued again", body = OutboxEmail),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such outbox email", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Only failed emails can be retried", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn retry_email(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    let email = app_state.email_outbox.retry(id).await?;
+
+    Ok(Json(email))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/email-suppressions",
+    tag = "admin",
+    params(SuppressionQuery),
+    responses(
+        (status = 200, description = "Suppressed addresses", body = Vec<EmailSuppression>),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_suppressions(
+    State(app_state): State<AppState>,
+    Query(query): Query<SuppressionQuery>,
+) -> Result<impl IntoResponse> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let suppressions = app_state
+        .email_preferences
+        .list_suppressions(limit, offset)
+        .await?;
+
+    Ok(Json(suppressions))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/email-suppressions",
+    tag = "admin",
+    request_body = CreateSuppressionRequest,
+    responses(
+        (status = 201, description = "Address suppressed", body = EmailSuppression),
+        (status = 400, description = "Invalid fields", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_suppression(
+    State(app_state): State<AppState>,
+    Json(request): Json<CreateSuppressionRequest>,
+) -> Result<impl IntoResponse> {
+    validate_request(&request)?;
+
+    let suppression = app_state
+        .email_preferences
+        .suppress(&request.email, &request.reason)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(suppression)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/email-suppressions/{email}",
+    tag = "admin",
+    params(("email" = String, Path, description = "Suppressed address")),
+    responses(
+        (status = 204, description = "Address removed from the list"),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Address is not suppressed", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_suppression(
+    State(app_state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse> {
+    app_state.email_preferences.unsuppress(&email).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/rate-limit-exemptions",
+    tag = "admin",
+    params(RateLimitExemptionQuery),
+    responses(
+        (status = 200, description = "Users exempt from rate limits", body = Vec<RateLimitExemption>),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_rate_limit_exemptions(
+    State(app_state): State<AppState>,
+    Query(query): Query<RateLimitExemptionQuery>,
+) -> Result<impl IntoResponse> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let exemptions = app_state
+        .rate_limiter
+        .exemptions()
+        .list(limit, offset)
+        .await?;
+
+    Ok(Json(exemptions))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/rate-limit-exemptions",
+    tag = "admin",
+    request_body = CreateRateLimitExemptionRequest,
+    responses(
+        (status = 201, description = "User exempted", body = RateLimitExemption),
+        (status = 400, description = "Invalid fields", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such user", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_rate_limit_exemption(
+    State(app_state): State<AppState>,
+    Extension(admin): Extension<User>,
+    Json(request): Json<CreateRateLimitExemptionRequest>,
+) -> Result<impl IntoResponse> {
+    validate_request(&request)?;
+
+    let exemption = app_state
+        .rate_limiter
+        .exemptions()
+        .add(request.user_id, &request.reason, admin.id)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(exemption)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/rate-limit-exemptions/{user_id}",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "Exempt user")),
+    responses(
+        (status = 204, description = "Exemption removed"),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not an administrator", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User is not exempt", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_rate_limit_exemption(
+    State(app_state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    app_state.rate_limiter.exemptions().remove(user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
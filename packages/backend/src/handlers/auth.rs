@@ -1,202 +1,548 @@
-use crate::error::{AppError, Result};
+use crate::error::{AppError, ProblemDetails, Result};
+use crate::i18n::Locale;
 use crate::models::{
-    ForgotPasswordRequest, LoginRequest, OAuthCallbackQuery, RegisterRequest, ResetPasswordRequest,
-    User, UserResponse, VerifyEmailRequest,
+    AuthProvidersResponse, AuthResponse, ForgotPasswordRequest, LinkTokenRequest,
+    LinkWithPasswordRequest, LoginRequest, MessageResponse, OAuthAuthorization, OAuthCallbackQuery,
+    OAuthExchangeRequest, OAuthLoginOutcome, PendingLinkResponse, RegisterRequest,
+    RegisterResponse, ResendVerificationRequest, ResetPasswordRequest, UpdateLocaleRequest, User,
+    UserResponse, VerifyEmailRequest,
 };
-use crate::routes::auth::AppState;
+use crate::state::AppState;
+use crate::telemetry::record_login;
+use crate::utils::Json;
 use axum::{
     extract::{Extension, Query, State},
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Redirect},
-    Json,
 };
-use serde_json::json;
 
-fn get_locale_from_headers(headers: &HeaderMap) -> String {
-    headers
-        .get("accept-language")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|lang| lang.split(',').next())
-        .and_then(|lang| lang.split('-').next())
-        .map(|lang| match lang {
-            "tr" => "tr".to_string(),
-            _ => "en".to_string(),
-        })
-        .unwrap_or_else(|| "en".to_string())
-}
+/// Holds the hash of the pending OAuth `state` for the browser that started
+/// the flow.
+const OAUTH_BINDING_COOKIE: &str = "oauth_binding";
+/// Matches the lifetime of a stored state.
+const OAUTH_BINDING_MAX_AGE_SECS: u64 = 600;
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/register",
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 201, description = "Account created; a verification email was sent", body = RegisterResponse),
+        (status = 400, description = "Invalid fields", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Email or username taken", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn register(
     State(app_state): State<AppState>,
-    headers: HeaderMap,
+    locale: Locale,
     Json(request): Json<RegisterRequest>,
 ) -> Result<impl IntoResponse> {
-    let locale = get_locale_from_headers(&headers);
-    let user = app_state.auth_service.register(request, &locale).await?;
+    let user = app_state
+        .auth_service
+        .register(request, locale.as_str())
+        .await?;
 
     Ok((
         StatusCode::CREATED,
-        Json(json!({
-            "message": "Registration successful. Please check your email to verify your account.",
-            "user": user
-        })),
+        Json(RegisterResponse {
+            message: "Registration successful. Please check your email to verify your account."
+                .to_string(),
+            user,
+        }),
     ))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Signed in", body = AuthResponse),
+        (status = 400, description = "Invalid fields", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Wrong credentials or unverified email", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn login(
     State(app_state): State<AppState>,
-    headers: HeaderMap,
     Json(request): Json<LoginRequest>,
 ) -> Result<impl IntoResponse> {
-    let locale = get_locale_from_headers(&headers);
-    let response = app_state.auth_service.login(request, &locale).await?;
+    let result = app_state.auth_service.login(request).await;
+    record_login(
+        "password",
+        if result.is_ok() { "success" } else { "failure" },
+    );
 
-    Ok(Json(response))
+    Ok(Json(result?))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/verify-email",
+    tag = "auth",
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 200, description = "Email verified", body = MessageResponse),
+        (status = 401, description = "Invalid or expired token", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn verify_email(
     State(app_state): State<AppState>,
-    headers: HeaderMap,
     Json(request): Json<VerifyEmailRequest>,
 ) -> Result<impl IntoResponse> {
-    let locale = get_locale_from_headers(&headers);
-    app_state
-        .auth_service
-        .verify_email(&request.token, &locale)
-        .await?;
+    app_state.auth_service.verify_email(&request.token).await?;
 
-    Ok(Json(json!({
-        "message": "Email verified successfully"
-    })))
+    Ok(Json(MessageResponse::new("Email verified successfully")))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/resend-verification",
+    tag = "auth",
+    request_body = ResendVerificationRequest,
+    responses(
+        (status = 200, description = "Verification email queued", body = MessageResponse),
+        (status = 400, description = "Already verified", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such user", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn resend_verification(
     State(app_state): State<AppState>,
-    headers: HeaderMap,
-    Json(request): Json<serde_json::Value>,
+    Json(request): Json<ResendVerificationRequest>,
 ) -> Result<impl IntoResponse> {
-    let locale = get_locale_from_headers(&headers);
-    let email = request["email"]
-        .as_str()
-        .ok_or_else(|| AppError::Validation("Email is required".to_string()))?;
-
     app_state
         .auth_service
-        .resend_verification(email, &locale)
+        .resend_verification(&request.email)
         .await?;
 
-    Ok(Json(json!({
-        "message": "Verification email resent"
-    })))
+    Ok(Json(MessageResponse::new("Verification email resent")))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/forgot-password",
+    tag = "auth",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (status = 200, description = "Reset email queued if the account exists", body = MessageResponse),
+        (status = 400, description = "Invalid fields or OAuth account", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn forgot_password(
     State(app_state): State<AppState>,
-    headers: HeaderMap,
     Json(request): Json<ForgotPasswordRequest>,
 ) -> Result<impl IntoResponse> {
-    let locale = get_locale_from_headers(&headers);
     app_state
         .auth_service
-        .forgot_password(&request.email, &locale)
+        .forgot_password(&request.email)
         .await?;
 
-    Ok(Json(json!({
-        "message": "Password reset email sent if account exists"
-    })))
+    Ok(Json(MessageResponse::new(
+        "Password reset email sent if account exists",
+    )))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/reset-password",
+    tag = "auth",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password changed", body = MessageResponse),
+        (status = 400, description = "Password too short", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Invalid or expired token", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn reset_password(
     State(app_state): State<AppState>,
-    headers: HeaderMap,
     Json(request): Json<ResetPasswordRequest>,
 ) -> Result<impl IntoResponse> {
-    let locale = get_locale_from_headers(&headers);
     app_state
         .auth_service
-        .reset_password(&request.token, &request.new_password, &locale)
+        .reset_password(&request.token, &request.new_password)
         .await?;
 
-    Ok(Json(json!({
-        "message": "Password reset successful"
-    })))
+    Ok(Json(MessageResponse::new("Password reset successful")))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/me",
+    tag = "auth",
+    responses(
+        (status = 200, description = "The signed-in user", body = UserResponse),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn me(Extension(user): Extension<User>) -> Result<impl IntoResponse> {
     let user_response: UserResponse = user.into();
     Ok(Json(user_response))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/update-locale",
+    tag = "auth",
+    request_body = UpdateLocaleRequest,
+    responses(
+        (status = 200, description = "Locale updated", body = UserResponse),
+        (status = 400, description = "Unsupported locale", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn update_locale(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
-    Json(request): Json<serde_json::Value>,
+    Json(request): Json<UpdateLocaleRequest>,
 ) -> Result<impl IntoResponse> {
-    let locale = request["locale"]
-        .as_str()
-        .ok_or_else(|| AppError::Validation("Locale is required".to_string()))?;
-
     let updated_user = app_state
         .auth_service
-        .update_locale(user.id, locale)
+        .update_locale(user.id, &request.locale)
         .await?;
 
     Ok(Json(updated_user))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/providers",
+    tag = "auth",
+    responses(
+        (status = 200, description = "OAuth providers that can be used to sign in", body = AuthProvidersResponse),
+    ),
+)]
+pub async fn providers(State(app_state): State<AppState>) -> Json<AuthProvidersResponse> {
+    Json(AuthProvidersResponse {
+        google: app_state.oauth_service.google_enabled(),
+        discord: app_state.oauth_service.discord_enabled(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/google",
+    tag = "auth",
+    responses(
+        (status = 307, description = "Redirect to Google; sets the `oauth_binding` cookie the callback requires"),
+        (status = 404, description = "Google sign-in is not configured", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn google_auth(State(app_state): State<AppState>) -> Result<impl IntoResponse> {
-    let (auth_url, _csrf_token) = app_state.oauth_service.get_google_auth_url();
-    Ok(Redirect::temporary(&auth_url))
+    let authorization = app_state.oauth_service.get_google_auth_url().await?;
+    Ok(oauth_authorize_redirect(&app_state, authorization))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/google/callback",
+    tag = "auth",
+    params(OAuthCallbackQuery),
+    responses(
+        (status = 307, description = "Redirect to the frontend with an exchange code, link code or error code"),
+    ),
+)]
 pub async fn google_callback(
     State(app_state): State<AppState>,
+    locale: Locale,
     headers: HeaderMap,
     Query(params): Query<OAuthCallbackQuery>,
-) -> Result<impl IntoResponse> {
-    let locale = get_locale_from_headers(&headers);
-    let auth_response = app_state
-        .oauth_service
-        .handle_google_callback(&params.code, &locale)
-        .await?;
+) -> impl IntoResponse {
+    let result = match oauth_callback_code(&params) {
+        Ok(code) => app_state
+            .oauth_service
+            .handle_google_callback(
+                code,
+                params.state.as_deref(),
+                oauth_binding(&headers),
+                locale.as_str(),
+            )
+            .await
+            .map_err(|e| oauth_error_code("Google", &e)),
+        Err(error_code) => Err(error_code),
+    };
+    record_oauth_login("google", &result);
 
-    let redirect_url = format!(
-        "{}/auth/callback?token={}&user={}",
-        app_state.config.frontend_url,
-        auth_response.token,
-        serde_json::to_string(&auth_response.user).unwrap_or_default()
-    );
-
-    Ok(Redirect::temporary(&redirect_url))
+    (
+        [(header::SET_COOKIE, oauth_binding_cookie(&app_state, "", 0))],
+        oauth_redirect(&app_state.config.frontend_url, result),
+    )
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/discord",
+    tag = "auth",
+    responses(
+        (status = 307, description = "Redirect to Discord; sets the `oauth_binding` cookie the callback requires"),
+        (status = 404, description = "Discord sign-in is not configured", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn discord_auth(State(app_state): State<AppState>) -> Result<impl IntoResponse> {
-    let (auth_url, _csrf_token) = app_state.oauth_service.get_discord_auth_url();
-    Ok(Redirect::temporary(&auth_url))
+    let authorization = app_state.oauth_service.get_discord_auth_url().await?;
+    Ok(oauth_authorize_redirect(&app_state, authorization))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/discord/callback",
+    tag = "auth",
+    params(OAuthCallbackQuery),
+    responses(
+        (status = 307, description = "Redirect to the frontend with an exchange code, link code or error code"),
+    ),
+)]
 pub async fn discord_callback(
     State(app_state): State<AppState>,
+    locale: Locale,
     headers: HeaderMap,
     Query(params): Query<OAuthCallbackQuery>,
+) -> impl IntoResponse {
+    let result = match oauth_callback_code(&params) {
+        Ok(code) => app_state
+            .oauth_service
+            .handle_discord_callback(
+                code,
+                params.state.as_deref(),
+                oauth_binding(&headers),
+                locale.as_str(),
+            )
+            .await
+            .map_err(|e| oauth_error_code("Discord", &e)),
+        Err(error_code) => Err(error_code),
+    };
+    record_oauth_login("discord", &result);
+
+    (
+        [(header::SET_COOKIE, oauth_binding_cookie(&app_state, "", 0))],
+        oauth_redirect(&app_state.config.frontend_url, result),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/oauth/exchange",
+    tag = "auth",
+    request_body = OAuthExchangeRequest,
+    responses(
+        (status = 200, description = "Signed in", body = AuthResponse),
+        (status = 401, description = "Invalid or expired code", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn oauth_exchange(
+    State(app_state): State<AppState>,
+    Json(request): Json<OAuthExchangeRequest>,
+) -> Result<impl IntoResponse> {
+    let response = app_state.oauth_service.exchange_code(&request.code).await?;
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/link/exchange",
+    tag = "auth",
+    request_body = OAuthExchangeRequest,
+    responses(
+        (status = 200, description = "The pending link request", body = PendingLinkResponse),
+        (status = 401, description = "Invalid or expired code", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn link_exchange(
+    State(app_state): State<AppState>,
+    Json(request): Json<OAuthExchangeRequest>,
 ) -> Result<impl IntoResponse> {
-    let locale = get_locale_from_headers(&headers);
-    let auth_response = app_state
+    let response = app_state
         .oauth_service
-        .handle_discord_callback(&params.code, &locale)
+        .exchange_link_code(&request.code)
         .await?;
 
-    let redirect_url = format!(
-        "{}/auth/callback?token={}&user={}",
-        app_state.config.frontend_url,
-        auth_response.token,
-        serde_json::to_string(&auth_response.user).unwrap_or_default()
-    );
+    Ok(Json(response))
+}
+
+/// Sends the browser to the provider, remembering which state it was given.
+fn oauth_authorize_redirect(
+    app_state: &AppState,
+    authorization: OAuthAuthorization,
+) -> impl IntoResponse {
+    (
+        [(
+            header::SET_COOKIE,
+            oauth_binding_cookie(
+                app_state,
+                &authorization.binding,
+                OAUTH_BINDING_MAX_AGE_SECS,
+            ),
+        )],
+        Redirect::temporary(&authorization.url),
+    )
+}
+
+/// `SameSite=Lax` rather than `Strict`: the callback is a cross-site
+/// navigation from the provider and must still carry the cookie.
+fn oauth_binding_cookie(app_state: &AppState, value: &str, max_age_secs: u64) -> String {
+    let secure = if app_state.config.backend_url.starts_with("https://") {
+        "; Secure"
+    } else {
+        ""
+    };
 
-    Ok(Redirect::temporary(&redirect_url))
+    format!(
+        "{OAUTH_BINDING_COOKIE}={value}; Max-Age={max_age_secs}; Path=/api/v1/auth; HttpOnly; SameSite=Lax{secure}"
+    )
+}
+
+fn oauth_binding(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == OAUTH_BINDING_COOKIE)
+        .map(|(_, value)| value)
+}
+
+/// Returns the authorization code, or the error code to show the user when
+/// the provider redirected back with an error instead.
+fn oauth_callback_code(params: &OAuthCallbackQuery) -> std::result::Result<&str, &'static str> {
+    if let Some(error) = &params.error {
+        tracing::warn!("OAuth provider returned error: {}", error);
+        return Err(match error.as_str() {
+            "access_denied" => "oauth-cancelled",
+            _ => "oauth-failed",
+        });
+    }
+
+    params.code.as_deref().ok_or("oauth-failed")
+}
+
+fn oauth_error_code(provider: &str, err: &AppError) -> &'static str {
+    tracing::error!("{} OAuth login failed: {}", provider, err);
+
+    match err {
+        AppError::Conflict(_) => "oauth-account-conflict",
+        _ => "oauth-failed",
+    }
+}
+
+fn record_oauth_login(
+    provider: &'static str,
+    result: &std::result::Result<OAuthLoginOutcome, &'static str>,
+) {
+    let outcome = match result {
+        Ok(OAuthLoginOutcome::Authenticated { .. }) => "success",
+        Ok(OAuthLoginOutcome::LinkRequired { .. }) => "link_required",
+        Err(_) => "failure",
+    };
+
+    record_login(provider, outcome);
+}
+
+/// Sends the browser back to the frontend. Failures carry an error code that
+/// the frontend translates; session and link tokens are never put in the
+/// URL, only one-time codes for them.
+fn oauth_redirect(
+    frontend_url: &str,
+    result: std::result::Result<OAuthLoginOutcome, &'static str>,
+) -> Redirect {
+    let (path, params) = match result {
+        Ok(OAuthLoginOutcome::Authenticated { exchange_code }) => {
+            ("/auth/callback", vec![("code", exchange_code)])
+        }
+        Ok(OAuthLoginOutcome::LinkRequired { link_code }) => {
+            ("/auth/link-account", vec![("code", link_code)])
+        }
+        Err(error_code) => ("/auth/callback", vec![("error", error_code.to_string())]),
+    };
+
+    let base_url = format!("{frontend_url}{path}");
+    let redirect_url = reqwest::Url::parse_with_params(&base_url, &params)
+        .map(|url| url.to_string())
+        .unwrap_or(base_url);
+
+    Redirect::temporary(&redirect_url)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/link/password",
+    tag = "auth",
+    request_body = LinkWithPasswordRequest,
+    responses(
+        (status = 200, description = "Identity linked and signed in", body = AuthResponse),
+        (status = 400, description = "The account has no password", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Wrong password or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn link_with_password(
+    State(app_state): State<AppState>,
+    Json(request): Json<LinkWithPasswordRequest>,
+) -> Result<impl IntoResponse> {
+    let response = app_state
+        .oauth_service
+        .link_with_password(&request.token, &request.password)
+        .await?;
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/link/email",
+    tag = "auth",
+    request_body = LinkTokenRequest,
+    responses(
+        (status = 200, description = "Confirmation email queued", body = MessageResponse),
+        (status = 401, description = "Invalid or expired token", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn send_link_confirmation(
+    State(app_state): State<AppState>,
+    Json(request): Json<LinkTokenRequest>,
+) -> Result<impl IntoResponse> {
+    app_state
+        .oauth_service
+        .send_link_confirmation(&request.token)
+        .await?;
+
+    Ok(Json(MessageResponse::new("Confirmation email sent")))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/link/confirm",
+    tag = "auth",
+    request_body = LinkTokenRequest,
+    responses(
+        (status = 200, description = "Identity linked and signed in", body = AuthResponse),
+        (status = 401, description = "Invalid or expired token", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn confirm_link_email(
+    State(app_state): State<AppState>,
+    Json(request): Json<LinkTokenRequest>,
+) -> Result<impl IntoResponse> {
+    let response = app_state
+        .oauth_service
+        .confirm_link_email(&request.token)
+        .await?;
+
+    Ok(Json(response))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Signed out", body = MessageResponse),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn logout() -> Result<impl IntoResponse> {
-    Ok(Json(json!({
-        "message": "Logged out successfully"
-    })))
+    Ok(Json(MessageResponse::new("Logged out successfully")))
 }
@@ -0,0 +1,127 @@
+use crate::error::{ProblemDetails, Result};
+use crate::models::{
+    EmailPreference, UnsubscribeQuery, UnsubscribeResponse, UpdateDigestScheduleRequest,
+    UpdateEmailPreferencesRequest, User, UserResponse,
+};
+use crate::state::AppState;
+use crate::utils::Json;
+use axum::{
+    extract::{Extension, Query, State},
+    response::{IntoResponse, Redirect},
+};
+
+/// Opening the link from an email shows the confirmation page instead of
+/// unsubscribing right away, so link scanners can't opt users out.
+#[utoipa::path(
+    get,
+    path = "/api/v1/email/unsubscribe",
+    tag = "email",
+    params(UnsubscribeQuery),
+    responses(
+        (status = 307, description = "Redirect to the frontend confirmation page"),
+    ),
+)]
+pub async fn unsubscribe_page(
+    State(app_state): State<AppState>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> Redirect {
+    let base_url = format!("{}/unsubscribe", app_state.config.frontend_url);
+    let redirect_url = reqwest::Url::parse_with_params(&base_url, [("token", &query.token)])
+        .map(|url| url.to_string())
+        .unwrap_or(base_url);
+
+    Redirect::temporary(&redirect_url)
+}
+
+/// RFC 8058 one-click unsubscribe. Mail clients POST here directly, and the
+/// confirmation page uses the same endpoint.
+#[utoipa::path(
+    post,
+    path = "/api/v1/email/unsubscribe",
+    tag = "email",
+    params(UnsubscribeQuery),
+    responses(
+        (status = 200, description = "Unsubscribed from the category", body = UnsubscribeResponse),
+        (status = 400, description = "Invalid unsubscribe link", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn unsubscribe(
+    State(app_state): State<AppState>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> Result<impl IntoResponse> {
+    let category = app_state
+        .email_preferences
+        .unsubscribe(&query.token)
+        .await?;
+
+    Ok(Json(UnsubscribeResponse { category }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/email/preferences",
+    tag = "email",
+    responses(
+        (status = 200, description = "Preferences for every category", body = Vec<EmailPreference>),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_preferences(
+    State(app_state): State<AppState>,
+    Extension(user): Extension<User>,
+) -> Result<impl IntoResponse> {
+    let preferences = app_state.email_preferences.get(user.id).await?;
+
+    Ok(Json(preferences))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/email/preferences",
+    tag = "email",
+    request_body = UpdateEmailPreferencesRequest,
+    responses(
+        (status = 200, description = "Updated preferences", body = Vec<EmailPreference>),
+        (status = 400, description = "Security emails cannot be turned off", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_preferences(
+    State(app_state): State<AppState>,
+    Extension(user): Extension<User>,
+    Json(request): Json<UpdateEmailPreferencesRequest>,
+) -> Result<impl IntoResponse> {
+    let preferences = app_state
+        .email_preferences
+        .update(user.id, &request.preferences)
+        .await?;
+
+    Ok(Json(preferences))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/email/digest",
+    tag = "email",
+    request_body = UpdateDigestScheduleRequest,
+    responses(
+        (status = 200, description = "Updated user", body = UserResponse),
+        (status = 400, description = "Invalid fields or time zone", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_digest_schedule(
+    State(app_state): State<AppState>,
+    Extension(user): Extension<User>,
+    Json(request): Json<UpdateDigestScheduleRequest>,
+) -> Result<impl IntoResponse> {
+    let user = app_state
+        .email_preferences
+        .update_digest_schedule(user.id, &request)
+        .await?;
+
+    Ok(Json(UserResponse::from(user)))
+}
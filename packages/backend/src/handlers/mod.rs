@@ -1,3 +1,9 @@
+pub mod admin;
 pub mod auth;
+pub mod email;
+pub mod monitoring;
 
+pub use admin::*;
 pub use auth::*;
+pub use email::*;
+pub use monitoring::*;
@@ -0,0 +1,124 @@
+use crate::error::{AppError, Message, Result};
+use crate::models::{CheckFailure, CheckResult, CheckStatus, ReadinessReport};
+use crate::routes::monitoring::{HealthState, MetricsState};
+use crate::telemetry::record_pool;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// How long a single readiness check may take before it counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Liveness: the process is up and serving requests. Deliberately checks
+/// nothing else, so a database outage doesn't get the instance restarted.
+pub async fn healthz() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+/// Readiness: the instance can serve traffic. Answers 503 with the failing
+/// checks otherwise.
+pub async fn readyz(State(state): State<HealthState>) -> impl IntoResponse {
+    let database = run_check("database", async {
+        sqlx::query("SELECT 1")
+            .execute(state.db.pool())
+            .await
+            .map(|_| ())
+    });
+
+    let migrations = run_check("migrations", async {
+        match state.db.pending_migrations().await {
+            Ok(pending) if pending.is_empty() => Ok(()),
+            Ok(pending) => Err(format!("pending migrations: {pending:?}")),
+            Err(e) => Err(e.to_string()),
+        }
+    });
+
+    let mail = async {
+        match &state.email_service {
+            Some(email_service) => Some(run_check("mail", email_service.check_transport()).await),
+            None => None,
+        }
+    };
+
+    let (database, migrations, mail) = tokio::join!(database, migrations, mail);
+
+    let mut checks = BTreeMap::from([("database", database), ("migrations", migrations)]);
+    if let Some(mail) = mail {
+        checks.insert("mail", mail);
+    }
+
+    let report = ReadinessReport::new(checks);
+    let status = if report.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(report))
+}
+
+async fn run_check<E: Display>(
+    name: &str,
+    check: impl Future<Output = std::result::Result<(), E>>,
+) -> CheckResult {
+    let started = Instant::now();
+
+    let reason = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => {
+            tracing::warn!("Readiness check {} failed: {}", name, e);
+            Some(CheckFailure::Error)
+        }
+        Err(_) => {
+            tracing::warn!(
+                "Readiness check {} timed out after {}s",
+                name,
+                CHECK_TIMEOUT.as_secs()
+            );
+            Some(CheckFailure::Timeout)
+        }
+    };
+
+    CheckResult {
+        status: if reason.is_none() {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Failed
+        },
+        duration_ms: started.elapsed().as_millis() as u64,
+        reason,
+    }
+}
+
+/// Prometheus scrape endpoint.
+pub async fn metrics(
+    State(state): State<MetricsState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if !token.is_some_and(|token| state.metrics_token.matches(token)) {
+        return Err(AppError::Authentication(Message::new(
+            "missing-authorization",
+        )));
+    }
+
+    record_pool(state.db.pool());
+    state.prometheus.run_upkeep();
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.prometheus.render(),
+    ))
+}
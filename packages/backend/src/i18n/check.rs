@@ -0,0 +1,475 @@
+//! Translation completeness checks. Every catalog is compared against
+//! `en`, and the message ids the code looks up must exist in `en`.
+
+use super::{ftl_files, DEFAULT_LOCALE, EMBEDDED};
+use crate::error::{AppError, Result};
+use fluent_syntax::ast::{Entry, Expression, InlineExpression, Pattern, PatternElement};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Calls whose first string argument is a message id.
+const LOOKUP_MARKERS: &[&str] = &["Message::new(\"", "code = \"", "t(\""];
+
+/// Where catalogs and the code that uses them live.
+#[derive(Debug, Clone)]
+pub struct CheckPaths {
+    pub ftl_dir: Option<PathBuf>,
+    /// Check the catalogs built into the binary, with `ftl_dir` layered on
+    /// top as the server loads them.
+    pub bundled: bool,
+    /// Rust sources and email templates scanned for message ids.
+    pub source_dirs: Vec<PathBuf>,
+    pub frontend_dir: Option<PathBuf>,
+}
+
+impl CheckPaths {
+    /// The layout of this repository, relative to the backend crate.
+    pub fn for_crate(root: &Path) -> Self {
+        let frontend_dir = root.join("../frontend/i18n/locales");
+
+        Self {
+            ftl_dir: Some(root.join("src/i18n/locales")),
+            bundled: false,
+            source_dirs: vec![root.join("src")],
+            frontend_dir: frontend_dir.is_dir().then_some(frontend_dir),
+        }
+    }
+
+    /// What a deployed server loads: the bundled catalogs and, if set, the
+    /// `i18n.dir` overrides. Without the sources only the catalogs are
+    /// compared.
+    pub fn for_deployment(dir: Option<&Path>) -> Self {
+        Self {
+            ftl_dir: dir.map(Path::to_path_buf),
+            bundled: true,
+            source_dirs: Vec::new(),
+            frontend_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Issue {
+    Syntax {
+        file: String,
+        error: String,
+    },
+    /// A message id is looked up in code but `en` doesn't define it.
+    Untranslated {
+        key: String,
+        location: String,
+    },
+    Missing {
+        file: String,
+        key: String,
+    },
+    Variables {
+        file: String,
+        key: String,
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    Extra {
+        file: String,
+        key: String,
+    },
+    Unused {
+        key: String,
+    },
+}
+
+impl Issue {
+    /// Extra and unused keys are worth cleaning up but can't break a page.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Issue::Extra { .. } | Issue::Unused { .. })
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Syntax { file, error } => write!(f, "{file}: syntax error: {error}"),
+            Issue::Untranslated { key, location } => {
+                write!(f, "{location}: `{key}` has no {DEFAULT_LOCALE} translation")
+            }
+            Issue::Missing { file, key } => write!(f, "{file}: missing `{key}`"),
+            Issue::Variables {
+                file,
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{file}: `{key}` uses variables [{}], expected [{}]",
+                found.join(", "),
+                expected.join(", ")
+            ),
+            Issue::Extra { file, key } => {
+                write!(f, "{file}: `{key}` is not in the {DEFAULT_LOCALE} catalog")
+            }
+            Issue::Unused { key } => write!(f, "`{key}` is never used"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(Issue::is_error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|issue| issue.is_error())
+    }
+}
+
+/// Keys mapped to the variables their translation uses.
+type Catalog = BTreeMap<String, BTreeSet<String>>;
+
+pub fn check_catalogs(paths: &CheckPaths) -> Result<Report> {
+    let mut issues = Vec::new();
+
+    let mut ftl: BTreeMap<String, (String, Catalog)> = BTreeMap::new();
+    let mut references = BTreeSet::new();
+    let mut sources = Vec::new();
+    if paths.bundled {
+        for (locale, source) in EMBEDDED {
+            sources.push((
+                locale.to_string(),
+                format!("embedded {locale}.ftl"),
+                source.to_string(),
+            ));
+        }
+    }
+    if let Some(dir) = &paths.ftl_dir {
+        for (locale, path) in ftl_files(dir)? {
+            sources.push((locale, display_name(dir, &path), read(&path)?));
+        }
+    }
+    for (locale, file, source) in sources {
+        let (name, catalog) = ftl.entry(locale).or_insert_with(|| (file, Catalog::new()));
+        parse_ftl(&source, name, catalog, &mut references, &mut issues);
+    }
+    compare(&ftl, &mut issues);
+
+    let mut lookups = Vec::new();
+    let mut mentions = BTreeSet::new();
+    for dir in &paths.source_dirs {
+        scan_sources(dir, dir, &mut lookups, &mut mentions)?;
+    }
+
+    // Usage can only be judged with the sources at hand.
+    let en = ftl
+        .get(DEFAULT_LOCALE)
+        .filter(|_| !paths.source_dirs.is_empty());
+    if let Some((_, en)) = en {
+        for (key, location) in lookups {
+            if !en.contains_key(&key) {
+                issues.push(Issue::Untranslated { key, location });
+            }
+        }
+        for key in en.keys() {
+            if !key.starts_with('-') && !mentions.contains(key) && !references.contains(key) {
+                issues.push(Issue::Unused { key: key.clone() });
+            }
+        }
+    }
+
+    if let Some(dir) = &paths.frontend_dir {
+        let mut json = BTreeMap::new();
+        for path in sorted_entries(dir)? {
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+                let name = display_name(dir, &path);
+                let catalog = parse_json(&read(&path)?, &name, &mut issues);
+                json.insert(locale.to_string(), (name, catalog));
+            }
+        }
+        compare(&json, &mut issues);
+    }
+
+    issues.sort();
+    issues.dedup();
+    Ok(Report { issues })
+}
+
+fn compare(catalogs: &BTreeMap<String, (String, Catalog)>, issues: &mut Vec<Issue>) {
+    let Some((_, en)) = catalogs.get(DEFAULT_LOCALE) else {
+        return;
+    };
+
+    for (locale, (file, catalog)) in catalogs {
+        if locale == DEFAULT_LOCALE {
+            continue;
+        }
+
+        for (key, expected) in en {
+            match catalog.get(key) {
+                None => issues.push(Issue::Missing {
+                    file: file.clone(),
+                    key: key.clone(),
+                }),
+                Some(found) if found != expected => issues.push(Issue::Variables {
+                    file: file.clone(),
+                    key: key.clone(),
+                    expected: expected.iter().cloned().collect(),
+                    found: found.iter().cloned().collect(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for key in catalog.keys().filter(|key| !en.contains_key(*key)) {
+            issues.push(Issue::Extra {
+                file: file.clone(),
+                key: key.clone(),
+            });
+        }
+    }
+}
+
+fn parse_ftl(
+    source: &str,
+    file: &str,
+    catalog: &mut Catalog,
+    references: &mut BTreeSet<String>,
+    issues: &mut Vec<Issue>,
+) {
+    let resource = fluent_syntax::parser::parse(source).unwrap_or_else(|(resource, errors)| {
+        for error in errors {
+            let line = source[..error.pos.start.min(source.len())].lines().count();
+            issues.push(Issue::Syntax {
+                file: format!("{file}:{line}"),
+                error: error.kind.to_string(),
+            });
+        }
+        resource
+    });
+
+    for entry in resource.body {
+        let (key, value, attributes) = match entry {
+            Entry::Message(message) => (
+                message.id.name.to_string(),
+                message.value,
+                message.attributes,
+            ),
+            Entry::Term(term) => (
+                format!("-{}", term.id.name),
+                Some(term.value),
+                term.attributes,
+            ),
+            _ => continue,
+        };
+
+        let mut variables = BTreeSet::new();
+        let patterns = value.iter().chain(attributes.iter().map(|a| &a.value));
+        for pattern in patterns {
+            pattern_references(pattern, &mut variables, references);
+        }
+        catalog.insert(key, variables);
+    }
+}
+
+fn pattern_references(
+    pattern: &Pattern<&str>,
+    variables: &mut BTreeSet<String>,
+    messages: &mut BTreeSet<String>,
+) {
+    for element in &pattern.elements {
+        if let PatternElement::Placeable { expression } = element {
+            expression_references(expression, variables, messages);
+        }
+    }
+}
+
+fn expression_references(
+    expression: &Expression<&str>,
+    variables: &mut BTreeSet<String>,
+    messages: &mut BTreeSet<String>,
+) {
+    match expression {
+        Expression::Select { selector, variants } => {
+            inline_references(selector, variables, messages);
+            for variant in variants {
+                pattern_references(&variant.value, variables, messages);
+            }
+        }
+        Expression::Inline(inline) => inline_references(inline, variables, messages),
+    }
+}
+
+fn inline_references(
+    expression: &InlineExpression<&str>,
+    variables: &mut BTreeSet<String>,
+    messages: &mut BTreeSet<String>,
+) {
+    match expression {
+        InlineExpression::VariableReference { id } => {
+            variables.insert(id.name.to_string());
+        }
+        InlineExpression::MessageReference { id, .. } => {
+            messages.insert(id.name.to_string());
+        }
+        InlineExpression::FunctionReference { arguments, .. } => {
+            let named = arguments.named.iter().map(|argument| &argument.value);
+            for argument in arguments.positional.iter().chain(named) {
+                inline_references(argument, variables, messages);
+            }
+        }
+        InlineExpression::TermReference {
+            arguments: Some(arguments),
+            ..
+        } => {
+            for argument in &arguments.positional {
+                inline_references(argument, variables, messages);
+            }
+        }
+        InlineExpression::Placeable { expression } => {
+            expression_references(expression, variables, messages)
+        }
+        _ => {}
+    }
+}
+
+/// Flattens nested objects into dotted keys. Variables are `{name}`
+/// placeholders.
+fn parse_json(source: &str, file: &str, issues: &mut Vec<Issue>) -> Catalog {
+    fn flatten(prefix: &str, value: &serde_json::Value, catalog: &mut Catalog) {
+        match value {
+            serde_json::Value::Object(entries) => {
+                for (key, value) in entries {
+                    let key = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{prefix}.{key}")
+                    };
+                    flatten(&key, value, catalog);
+                }
+            }
+            serde_json::Value::String(text) => {
+                catalog.insert(prefix.to_string(), json_variables(text));
+            }
+            _ => {
+                catalog.insert(prefix.to_string(), BTreeSet::new());
+            }
+        }
+    }
+
+    let mut catalog = Catalog::new();
+    match serde_json::from_str(source) {
+        Ok(value) => flatten("", &value, &mut catalog),
+        Err(e) => issues.push(Issue::Syntax {
+            file: file.to_string(),
+            error: e.to_string(),
+        }),
+    }
+    catalog
+}
+
+fn json_variables(text: &str) -> BTreeSet<String> {
+    text.split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}'))
+        .map(|(name, _)| name.trim())
+        .filter(|name| is_identifier(name))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Collects ids passed to a lookup (`lookups`, with their location) and
+/// every quoted string that could be a message id (`mentions`).
+fn scan_sources(
+    root: &Path,
+    dir: &Path,
+    lookups: &mut Vec<(String, String)>,
+    mentions: &mut BTreeSet<String>,
+) -> Result<()> {
+    for path in sorted_entries(dir)? {
+        if path.is_dir() {
+            scan_sources(root, &path, lookups, mentions)?;
+            continue;
+        }
+
+        let scanned = ["rs", "html", "txt"];
+        if !path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| scanned.contains(&ext))
+        {
+            continue;
+        }
+
+        let source = read(&path)?;
+        for (number, line) in source.lines().enumerate() {
+            for marker in LOOKUP_MARKERS {
+                for (offset, _) in line.match_indices(marker) {
+                    let starts_word = line[..offset]
+                        .chars()
+                        .next_back()
+                        .is_none_or(|c| !c.is_alphanumeric() && c != '_');
+                    let literal = line[offset + marker.len()..].split('"').next();
+                    if let Some(key) = literal.filter(|key| starts_word && is_message_id(key)) {
+                        let location = format!("{}:{}", display_name(root, &path), number + 1);
+                        lookups.push((key.to_string(), location));
+                    }
+                }
+            }
+
+            mentions.extend(
+                line.split('"')
+                    .skip(1)
+                    .step_by(2)
+                    .filter(|text| is_message_id(text))
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn is_message_id(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_lowercase())
+        && text.contains('-')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn is_identifier(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn display_name(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+fn read(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| {
+        AppError::Internal(anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+    })
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| {
+            AppError::Internal(anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))
+        })?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
@@ -0,0 +1,164 @@
+use super::{I18n, DEFAULT_LOCALE};
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use std::convert::Infallible;
+
+/// Cookie the frontend sets to pin the interface language.
+pub const LOCALE_COOKIE: &str = "locale";
+
+/// Where a negotiated locale came from, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LocaleSource {
+    Query,
+    Cookie,
+    User,
+    AcceptLanguage,
+    Default,
+}
+
+/// The locale to answer a request in. Negotiated by `locale_middleware`
+/// from `?lang=`, the `locale` cookie and `Accept-Language`, and refined by
+/// `auth_middleware` with the user's stored locale. Extracting it never
+/// fails; without the middleware it is the default locale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    pub code: String,
+    pub source: LocaleSource,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &str {
+        &self.code
+    }
+
+    /// Negotiates from the request alone, before the user is known.
+    pub fn negotiate(i18n: &I18n, parts: &Parts) -> Self {
+        let query = parts.uri.query().and_then(|query| {
+            query.split('&').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                (key == "lang").then_some(value)
+            })
+        });
+
+        if let Some(code) = query.and_then(|lang| i18n.resolve(lang)) {
+            return Self::new(code, LocaleSource::Query);
+        }
+
+        let cookie = parts
+            .headers
+            .get_all(axum::http::header::COOKIE)
+            .iter()
+            .filter_map(|header| header.to_str().ok())
+            .flat_map(|header| header.split(';'))
+            .find_map(|cookie| {
+                let (name, value) = cookie.trim().split_once('=')?;
+                (name == LOCALE_COOKIE).then_some(value)
+            });
+
+        if let Some(code) = cookie.and_then(|lang| i18n.resolve(lang)) {
+            return Self::new(code, LocaleSource::Cookie);
+        }
+
+        let accepted = parts
+            .headers
+            .get(axum::http::header::ACCEPT_LANGUAGE)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| {
+                parse_accept_language(header)
+                    .into_iter()
+                    .find_map(|lang| i18n.resolve(&lang))
+            });
+
+        match accepted {
+            Some(code) => Self::new(code, LocaleSource::AcceptLanguage),
+            None => Self::default(),
+        }
+    }
+
+    /// The user's stored locale beats the browser's, but not an explicit
+    /// `?lang=` or cookie.
+    pub fn with_user_locale(self, i18n: &I18n, user_locale: &str) -> Self {
+        if self.source < LocaleSource::User {
+            return self;
+        }
+
+        match i18n.resolve(user_locale) {
+            Some(code) => Self::new(code, LocaleSource::User),
+            None => self,
+        }
+    }
+
+    fn new(code: String, source: LocaleSource) -> Self {
+        Self { code, source }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOCALE.to_string(), LocaleSource::Default)
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.code)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<Locale>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+/// Language tags from an `Accept-Language` header, highest quality first.
+/// Tags with `q=0`, malformed weights and `*` are dropped.
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut tags: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim();
+
+            let quality = match parts.find_map(|param| param.trim().strip_prefix("q=")) {
+                Some(q) => q.trim().parse::<f32>().ok()?,
+                None => 1.0,
+            };
+
+            (!tag.is_empty() && tag != "*" && quality > 0.0 && quality <= 1.0)
+                .then(|| (tag.to_string(), quality))
+        })
+        .collect();
+
+    // Stable, so equal weights keep the client's order.
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+tokio::task_local! {
+    static LOCALE_SCOPE: (I18n, Locale);
+}
+
+/// Makes the negotiated locale available to code that cannot extract it,
+/// such as `AppError::into_response`.
+pub struct LocaleScope;
+
+impl LocaleScope {
+    pub async fn run<F: std::future::Future>(i18n: I18n, locale: Locale, future: F) -> F::Output {
+        LOCALE_SCOPE.scope((i18n, locale), future).await
+    }
+
+    /// The current request's catalogs and locale, or the embedded catalogs
+    /// and the default locale outside a request.
+    pub fn current() -> (I18n, Locale) {
+        LOCALE_SCOPE
+            .try_with(|(i18n, locale)| (i18n.clone(), locale.clone()))
+            .unwrap_or_else(|_| (I18n::embedded(), Locale::default()))
+    }
+}
@@ -18,11 +18,79 @@ email-required = Email is required
 password-required = Password is required
 username-required = Username is required
 invalid-email = Invalid email format
-password-too-short = Password must be at least 8 characters
-username-too-short = Username must be at least 3 characters
-username-too-long = Username must be less than 50 characters
+password-too-short = Password must be at least { $min } characters
 oauth-success = OAuth login successful
 oauth-failed = OAuth login failed
 oauth-password-reset-not-allowed = Password reset is not available for OAuth accounts
 account-not-verified = Please verify your email address first
-verification-email-resent = Verification email resent
\ No newline at end of file
+verification-email-resent = Verification email resent
+account-has-no-password = This account has no password. Confirm the link by email instead
+oauth-cancelled = OAuth login was cancelled
+oauth-account-conflict = This login is already linked to another account
+oauth-provider-disabled = Sign-in with { $provider } is not available
+email-greeting = Hello { $username },
+email-link-fallback = If the button doesn't work, you can copy and paste this link into your browser:
+email-signature = The ForMangaReaders team
+email-verification-subject = Verify your email address
+email-verification-heading = Email Verification
+email-verification-intro = Thank you for registering with ForMangaReaders! Please click the button below to verify your email address:
+email-verification-button = Verify Email
+email-verification-expiry = This link will expire in 24 hours. If you didn't create an account, please ignore this email.
+email-password-reset-subject = Reset your password
+email-password-reset-heading = Password Reset
+email-password-reset-intro = You requested to reset your password. Click the button below to set a new password:
+email-password-reset-button = Reset Password
+email-password-reset-expiry = This link will expire in 1 hour. If you didn't request a password reset, please ignore this email.
+email-account-link-subject = Confirm linking your account
+email-account-link-heading = Link Your Account
+email-account-link-intro = Someone signed in with { $provider } using your email address and asked to link that login to your ForMangaReaders account. Click the button below to confirm:
+email-account-link-button = Link Account
+email-account-link-expiry = This link will expire in 30 minutes. If this wasn't you, please ignore this email and your account will stay unchanged.
+email-unsubscribe = Unsubscribe from these emails
+email-category-locked = Security emails cannot be turned off
+invalid-unsubscribe-link = This unsubscribe link is invalid
+email-digest-subject = Your weekly ForMangaReaders digest
+email-digest-heading = New Chapters This Week
+email-digest-intro = { $count ->
+    [one] There is 1 new chapter in the series you follow:
+   *[other] There are { $count } new chapters in the series you follow:
+}
+email-digest-chapter = Chapter { $number }
+email-digest-more = { $count ->
+    [one] and 1 more chapter
+   *[other] and { $count } more chapters
+}
+email-digest-button = Start Reading
+email-digest-footer = You get this digest once a week. You can change the day or turn it off in your profile.
+invalid-timezone = Unknown time zone
+unsupported-locale = This language is not supported
+missing-authorization = Missing or invalid authorization header
+admin-required = Administrator access required
+oauth-login-required = Please use OAuth login for this account
+email-already-verified = Email is already verified
+username-length = Username must be between { $min } and { $max } characters
+digest-day-range = Day must be between { $min } and { $max }
+suppression-reason-length = Reason must be between { $min } and { $max } characters
+suppression-not-found = Suppression not found
+outbox-email-not-found = Email not found
+outbox-retry-not-failed = Only failed emails can be retried
+rate-limited = Too many requests. Try again in { $seconds ->
+    [one] 1 second
+   *[other] { $seconds } seconds
+}
+exemption-reason-length = Reason must be between { $min } and { $max } characters
+rate-limit-exemption-not-found = Rate limit exemption not found
+validation-failed = Some fields are invalid
+validation-email = Invalid email format
+validation-length = Invalid length
+validation-range = Value is out of range
+validation-required = This field is required
+validation-type = Value has the wrong type
+validation-invalid = Invalid value
+malformed-json = The request body is not valid JSON
+json-content-type-required = The request body must be JSON, sent with Content-Type: application/json
+error-database = Database error
+error-password-hashing = Password hashing error
+error-email-delivery = Email sending error
+error-http-client = HTTP client error
+error-internal = Internal server error
\ No newline at end of file
@@ -18,11 +18,70 @@ email-required = E-posta gerekli
 password-required = Şifre gerekli
 username-required = Kullanıcı adı gerekli
 invalid-email = Geçersiz e-posta formatı
-password-too-short = Şifre en az 8 karakter olmalı
-username-too-short = Kullanıcı adı en az 3 karakter olmalı
-username-too-long = Kullanıcı adı 50 karakterden az olmalı
+password-too-short = Şifre en az { $min } karakter olmalı
 oauth-success = OAuth girişi başarılı
 oauth-failed = OAuth girişi başarısız
 oauth-password-reset-not-allowed = OAuth hesapları için şifre sıfırlama mevcut değil
 account-not-verified = Lütfen önce e-posta adresinizi doğrulayın
-verification-email-resent = Doğrulama e-postası yeniden gönderildi
\ No newline at end of file
+verification-email-resent = Doğrulama e-postası yeniden gönderildi
+account-has-no-password = Bu hesabın şifresi yok. Bağlantıyı e-posta ile onaylayın
+oauth-cancelled = OAuth girişi iptal edildi
+oauth-account-conflict = Bu giriş zaten başka bir hesaba bağlı
+oauth-provider-disabled = { $provider } ile giriş kullanılamıyor
+email-greeting = Merhaba { $username },
+email-link-fallback = Düğme çalışmazsa bu bağlantıyı kopyalayıp tarayıcınıza yapıştırabilirsiniz:
+email-signature = ForMangaReaders ekibi
+email-verification-subject = E-posta adresinizi doğrulayın
+email-verification-heading = E-posta Doğrulama
+email-verification-intro = ForMangaReaders'a kaydolduğunuz için teşekkürler! E-posta adresinizi doğrulamak için aşağıdaki düğmeye tıklayın:
+email-verification-button = E-postayı Doğrula
+email-verification-expiry = Bu bağlantının süresi 24 saat içinde dolacak. Hesap oluşturmadıysanız bu e-postayı dikkate almayın.
+email-password-reset-subject = Şifrenizi sıfırlayın
+email-password-reset-heading = Şifre Sıfırlama
+email-password-reset-intro = Şifrenizi sıfırlamak istediniz. Yeni bir şifre belirlemek için aşağıdaki düğmeye tıklayın:
+email-password-reset-button = Şifreyi Sıfırla
+email-password-reset-expiry = Bu bağlantının süresi 1 saat içinde dolacak. Şifre sıfırlama isteğinde bulunmadıysanız bu e-postayı dikkate almayın.
+email-account-link-subject = Hesap bağlamayı onaylayın
+email-account-link-heading = Hesabınızı Bağlayın
+email-account-link-intro = Birisi e-posta adresinizle { $provider } üzerinden giriş yaptı ve bu girişi ForMangaReaders hesabınıza bağlamak istedi. Onaylamak için aşağıdaki düğmeye tıklayın:
+email-account-link-button = Hesabı Bağla
+email-account-link-expiry = Bu bağlantının süresi 30 dakika içinde dolacak. Bu siz değilseniz bu e-postayı dikkate almayın; hesabınız değişmeden kalacak.
+email-unsubscribe = Bu e-postaların aboneliğinden çık
+email-category-locked = Güvenlik e-postaları kapatılamaz
+invalid-unsubscribe-link = Bu abonelikten çıkma bağlantısı geçersiz
+email-digest-subject = Haftalık ForMangaReaders özetiniz
+email-digest-heading = Bu Haftanın Yeni Bölümleri
+email-digest-intro = Takip ettiğiniz serilerde { $count } yeni bölüm var:
+email-digest-chapter = Bölüm { $number }
+email-digest-more = ve { $count } bölüm daha
+email-digest-button = Okumaya Başla
+email-digest-footer = Bu özeti haftada bir alırsınız. Gününü değiştirmek veya kapatmak için profilinizi kullanabilirsiniz.
+invalid-timezone = Bilinmeyen saat dilimi
+unsupported-locale = Bu dil desteklenmiyor
+missing-authorization = Yetkilendirme başlığı eksik veya geçersiz
+admin-required = Yönetici erişimi gerekli
+oauth-login-required = Lütfen bu hesap için OAuth girişini kullanın
+email-already-verified = E-posta zaten doğrulanmış
+username-length = Kullanıcı adı { $min } ile { $max } karakter arasında olmalı
+digest-day-range = Gün { $min } ile { $max } arasında olmalı
+suppression-reason-length = Sebep { $min } ile { $max } karakter arasında olmalı
+suppression-not-found = Engelleme kaydı bulunamadı
+outbox-email-not-found = E-posta bulunamadı
+outbox-retry-not-failed = Yalnızca başarısız e-postalar yeniden denenebilir
+rate-limited = Çok fazla istek. { $seconds } saniye sonra tekrar deneyin
+exemption-reason-length = Sebep { $min } ile { $max } karakter arasında olmalı
+rate-limit-exemption-not-found = İstek sınırı muafiyeti bulunamadı
+validation-failed = Bazı alanlar geçersiz
+validation-email = Geçersiz e-posta formatı
+validation-length = Geçersiz uzunluk
+validation-range = Değer aralık dışında
+validation-required = Bu alan gerekli
+validation-type = Değerin türü yanlış
+validation-invalid = Geçersiz değer
+malformed-json = İstek gövdesi geçerli bir JSON değil
+json-content-type-required = İstek gövdesi JSON olmalı ve Content-Type: application/json ile gönderilmeli
+error-database = Veritabanı hatası
+error-password-hashing = Şifre işleme hatası
+error-email-delivery = E-posta gönderme hatası
+error-http-client = HTTP istemci hatası
+error-internal = Sunucu hatası
\ No newline at end of file
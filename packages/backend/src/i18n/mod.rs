@@ -1,66 +1,147 @@
-use fluent::{FluentBundle, FluentResource};
-use fluent_bundle::FluentArgs;
+use crate::config::I18nConfig;
+use crate::error::{AppError, Result};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock, Weak};
+use std::time::{Duration, SystemTime};
 use unic_langid::LanguageIdentifier;
 
-struct SafeFluentBundle(FluentBundle<FluentResource>);
+mod check;
+mod locale;
 
-unsafe impl Send for SafeFluentBundle {}
-unsafe impl Sync for SafeFluentBundle {}
+pub use check::*;
+pub use locale::*;
 
+/// Locale every lookup falls back to last.
+pub const DEFAULT_LOCALE: &str = "en";
+
+const HOT_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Catalogs compiled into the binary, so the server works without `I18N_DIR`.
+const EMBEDDED: &[(&str, &str)] = &[
+    ("en", include_str!("locales/en.ftl")),
+    ("tr", include_str!("locales/tr.ftl")),
+];
+
+type Bundle = FluentBundle<FluentResource>;
+
+/// Fluent translations for every available locale. Lookups walk a fallback
+/// chain such as `pt-BR` → `pt` → `en`, so a partial translation still
+/// renders.
 #[derive(Clone)]
 pub struct I18n {
-    bundles: Arc<Mutex<HashMap<String, SafeFluentBundle>>>,
+    bundles: Arc<RwLock<HashMap<String, Bundle>>>,
 }
 
 impl I18n {
+    /// Only the embedded catalogs.
     pub fn new() -> Self {
-        let mut bundles = HashMap::new();
-
-        let locales = vec!["en", "tr"];
+        let bundles = load_bundles(None).expect("embedded catalogs are valid");
 
-        for locale in locales {
-            let lang_id: LanguageIdentifier = locale.parse().expect("Invalid language identifier");
-            let mut bundle = FluentBundle::new(vec![lang_id]);
-
-            let ftl_string = match locale {
-                "en" => include_str!("locales/en.ftl"),
-                "tr" => include_str!("locales/tr.ftl"),
-                _ => include_str!("locales/en.ftl"),
-            };
+        Self {
+            bundles: Arc::new(RwLock::new(bundles)),
+        }
+    }
 
-            let resource = FluentResource::try_new(ftl_string.to_string())
-                .expect("Failed to parse FTL string");
+    /// A shared instance of the embedded catalogs, for rendering outside a
+    /// request.
+    pub fn embedded() -> Self {
+        static EMBEDDED_I18N: OnceLock<I18n> = OnceLock::new();
+        EMBEDDED_I18N.get_or_init(I18n::new).clone()
+    }
 
-            bundle
-                .add_resource(resource)
-                .expect("Failed to add resource to bundle");
+    /// Embedded catalogs plus the `.ftl` files in `config.dir`, either as
+    /// `{dir}/{locale}.ftl` or `{dir}/{locale}/*.ftl`.
+    pub fn load(config: &I18nConfig) -> Result<Self> {
+        let dir = config.dir.as_deref().map(Path::new);
+        let i18n = Self {
+            bundles: Arc::new(RwLock::new(load_bundles(dir)?)),
+        };
 
-            bundles.insert(locale.to_string(), SafeFluentBundle(bundle));
+        if let (Some(dir), true) = (dir, config.hot_reload) {
+            i18n.watch(dir.to_path_buf());
         }
 
-        Self {
-            bundles: Arc::new(Mutex::new(bundles)),
-        }
+        Ok(i18n)
     }
 
     pub fn get_message(&self, locale: &str, message_id: &str, args: Option<&FluentArgs>) -> String {
-        let bundles = self.bundles.lock().unwrap();
-        let bundle = bundles.get(locale).or_else(|| bundles.get("en"));
-
-        if let Some(SafeFluentBundle(bundle)) = bundle {
-            if let Some(message) = bundle.get_message(message_id) {
-                if let Some(pattern) = message.value() {
-                    let mut errors = vec![];
-                    let formatted = bundle.format_pattern(pattern, args, &mut errors);
-                    return formatted.to_string();
-                }
+        let bundles = self.bundles.read().unwrap_or_else(|e| e.into_inner());
+
+        for candidate in fallback_chain(locale) {
+            let Some(bundle) = bundles.get(&candidate) else {
+                continue;
+            };
+
+            if let Some(pattern) = bundle
+                .get_message(message_id)
+                .and_then(|message| message.value())
+            {
+                let mut errors = vec![];
+                return bundle
+                    .format_pattern(pattern, args, &mut errors)
+                    .to_string();
             }
         }
 
         message_id.to_string()
     }
+
+    /// Whether `locale`, or a less specific form of it, has a catalog.
+    pub fn supports(&self, locale: &str) -> bool {
+        self.resolve(locale).is_some()
+    }
+
+    /// The most specific available locale for `locale`, e.g. `pt` for
+    /// `pt-PT` when only `pt` and `pt-BR` exist.
+    pub fn resolve(&self, locale: &str) -> Option<String> {
+        let bundles = self.bundles.read().unwrap_or_else(|e| e.into_inner());
+
+        specific_chain(locale)
+            .into_iter()
+            .find(|candidate| bundles.contains_key(candidate))
+    }
+
+    pub fn available_locales(&self) -> Vec<String> {
+        let bundles = self.bundles.read().unwrap_or_else(|e| e.into_inner());
+        let mut locales: Vec<String> = bundles.keys().cloned().collect();
+        locales.sort();
+        locales
+    }
+
+    /// Polls `dir` and swaps in fresh bundles when a file changes. A catalog
+    /// that fails to load is logged and the previous one is kept.
+    fn watch(&self, dir: PathBuf) {
+        let bundles = Arc::downgrade(&self.bundles);
+
+        std::thread::spawn(move || {
+            let mut fingerprint = dir_fingerprint(&dir);
+
+            loop {
+                std::thread::sleep(HOT_RELOAD_INTERVAL);
+
+                let Some(bundles) = Weak::upgrade(&bundles) else {
+                    return;
+                };
+
+                let current = dir_fingerprint(&dir);
+                if current == fingerprint {
+                    continue;
+                }
+                fingerprint = current;
+
+                match load_bundles(Some(&dir)) {
+                    Ok(fresh) => {
+                        *bundles.write().unwrap_or_else(|e| e.into_inner()) = fresh;
+                        tracing::info!("Reloaded translations from {}", dir.display());
+                    }
+                    Err(e) => tracing::warn!("Keeping previous translations: {}", e),
+                }
+            }
+        });
+    }
 }
 
 impl Default for I18n {
@@ -68,3 +149,142 @@ impl Default for I18n {
         Self::new()
     }
 }
+
+/// `locale` and its less specific forms, most specific first.
+fn specific_chain(locale: &str) -> Vec<String> {
+    let Ok(langid) = locale.parse::<LanguageIdentifier>() else {
+        return Vec::new();
+    };
+
+    let mut chain = vec![langid.to_string()];
+
+    if langid.region.is_some() || langid.variants().next().is_some() {
+        if let Some(script) = langid.script {
+            chain.push(format!("{}-{}", langid.language, script));
+        }
+    }
+    chain.push(langid.language.to_string());
+
+    chain.dedup();
+    chain
+}
+
+fn fallback_chain(locale: &str) -> Vec<String> {
+    let mut chain = specific_chain(locale);
+
+    if !chain.iter().any(|candidate| candidate == DEFAULT_LOCALE) {
+        chain.push(DEFAULT_LOCALE.to_string());
+    }
+
+    chain
+}
+
+fn load_bundles(dir: Option<&Path>) -> Result<HashMap<String, Bundle>> {
+    let mut sources: HashMap<String, Vec<(String, String)>> = HashMap::new();
+
+    for (locale, source) in EMBEDDED {
+        sources
+            .entry(locale.to_string())
+            .or_default()
+            .push((format!("embedded {locale}.ftl"), source.to_string()));
+    }
+
+    if let Some(dir) = dir {
+        for (locale, path) in ftl_files(dir)? {
+            let source = std::fs::read_to_string(&path).map_err(|e| {
+                AppError::Internal(anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+            })?;
+            sources
+                .entry(locale)
+                .or_default()
+                .push((path.display().to_string(), source));
+        }
+    }
+
+    let mut bundles = HashMap::new();
+
+    for (locale, files) in sources {
+        let Ok(langid) = locale.parse::<LanguageIdentifier>() else {
+            tracing::warn!("Skipping translations for invalid locale {:?}", locale);
+            continue;
+        };
+
+        let mut bundle = Bundle::new_concurrent(vec![langid.clone()]);
+        bundle.set_use_isolating(false);
+
+        // Later files override messages from earlier ones, so files on disk
+        // win over the embedded defaults.
+        for (name, source) in files {
+            let resource = FluentResource::try_new(source).unwrap_or_else(|(resource, errors)| {
+                tracing::warn!("{} has {} syntax errors", name, errors.len());
+                resource
+            });
+            bundle.add_resource_overriding(resource);
+        }
+
+        bundles.insert(langid.to_string(), bundle);
+    }
+
+    Ok(bundles)
+}
+
+/// `(locale, path)` for `{dir}/{locale}.ftl` and `{dir}/{locale}/*.ftl`.
+fn ftl_files(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let read_dir = |dir: &Path| {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| {
+                AppError::Internal(anyhow::anyhow!(
+                    "Failed to read translations in {}: {}",
+                    dir.display(),
+                    e
+                ))
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        paths.sort();
+        Ok::<_, AppError>(paths)
+    };
+
+    let is_ftl = |path: &Path| path.extension().is_some_and(|ext| ext == "ftl");
+    let name = |path: &Path, stem: bool| {
+        let name = if stem {
+            path.file_stem()
+        } else {
+            path.file_name()
+        };
+        name.and_then(|name| name.to_str()).map(str::to_string)
+    };
+
+    let mut files = Vec::new();
+
+    for path in read_dir(dir)? {
+        if path.is_dir() {
+            let Some(locale) = name(&path, false) else {
+                continue;
+            };
+            for file in read_dir(&path)? {
+                if is_ftl(&file) {
+                    files.push((locale.clone(), file));
+                }
+            }
+        } else if is_ftl(&path) {
+            if let Some(locale) = name(&path, true) {
+                files.push((locale, path));
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Modification times of every catalog file, to notice edits.
+fn dir_fingerprint(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    ftl_files(dir)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, path)| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect()
+}
@@ -5,21 +5,47 @@ pub mod handlers;
 pub mod i18n;
 pub mod middleware;
 pub mod models;
+pub mod openapi;
 pub mod routes;
 pub mod services;
+pub mod state;
+pub mod telemetry;
 pub mod utils;
 
 pub use config::Config;
 pub use database::Database;
 pub use error::{AppError, Result};
+pub use state::AppState;
 
 use axum::Router;
-use tower_http::cors::CorsLayer;
+use middleware::{
+    cors_middleware, http_metrics_middleware, request_id_middleware, request_span, CorsPolicies,
+};
+use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 
-pub async fn create_app(_config: Config, db: Database) -> Router {
-    Router::new()
-        .nest("/api/v1", routes::create_routes(db.clone()))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+/// Builds the shared state from `config` and mounts every route on it.
+pub async fn create_app(config: Config, db: Database) -> Result<Router> {
+    Ok(create_app_with_state(AppState::new(config, db)?))
+}
+
+/// Mounts every route on a state built by the caller.
+pub fn create_app_with_state(state: AppState) -> Router {
+    telemetry::prometheus();
+
+    let mut app = Router::new()
+        .nest("/api/v1", routes::create_routes(state.clone()))
+        .route_layer(axum::middleware::from_fn(http_metrics_middleware))
+        .merge(routes::monitoring::create_monitoring_routes(&state));
+
+    if let Some(storage) = &state.config.storage {
+        app = app.nest_service("/media", ServeDir::new(&storage.media_dir));
+    }
+
+    app.layer(axum::middleware::from_fn_with_state(
+        CorsPolicies::new(&state.config.cors),
+        cors_middleware,
+    ))
+    .layer(TraceLayer::new_for_http().make_span_with(request_span))
+    .layer(axum::middleware::from_fn(request_id_middleware))
 }
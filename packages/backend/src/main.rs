@@ -1,13 +1,28 @@
-use backend::{create_app, Config, Database};
+use backend::config::MailTransportConfig;
+use backend::i18n::{check_catalogs, CheckPaths};
+use backend::services::{AvatarService, EmailOutboxWorker, MediaStorage, WeeklyDigestScheduler};
+use backend::{create_app_with_state, AppState, Config, Database};
 use dotenv::dotenv;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// How long closing the database pool waits for connections in use.
+const POOL_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
 
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("check-i18n") {
+        return check_i18n(args.next().map(PathBuf::from));
+    }
+
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -16,7 +31,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let config = Config::from_env().map_err(|e| format!("Failed to load configuration: {e}"))?;
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    log_disabled_features(&config);
 
     let database = Database::new(&config.database_url)
         .await
@@ -24,15 +46,171 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing::info!("Connected to database successfully");
 
-    let app = create_app(config.clone(), database).await;
+    // Built first so the metrics recorder is installed before the workers
+    // start recording.
+    let state = AppState::new(config.clone(), database.clone())?;
+    let app = create_app_with_state(state.clone());
+
+    // The workers send what the API enqueued, skipping suppressed addresses.
+    let email_service = state.email_service.with_suppression_list(database.clone());
+
+    let shutdown = CancellationToken::new();
+    tokio::spawn(cancel_on_signal(shutdown.clone()));
+
+    let mut workers = vec![
+        EmailOutboxWorker::new(database.clone(), email_service.clone()).spawn(shutdown.clone()),
+        WeeklyDigestScheduler::new(database.clone(), email_service, config.frontend_url.clone())
+            .spawn(shutdown.clone()),
+    ];
+    if let Some(storage) = &config.storage {
+        let avatars = AvatarService::new(
+            database.clone(),
+            MediaStorage::new(storage),
+            state.http_client.clone(),
+        );
+        workers.push(avatars.spawn_backfill(shutdown.clone()));
+    }
 
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
     let listener = TcpListener::bind(addr).await?;
 
     tracing::info!("Server running on http://{}", addr);
-    tracing::info!("API documentation available at http://{}/api/v1", addr);
+    tracing::info!("API documentation available at http://{}/api/v1/docs", addr);
+
+    // After the signal the listener is closed, open connections finish
+    // their requests and the workers finish what they are sending.
+    // Peer addresses key the per-IP rate limits.
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+    .into_future();
+    let drained = async {
+        server.await?;
+        for worker in workers {
+            worker.await?;
+        }
+        Ok::<_, Box<dyn std::error::Error>>(())
+    };
+
+    let timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let deadline = async {
+        shutdown.cancelled().await;
+        tokio::time::sleep(timeout).await;
+    };
 
-    axum::serve(listener, app).await?;
+    let result = tokio::select! {
+        result = drained => result,
+        _ = deadline => {
+            tracing::warn!(
+                "Shutdown timed out after {}s, abandoning in-flight work",
+                timeout.as_secs()
+            );
+            Ok(())
+        }
+    };
+
+    // Ends the database sessions instead of leaving Postgres to notice the
+    // dropped sockets. Abandoned work may still hold connections, so this
+    // doesn't wait on them for long.
+    if tokio::time::timeout(POOL_CLOSE_TIMEOUT, database.pool().close())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Closed the database pool with connections still in use");
+    }
+
+    result?;
+    tracing::info!("Shut down");
 
     Ok(())
 }
+
+/// Cancels `shutdown` on SIGINT (Ctrl+C) or SIGTERM.
+async fn cancel_on_signal(shutdown: CancellationToken) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+
+    tracing::info!("Shutdown signal received, draining");
+    shutdown.cancel();
+}
+
+/// `check-i18n [DIR]`: reports translation problems and fails if any of them
+/// would show users a raw message id or a broken message. Checks `DIR`, or
+/// the configured `i18n.dir`, on top of the bundled catalogs; in a checkout
+/// with neither, the crate's own catalogs and sources.
+fn check_i18n(dir: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = match dir {
+        Some(dir) => Some(dir),
+        None => Config::load_i18n()?.dir.map(PathBuf::from),
+    };
+    let cwd = std::env::current_dir()?;
+    let paths = match dir {
+        Some(dir) => CheckPaths::for_deployment(Some(&dir)),
+        None if cwd.join("src/i18n/locales").is_dir() => CheckPaths::for_crate(&cwd),
+        None => CheckPaths::for_deployment(None),
+    };
+    let report = check_catalogs(&paths)?;
+
+    for issue in &report.issues {
+        let level = if issue.is_error() { "error" } else { "warning" };
+        println!("{level}: {issue}");
+    }
+
+    let errors = report.errors().count();
+    println!(
+        "{} errors, {} warnings",
+        errors,
+        report.issues.len() - errors
+    );
+
+    if report.has_errors() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn log_disabled_features(config: &Config) {
+    if config.google.is_none() {
+        tracing::info!("Google sign-in is disabled");
+    }
+    if config.discord.is_none() {
+        tracing::info!("Discord sign-in is disabled");
+    }
+    if config.storage.is_none() {
+        tracing::info!("Media storage is disabled, OAuth avatars will not be mirrored");
+    }
+    if matches!(config.mail.transport, MailTransportConfig::Stdout) {
+        tracing::warn!("Mail transport is stdout, emails are printed instead of sent");
+    }
+    if !config.rate_limit.enabled {
+        tracing::warn!("Rate limiting is disabled");
+    }
+}
@@ -1,4 +1,7 @@
-use crate::error::AppError;
+use crate::error::{AppError, Message};
+use crate::i18n::{Locale, LocaleScope};
+use crate::middleware::locale::set_content_language;
+use crate::models::User;
 use crate::services::AuthService;
 use axum::{
     extract::{Request, State},
@@ -17,15 +20,16 @@ pub async fn auth_middleware(
         .get(AUTHORIZATION)
         .and_then(|header| header.to_str().ok())
         .and_then(|header| header.strip_prefix("Bearer "))
-        .ok_or_else(|| {
-            AppError::Authentication("Missing or invalid authorization header".to_string())
-        })?;
+        .ok_or_else(|| AppError::Authentication(Message::new("missing-authorization")))?;
 
     let user = auth_service.verify_token(auth_header).await?;
+    let locale = with_user_locale(&auth_service, &mut request, &user);
 
     request.extensions_mut().insert(user);
 
-    Ok(next.run(request).await)
+    let mut response = run_in_locale(&auth_service, &locale, next.run(request)).await;
+    set_content_language(&mut response, &locale, true);
+    Ok(response)
 }
 
 pub async fn optional_auth_middleware(
@@ -40,9 +44,49 @@ pub async fn optional_auth_middleware(
         .and_then(|header| header.strip_prefix("Bearer "))
     {
         if let Ok(user) = auth_service.verify_token(auth_header).await {
+            let locale = with_user_locale(&auth_service, &mut request, &user);
             request.extensions_mut().insert(user);
+
+            let mut response = run_in_locale(&auth_service, &locale, next.run(request)).await;
+            set_content_language(&mut response, &locale, true);
+            return response;
         }
     }
 
     next.run(request).await
 }
+
+/// Renegotiates the request's locale now that the user's stored one is known.
+fn with_user_locale(auth_service: &AuthService, request: &mut Request, user: &User) -> Locale {
+    let locale = request
+        .extensions()
+        .get::<Locale>()
+        .cloned()
+        .unwrap_or_default()
+        .with_user_locale(auth_service.i18n(), &user.locale);
+
+    request.extensions_mut().insert(locale.clone());
+    locale
+}
+
+async fn run_in_locale<F: std::future::Future>(
+    auth_service: &AuthService,
+    locale: &Locale,
+    future: F,
+) -> F::Output {
+    LocaleScope::run(auth_service.i18n().clone(), locale.clone(), future).await
+}
+
+/// Must run after `auth_middleware`, which puts the user in the request.
+pub async fn admin_middleware(request: Request, next: Next) -> Result<Response, AppError> {
+    let is_admin = request
+        .extensions()
+        .get::<User>()
+        .is_some_and(|user| user.can_admin());
+
+    if !is_admin {
+        return Err(AppError::Authorization(Message::new("admin-required")));
+    }
+
+    Ok(next.run(request).await)
+}
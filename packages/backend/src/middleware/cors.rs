@@ -0,0 +1,104 @@
+use crate::config::{CorsConfig, CorsPolicy};
+use crate::middleware::{
+    RATELIMIT_LIMIT, RATELIMIT_POLICY, RATELIMIT_REMAINING, RATELIMIT_RESET, X_REQUEST_ID,
+};
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::{Layer, ServiceExt};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// The configured CORS policies, ready to apply. Built once at startup;
+/// the config has been validated, so every value parses.
+#[derive(Clone)]
+pub struct CorsPolicies {
+    default: CorsLayer,
+    /// Longest path first, so the most specific override wins.
+    overrides: Arc<[(String, CorsLayer)]>,
+}
+
+impl CorsPolicies {
+    pub fn new(config: &CorsConfig) -> Self {
+        let mut overrides: Vec<_> = config
+            .overrides
+            .iter()
+            .map(|o| (o.path.clone(), cors_layer(&o.policy)))
+            .collect();
+        overrides.sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+
+        Self {
+            default: cors_layer(&config.policy),
+            overrides: overrides.into(),
+        }
+    }
+
+    fn for_path(&self, path: &str) -> &CorsLayer {
+        self.overrides
+            .iter()
+            .find(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .map_or(&self.default, |(_, layer)| layer)
+    }
+}
+
+fn cors_layer(policy: &CorsPolicy) -> CorsLayer {
+    let origin = if policy.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            policy
+                .allowed_origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin).expect("validated origin")),
+        )
+    };
+
+    CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods(
+            policy
+                .allowed_methods
+                .iter()
+                .map(|method| Method::from_bytes(method.as_bytes()).expect("validated method"))
+                .collect::<Vec<_>>(),
+        )
+        .allow_headers(
+            policy
+                .allowed_headers
+                .iter()
+                .map(|header| HeaderName::from_bytes(header.as_bytes()).expect("validated header"))
+                .collect::<Vec<_>>(),
+        )
+        .allow_credentials(policy.allow_credentials)
+        .expose_headers([
+            X_REQUEST_ID,
+            RATELIMIT_LIMIT,
+            RATELIMIT_REMAINING,
+            RATELIMIT_RESET,
+            RATELIMIT_POLICY,
+            RETRY_AFTER,
+        ])
+        .max_age(Duration::from_secs(policy.max_age_secs))
+}
+
+/// Applies the policy for the request path. Preflight requests are answered
+/// here, before routing, so it has to wrap the whole app.
+pub async fn cors_middleware(
+    State(policies): State<CorsPolicies>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let cors = policies.for_path(request.uri().path()).layer(next);
+
+    match cors.oneshot(request).await {
+        Ok(response) => response.into_response(),
+        Err(infallible) => match infallible {},
+    }
+}
@@ -0,0 +1,26 @@
+use crate::telemetry::record_http_request;
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+
+/// Records the request count and latency of matched routes. Add it with
+/// `route_layer` so the matched path is known.
+pub async fn http_metrics_middleware(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned());
+
+    let response = next.run(request).await;
+
+    if let Some(route) = route {
+        record_http_request(method.as_str(), &route, response.status().as_u16(), started);
+    }
+
+    response
+}
@@ -0,0 +1,35 @@
+use crate::i18n::{I18n, Locale, LocaleScope};
+use axum::{
+    extract::{Request, State},
+    http::{header::CONTENT_LANGUAGE, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+/// Negotiates the request's `Locale` and reports it in `Content-Language`.
+/// Errors are rendered in it. `auth_middleware` may refine it later, and its
+/// header then wins.
+pub async fn locale_middleware(State(i18n): State<I18n>, request: Request, next: Next) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let locale = Locale::negotiate(&i18n, &parts);
+    parts.extensions.insert(locale.clone());
+
+    let mut response = LocaleScope::run(
+        i18n,
+        locale.clone(),
+        next.run(Request::from_parts(parts, body)),
+    )
+    .await;
+    set_content_language(&mut response, &locale, false);
+    response
+}
+
+pub(crate) fn set_content_language(response: &mut Response, locale: &Locale, overwrite: bool) {
+    if !overwrite && response.headers().contains_key(CONTENT_LANGUAGE) {
+        return;
+    }
+
+    if let Ok(value) = HeaderValue::from_str(locale.as_str()) {
+        response.headers_mut().insert(CONTENT_LANGUAGE, value);
+    }
+}
@@ -1,3 +1,13 @@
 pub mod auth;
+pub mod cors;
+pub mod http_metrics;
+pub mod locale;
+pub mod rate_limit;
+pub mod request_id;
 
 pub use auth::*;
+pub use cors::*;
+pub use http_metrics::*;
+pub use locale::*;
+pub use rate_limit::*;
+pub use request_id::*;
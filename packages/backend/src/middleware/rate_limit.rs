@@ -0,0 +1,109 @@
+use crate::config::RateLimitPolicyName;
+use crate::error::AppError;
+use crate::services::{RateLimitDecision, RateLimiter};
+use crate::telemetry::record_rate_limited;
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+pub const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+pub const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+pub const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+pub const RATELIMIT_POLICY: HeaderName = HeaderName::from_static("ratelimit-policy");
+
+/// Limits the routes it wraps with one named policy. Get one from
+/// `RateLimiter::layer` and attach it with `route_layer`.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: RateLimiter,
+    policy: RateLimitPolicyName,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: RateLimiter, policy: RateLimitPolicyName) -> Self {
+        Self { limiter, policy }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+            policy: self.policy,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: RateLimiter,
+    policy: RateLimitPolicyName,
+}
+
+impl<S> Service<Request> for RateLimit<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The clone may not be ready; keep the instance `poll_ready` was
+        // called on.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        let policy = self.policy;
+
+        Box::pin(async move {
+            let decision = match limiter.key(policy, &request) {
+                Some(key) => limiter.acquire(policy, &key).await,
+                None => None,
+            };
+
+            let mut response = match decision {
+                Some(decision) if !decision.allowed => {
+                    record_rate_limited(policy.as_str());
+                    AppError::RateLimited(decision.retry_after_secs).into_response()
+                }
+                _ => inner.call(request).await?,
+            };
+
+            if let Some(decision) = decision {
+                set_rate_limit_headers(response.headers_mut(), &decision);
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// The `RateLimit-*` headers from the IETF draft, with the reset as
+/// seconds from now.
+fn set_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    let number = |value: u64| HeaderValue::from(value);
+
+    headers.insert(RATELIMIT_LIMIT, number(decision.limit.into()));
+    headers.insert(RATELIMIT_REMAINING, number(decision.remaining.into()));
+    headers.insert(RATELIMIT_RESET, number(decision.reset_secs));
+    headers.insert(
+        RATELIMIT_POLICY,
+        HeaderValue::from_str(&format!("{};w={}", decision.limit, decision.window_secs))
+            .expect("digits and separators are valid header characters"),
+    );
+}
@@ -0,0 +1,126 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::fmt;
+use std::future::Future;
+use tracing::Span;
+use uuid::Uuid;
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest caller-supplied id we keep; longer ones are replaced.
+const MAX_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+/// Identifies one request across logs and error reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    /// Accepts a caller's id if it is short printable ASCII.
+    pub fn parse(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?;
+        let valid = !value.is_empty()
+            && value.len() <= MAX_LENGTH
+            && value.chars().all(|c| c.is_ascii_graphic());
+
+        valid.then(|| Self(value.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The id of the request being handled, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT_REQUEST_ID.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `future` as part of the request with this id, such as work the
+    /// request spawned.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_REQUEST_ID.scope(self, future).await
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<RequestId> for String {
+    fn from(id: RequestId) -> Self {
+        id.0
+    }
+}
+
+impl From<&RequestId> for HeaderValue {
+    fn from(id: &RequestId) -> Self {
+        // `parse` and `generate` only produce visible ASCII.
+        HeaderValue::from_str(&id.0).expect("request ids are valid header values")
+    }
+}
+
+/// Forwards the current request's id on an outgoing call, so the provider's
+/// logs and ours can be matched up.
+pub trait PropagateRequestId {
+    fn propagate_request_id(self) -> Self;
+}
+
+impl PropagateRequestId for reqwest::RequestBuilder {
+    fn propagate_request_id(self) -> Self {
+        match RequestId::current() {
+            // reqwest is on a different `http` major than axum, so go through strings.
+            Some(id) => self.header(X_REQUEST_ID.as_str(), id.as_str()),
+            None => self,
+        }
+    }
+}
+
+/// The `TraceLayer` span for a request, tagged with its id so every log line
+/// written while handling it can be found by the id.
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(RequestId::as_str)
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id,
+    )
+}
+
+/// Keeps the caller's `X-Request-Id` or generates one, makes it available to
+/// the rest of the request and echoes it on the response.
+pub async fn request_id_middleware(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(RequestId::parse)
+        .unwrap_or_else(RequestId::generate);
+
+    request.extensions_mut().insert(request_id.clone());
+
+    let header = HeaderValue::from(&request_id);
+    let mut response = CURRENT_REQUEST_ID
+        .scope(request_id, next.run(request))
+        .await;
+    response.headers_mut().insert(X_REQUEST_ID, header);
+
+    response
+}
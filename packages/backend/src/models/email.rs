@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Delivery state of an outbox message. `Failed` is the dead-letter state
+/// reached once every retry has been used up.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, ToSchema)]
+#[sqlx(type_name = "email_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum EmailStatus {
+    Pending,
+    Sending,
+    Sent,
+    Failed,
+    /// Dropped because the recipient is suppressed or opted out.
+    Suppressed,
+}
+
+/// What an email is about. Users choose per category whether they want it,
+/// except for security mail, which is always sent.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Hash, ToSchema)]
+#[sqlx(type_name = "email_category", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum EmailCategory {
+    Security,
+    ProductNews,
+    NewChapters,
+    Replies,
+    Digests,
+}
+
+impl EmailCategory {
+    pub const ALL: [EmailCategory; 5] = [
+        EmailCategory::Security,
+        EmailCategory::ProductNews,
+        EmailCategory::NewChapters,
+        EmailCategory::Replies,
+        EmailCategory::Digests,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailCategory::Security => "security",
+            EmailCategory::ProductNews => "product_news",
+            EmailCategory::NewChapters => "new_chapters",
+            EmailCategory::Replies => "replies",
+            EmailCategory::Digests => "digests",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.as_str() == value)
+    }
+
+    pub fn can_unsubscribe(&self) -> bool {
+        !matches!(self, EmailCategory::Security)
+    }
+
+    /// Used when the user never changed the preference. Product news is
+    /// opt-in.
+    pub fn enabled_by_default(&self) -> bool {
+        !matches!(self, EmailCategory::ProductNews)
+    }
+}
+
+/// A rendered email that has not been queued yet.
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: Option<String>,
+    pub category: EmailCategory,
+    /// RFC 8058 one-click unsubscribe endpoint, set for categories users can
+    /// opt out of.
+    pub unsubscribe_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    pub to_email: String,
+    pub subject: String,
+    #[serde(skip_serializing)]
+    pub html_body: String,
+    #[serde(skip_serializing)]
+    pub text_body: Option<String>,
+    pub category: EmailCategory,
+    #[serde(skip_serializing)]
+    pub unsubscribe_url: Option<String>,
+    pub status: EmailStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    #[serde(skip_serializing)]
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub sent_at: Option<DateTime<Utc>>,
+    /// The `X-Request-Id` of the request that queued the email.
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&OutboxEmail> for OutgoingEmail {
+    fn from(email: &OutboxEmail) -> Self {
+        Self {
+            to: email.to_email.clone(),
+            subject: email.subject.clone(),
+            html_body: email.html_body.clone(),
+            text_body: email.text_body.clone(),
+            category: email.category,
+            unsubscribe_url: email.unsubscribe_url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OutboxQuery {
+    pub status: Option<EmailStatus>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EmailPreference {
+    pub category: EmailCategory,
+    pub enabled: bool,
+    /// Set for categories that cannot be turned off.
+    pub locked: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateEmailPreferencesRequest {
+    pub preferences: HashMap<EmailCategory, bool>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdateDigestScheduleRequest {
+    /// ISO weekday, 1 = Monday.
+    #[validate(range(min = 1, max = 7, code = "digest-day-range"))]
+    pub day: i16,
+    /// IANA time zone name, e.g. `Europe/Istanbul`.
+    pub timezone: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UnsubscribeQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UnsubscribeResponse {
+    /// The category that was turned off.
+    pub category: EmailCategory,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuppressionQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct EmailSuppression {
+    pub email: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateSuppressionRequest {
+    #[validate(email(code = "invalid-email"))]
+    pub email: String,
+    #[validate(length(min = 1, max = 50, code = "suppression-reason-length"))]
+    pub reason: String,
+}
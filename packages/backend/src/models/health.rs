@@ -0,0 +1,50 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+}
+
+/// Why a check failed. The details are only logged, since `/readyz` is
+/// public.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckFailure {
+    Error,
+    Timeout,
+}
+
+/// The outcome of one readiness check.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<CheckFailure>,
+}
+
+/// Body of `/readyz`. The instance is ready when every check passed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub status: CheckStatus,
+    pub checks: BTreeMap<&'static str, CheckResult>,
+}
+
+impl ReadinessReport {
+    pub fn new(checks: BTreeMap<&'static str, CheckResult>) -> Self {
+        let status = if checks.values().all(|check| check.status == CheckStatus::Ok) {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Failed
+        };
+
+        Self { status, checks }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status == CheckStatus::Ok
+    }
+}
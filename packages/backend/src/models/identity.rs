@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserIdentity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub provider_id: String,
+    pub email: String,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AccountLinkRequest {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub link_token: String,
+    pub email_token: Option<String>,
+    pub provider: String,
+    pub provider_id: String,
+    pub provider_email: String,
+    pub provider_email_verified: bool,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Profile returned by an OAuth provider after a successful code exchange.
+#[derive(Debug, Clone)]
+pub struct OAuthProfile {
+    pub provider: String,
+    pub provider_id: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub username: String,
+    pub display_name: Option<String>,
+    /// Provider-hosted avatar; mirrored into our storage, never stored as is.
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+}
+
+/// Where to send the browser to sign in, and the value it must carry back
+/// in the binding cookie so the callback is only accepted from the browser
+/// that started the flow.
+#[derive(Debug)]
+pub struct OAuthAuthorization {
+    pub url: String,
+    pub binding: String,
+}
+
+/// Result of an OAuth login. A successful login yields a one-time code for the
+/// frontend to exchange. When the provider email belongs to an existing
+/// account that could not be linked automatically, the user has to prove
+/// ownership of that account before the identity is attached; the frontend
+/// exchanges `link_code` for the pending link.
+#[derive(Debug)]
+pub enum OAuthLoginOutcome {
+    Authenticated { exchange_code: String },
+    LinkRequired { link_code: String },
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LinkWithPasswordRequest {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LinkTokenRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OAuthExchangeRequest {
+    pub code: String,
+}
+
+/// A link request waiting for the user to prove they own `email`'s account.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PendingLinkResponse {
+    pub token: String,
+    pub email: String,
+}
+
+/// Which OAuth sign-in options this server has configured.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuthProvidersResponse {
+    pub google: bool,
+    pub discord: bool,
+}
@@ -1,3 +1,13 @@
+pub mod email;
+pub mod health;
+pub mod identity;
+pub mod rate_limit;
+pub mod series;
 pub mod user;
 
+pub use email::*;
+pub use health::*;
+pub use identity::*;
+pub use rate_limit::*;
+pub use series::*;
 pub use user::*;
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Series {
+    pub id: Uuid,
+    pub title: String,
+    pub slug: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Chapter {
+    pub id: Uuid,
+    pub series_id: Uuid,
+    pub number: f64,
+    pub title: Option<String>,
+    pub published_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A new chapter of a followed series, as collected for the weekly digest.
+#[derive(Debug, Clone, FromRow)]
+pub struct DigestChapter {
+    pub series_title: String,
+    pub series_slug: String,
+    pub number: f64,
+    pub title: Option<String>,
+    pub published_at: DateTime<Utc>,
+}
+
+/// One series in a digest email.
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestSeries {
+    pub title: String,
+    pub url: String,
+    pub chapters: Vec<DigestEntry>,
+    /// New chapters left out of the email to keep it short.
+    pub more: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestEntry {
+    pub number: String,
+    pub title: Option<String>,
+}
@@ -0,0 +1,117 @@
+use crate::error::{FieldProblem, ProblemDetails};
+use crate::handlers;
+use crate::models::{
+    AuthProvidersResponse, AuthResponse, CreateRateLimitExemptionRequest, CreateSuppressionRequest,
+    EmailCategory, EmailPreference, EmailStatus, EmailSuppression, ForgotPasswordRequest,
+    LinkTokenRequest, LinkWithPasswordRequest, LoginRequest, MessageResponse, OAuthExchangeRequest,
+    OutboxEmail, PendingLinkResponse, RateLimitExemption, RegisterRequest, RegisterResponse,
+    ResendVerificationRequest, ResetPasswordRequest, UnsubscribeResponse,
+    UpdateDigestScheduleRequest, UpdateEmailPreferencesRequest, UpdateLocaleRequest, UserResponse,
+    UserRole, VerifyEmailRequest,
+};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// The OpenAPI document for `/api/v1`, served at `/api/v1/openapi.json`.
+///
+/// Every handler mounted under `routes` must be listed in `paths`;
+/// `tests/openapi.rs` fails otherwise.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Backend API",
+        description = "Errors are `application/problem+json` documents; see `docs/errors.md` for the codes."
+    ),
+    paths(
+        handlers::register,
+        handlers::login,
+        handlers::verify_email,
+        handlers::resend_verification,
+        handlers::forgot_password,
+        handlers::reset_password,
+        handlers::me,
+        handlers::logout,
+        handlers::update_locale,
+        handlers::providers,
+        handlers::google_auth,
+        handlers::google_callback,
+        handlers::discord_auth,
+        handlers::discord_callback,
+        handlers::oauth_exchange,
+        handlers::link_exchange,
+        handlers::link_with_password,
+        handlers::send_link_confirmation,
+        handlers::confirm_link_email,
+        handlers::unsubscribe_page,
+        handlers::unsubscribe,
+        handlers::get_preferences,
+        handlers::update_preferences,
+        handlers::update_digest_schedule,
+        handlers::list_emails,
+        handlers::get_email,
+        handlers::retry_email,
+        handlers::list_suppressions,
+        handlers::create_suppression,
+        handlers::delete_suppression,
+        handlers::list_rate_limit_exemptions,
+        handlers::create_rate_limit_exemption,
+        handlers::delete_rate_limit_exemption,
+    ),
+    components(schemas(
+        ProblemDetails,
+        FieldProblem,
+        UserRole,
+        UserResponse,
+        RegisterRequest,
+        RegisterResponse,
+        LoginRequest,
+        AuthResponse,
+        VerifyEmailRequest,
+        ResendVerificationRequest,
+        ForgotPasswordRequest,
+        ResetPasswordRequest,
+        UpdateLocaleRequest,
+        MessageResponse,
+        OAuthExchangeRequest,
+        PendingLinkResponse,
+        AuthProvidersResponse,
+        LinkWithPasswordRequest,
+        LinkTokenRequest,
+        EmailCategory,
+        EmailStatus,
+        EmailPreference,
+        UpdateEmailPreferencesRequest,
+        UpdateDigestScheduleRequest,
+        UnsubscribeResponse,
+        OutboxEmail,
+        EmailSuppression,
+        CreateSuppressionRequest,
+        RateLimitExemption,
+        CreateRateLimitExemptionRequest,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Accounts, sessions and OAuth sign-in"),
+        (name = "email", description = "Email preferences and unsubscribing"),
+        (name = "admin", description = "Outbox, suppression list and rate limit exemptions; administrators only"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Registers the `bearer_auth` scheme referenced by protected paths.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use crate::config::RateLimitPolicyName;
+use crate::handlers::admin::*;
+use crate::middleware::auth::{admin_middleware, auth_middleware};
+use crate::state::AppState;
+use axum::{
+    middleware,
+    routing::{delete, get, post},
+    Router,
+};
+
+pub fn create_admin_routes(app_state: AppState) -> Router {
+    Router::new()
+        .route("/emails", get(list_emails))
+        .route("/emails/:id", get(get_email))
+        .route("/emails/:id/retry", post(retry_email))
+        .route(
+            "/email-suppressions",
+            get(list_suppressions).post(create_suppression),
+        )
+        .route("/email-suppressions/:email", delete(delete_suppression))
+        .route(
+            "/rate-limit-exemptions",
+            get(list_rate_limit_exemptions).post(create_rate_limit_exemption),
+        )
+        .route(
+            "/rate-limit-exemptions/:user_id",
+            delete(delete_rate_limit_exemption),
+        )
+        .route_layer(middleware::from_fn(admin_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.auth_service.clone(),
+            auth_middleware,
+        ))
+        .route_layer(
+            app_state
+                .rate_limiter
+                .layer(RateLimitPolicyName::ReadDefault),
+        )
+        .with_state(app_state)
+}
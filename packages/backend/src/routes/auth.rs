@@ -1,57 +1,49 @@
-use crate::config::Config;
-use crate::database::Database;
+use crate::config::RateLimitPolicyName;
 use crate::handlers::auth::*;
 use crate::middleware::auth::auth_middleware;
-use crate::services::auth::AuthService;
-use crate::services::oauth::OAuthService;
+use crate::state::AppState;
 use axum::{
     middleware,
     routing::{get, post},
     Router,
 };
 
-#[derive(Clone)]
-pub struct AppState {
-    pub auth_service: AuthService,
-    pub oauth_service: OAuthService,
-    pub config: Config,
-}
-
-pub fn create_auth_routes(db: Database) -> Router {
-    let config = Config::from_env().expect("Failed to load configuration");
-
-    let auth_service =
-        AuthService::new(db.clone(), config.clone()).expect("Failed to create auth service");
-
-    let oauth_service =
-        OAuthService::new(db, config.clone()).expect("Failed to create oauth service");
-
-    let app_state = AppState {
-        auth_service: auth_service.clone(),
-        oauth_service,
-        config: config.clone(),
-    };
+pub fn create_auth_routes(app_state: AppState) -> Router {
+    let rate_limiter = &app_state.rate_limiter;
 
     let protected_routes = Router::new()
         .route("/me", get(me))
         .route("/logout", post(logout))
         .route("/update-locale", post(update_locale))
         .route_layer(middleware::from_fn_with_state(
-            auth_service,
+            app_state.auth_service.clone(),
             auth_middleware,
-        ));
+        ))
+        .route_layer(rate_limiter.layer(RateLimitPolicyName::ReadDefault));
 
-    Router::new()
+    // Endpoints that check passwords or tokens, or send mail.
+    let strict_routes = Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
         .route("/verify-email", post(verify_email))
         .route("/resend-verification", post(resend_verification))
         .route("/forgot-password", post(forgot_password))
         .route("/reset-password", post(reset_password))
+        .route("/oauth/exchange", post(oauth_exchange))
+        .route("/link/exchange", post(link_exchange))
+        .route("/link/password", post(link_with_password))
+        .route("/link/email", post(send_link_confirmation))
+        .route("/link/confirm", post(confirm_link_email))
+        .route_layer(rate_limiter.layer(RateLimitPolicyName::AuthStrict));
+
+    Router::new()
+        .route("/providers", get(providers))
         .route("/google", get(google_auth))
         .route("/google/callback", get(google_callback))
         .route("/discord", get(discord_auth))
         .route("/discord/callback", get(discord_callback))
+        .route_layer(rate_limiter.layer(RateLimitPolicyName::ReadDefault))
+        .merge(strict_routes)
         .merge(protected_routes)
         .with_state(app_state)
 }
@@ -0,0 +1,29 @@
+use crate::config::RateLimitPolicyName;
+use crate::handlers::email::*;
+use crate::middleware::auth::auth_middleware;
+use crate::state::AppState;
+use axum::{
+    middleware,
+    routing::{get, put},
+    Router,
+};
+
+pub fn create_email_routes(app_state: AppState) -> Router {
+    let protected_routes = Router::new()
+        .route("/preferences", get(get_preferences).put(update_preferences))
+        .route("/digest", put(update_digest_schedule))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.auth_service.clone(),
+            auth_middleware,
+        ));
+
+    Router::new()
+        .route("/unsubscribe", get(unsubscribe_page).post(unsubscribe))
+        .merge(protected_routes)
+        .route_layer(
+            app_state
+                .rate_limiter
+                .layer(RateLimitPolicyName::ReadDefault),
+        )
+        .with_state(app_state)
+}
@@ -1,8 +1,24 @@
+pub mod admin;
 pub mod auth;
+pub mod email;
+pub mod monitoring;
 
-use crate::database::Database;
-use axum::Router;
+use crate::middleware::locale_middleware;
+use crate::openapi::ApiDoc;
+use crate::state::AppState;
+use axum::{middleware, routing::get, Json, Router};
+use utoipa::OpenApi;
+use utoipa_scalar::{Scalar, Servable};
 
-pub fn create_routes(db: Database) -> Router {
-    Router::new().nest("/auth", auth::create_auth_routes(db))
+pub fn create_routes(app_state: AppState) -> Router {
+    Router::new()
+        .nest("/auth", auth::create_auth_routes(app_state.clone()))
+        .nest("/email", email::create_email_routes(app_state.clone()))
+        .nest("/admin", admin::create_admin_routes(app_state.clone()))
+        .route("/openapi.json", get(|| async { Json(ApiDoc::openapi()) }))
+        .merge(Scalar::with_url("/docs", ApiDoc::openapi()))
+        .layer(middleware::from_fn_with_state(
+            app_state.i18n,
+            locale_middleware,
+        ))
 }
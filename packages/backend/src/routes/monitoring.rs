@@ -0,0 +1,72 @@
+use crate::database::Database;
+use crate::handlers::monitoring::*;
+use crate::state::AppState;
+use crate::telemetry::prometheus;
+use crate::utils::EmailService;
+use axum::{routing::get, Router};
+use metrics_exporter_prometheus::PrometheusHandle;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct HealthState {
+    pub db: Database,
+    /// Set when `/readyz` should probe the mail transport.
+    pub email_service: Option<EmailService>,
+}
+
+#[derive(Clone)]
+pub struct MetricsState {
+    pub db: Database,
+    pub prometheus: PrometheusHandle,
+    pub metrics_token: MetricsToken,
+}
+
+/// The bearer token scrapers must present.
+#[derive(Clone)]
+pub struct MetricsToken(Arc<str>);
+
+impl MetricsToken {
+    /// Compares in constant time so the token can't be guessed byte by byte.
+    pub fn matches(&self, candidate: &str) -> bool {
+        let expected = self.0.as_bytes();
+        let candidate = candidate.as_bytes();
+
+        expected.len() == candidate.len()
+            && expected
+                .iter()
+                .zip(candidate)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+/// Operational endpoints, served at the root rather than under `/api/v1`
+/// and without authentication, locale negotiation or rate limits. `/metrics`
+/// is left out unless a metrics token is configured.
+pub fn create_monitoring_routes(state: &AppState) -> Router {
+    let health_routes = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(HealthState {
+            db: state.db.clone(),
+            email_service: state
+                .config
+                .health
+                .check_mail
+                .then(|| state.email_service.clone()),
+        });
+
+    let Some(token) = &state.config.metrics.token else {
+        return health_routes;
+    };
+
+    let metrics_routes = Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(MetricsState {
+            db: state.db.clone(),
+            prometheus: prometheus(),
+            metrics_token: MetricsToken(token.as_str().into()),
+        });
+
+    health_routes.merge(metrics_routes)
+}
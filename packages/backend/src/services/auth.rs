@@ -1,14 +1,16 @@
 use crate::config::Config;
 use crate::database::Database;
-use crate::error::{AppError, Result};
+use crate::error::{AppError, Message, Result};
 use crate::i18n::I18n;
 use crate::models::{AuthResponse, LoginRequest, RegisterRequest, User, UserResponse};
-use crate::services::UserService;
+use crate::services::{EmailOutbox, UserService};
 use crate::utils::{validate_request, verify_password, EmailService, JwtService};
 
 #[derive(Clone)]
 pub struct AuthService {
+    db: Database,
     user_service: UserService,
+    email_outbox: EmailOutbox,
     jwt_service: JwtService,
     email_service: EmailService,
     config: Config,
@@ -16,78 +18,93 @@ pub struct AuthService {
 }
 
 impl AuthService {
-    pub fn new(db: Database, config: Config) -> Result<Self> {
-        let user_service = UserService::new(db);
-        let jwt_service = JwtService::new(&config.jwt_secret);
-        let email_service = EmailService::new(&config.smtp)?;
-        let i18n = I18n::new();
-
-        Ok(Self {
+    pub fn new(
+        db: Database,
+        config: Config,
+        user_service: UserService,
+        jwt_service: JwtService,
+        email_service: EmailService,
+        i18n: I18n,
+    ) -> Self {
+        Self {
+            email_outbox: EmailOutbox::new(db.clone()),
+            db,
             user_service,
             jwt_service,
             email_service,
             config,
             i18n,
-        })
+        }
+    }
+
+    pub fn i18n(&self) -> &I18n {
+        &self.i18n
     }
 
-    pub async fn register(&self, request: RegisterRequest, _locale: &str) -> Result<UserResponse> {
+    /// New accounts keep the locale they picked, or get the one negotiated
+    /// for the request.
+    pub async fn register(
+        &self,
+        mut request: RegisterRequest,
+        locale: &str,
+    ) -> Result<UserResponse> {
         validate_request(&request)?;
 
-        let user = self.user_service.create_user(request).await?;
+        request.locale = Some(
+            request
+                .locale
+                .as_deref()
+                .and_then(|requested| self.i18n.resolve(requested))
+                .unwrap_or_else(|| locale.to_string()),
+        );
+
+        let mut tx = self.db.pool().begin().await?;
+
+        let user = self.user_service.create_user(&mut tx, request).await?;
 
         if let Some(verification_token) = &user.verification_token {
-            self.email_service
-                .send_verification_email(
-                    &user.email,
-                    &user.username,
-                    verification_token,
-                    &self.config.frontend_url,
-                )
-                .await
-                .map_err(|e| {
-                    tracing::error!("Failed to send verification email: {:?}", e);
-                    AppError::Internal(anyhow::anyhow!("Failed to send verification email"))
-                })?;
+            let email = self.email_service.verification_email(
+                &user,
+                verification_token,
+                &self.config.frontend_url,
+            )?;
+            self.email_outbox.enqueue(&mut tx, &email).await?;
         }
 
+        tx.commit().await?;
+
         Ok(user.into())
     }
 
-    pub async fn login(&self, request: LoginRequest, locale: &str) -> Result<AuthResponse> {
+    pub async fn login(&self, request: LoginRequest) -> Result<AuthResponse> {
         validate_request(&request)?;
 
         let user = self
             .user_service
             .find_by_email(&request.email)
             .await?
-            .ok_or_else(|| {
-                AppError::Authentication(self.i18n.get_message(locale, "invalid-credentials", None))
-            })?;
+            .ok_or_else(|| AppError::Authentication(Message::new("invalid-credentials")))?;
 
         if user.provider != "local" {
-            return Err(AppError::Authentication(
-                "Please use OAuth login for this account".to_string(),
-            ));
+            return Err(AppError::Authentication(Message::new(
+                "oauth-login-required",
+            )));
         }
 
-        let password_hash = user.password_hash.as_ref().ok_or_else(|| {
-            AppError::Authentication(self.i18n.get_message(locale, "invalid-credentials", None))
-        })?;
+        let password_hash = user
+            .password_hash
+            .as_ref()
+            .ok_or_else(|| AppError::Authentication(Message::new("invalid-credentials")))?;
 
         if !verify_password(&request.password, password_hash)? {
-            return Err(AppError::Authentication(self.i18n.get_message(
-                locale,
+            return Err(AppError::Authentication(Message::new(
                 "invalid-credentials",
-                None,
             )));
         }
 
         if !user.is_verified {
-            return Err(AppError::Authentication(self.i18n.get_message(
-                locale,
+            return Err(AppError::Authentication(Message::new(
                 "account-not-verified",
-                None,
             )));
         }
 
@@ -99,88 +116,80 @@ impl AuthService {
         })
     }
 
-    pub async fn verify_email(&self, token: &str, locale: &str) -> Result<()> {
+    pub async fn verify_email(&self, token: &str) -> Result<()> {
         let user = self
             .user_service
             .find_by_verification_token(token)
             .await?
-            .ok_or_else(|| {
-                AppError::Authentication(self.i18n.get_message(locale, "invalid-token", None))
-            })?;
+            .ok_or_else(|| AppError::Authentication(Message::new("invalid-token")))?;
 
         self.user_service.verify_email(user.id).await?;
 
         Ok(())
     }
 
-    pub async fn resend_verification(&self, email: &str, locale: &str) -> Result<()> {
+    pub async fn resend_verification(&self, email: &str) -> Result<()> {
         let user = self
             .user_service
             .find_by_email(email)
             .await?
-            .ok_or_else(|| {
-                AppError::NotFound(self.i18n.get_message(locale, "user-not-found", None))
-            })?;
+            .ok_or_else(|| AppError::NotFound(Message::new("user-not-found")))?;
 
         if user.is_verified {
-            return Err(AppError::Validation(
-                "Email is already verified".to_string(),
-            ));
+            return Err(AppError::Validation(Message::new("email-already-verified")));
         }
 
-        let verification_token = self.user_service.update_verification_token(user.id).await?;
+        let mut tx = self.db.pool().begin().await?;
 
-        self.email_service
-            .send_verification_email(
-                &user.email,
-                &user.username,
-                &verification_token,
-                &self.config.frontend_url,
-            )
+        let verification_token = self
+            .user_service
+            .update_verification_token(&mut tx, user.id)
             .await?;
 
+        let email = self.email_service.verification_email(
+            &user,
+            &verification_token,
+            &self.config.frontend_url,
+        )?;
+        self.email_outbox.enqueue(&mut tx, &email).await?;
+
+        tx.commit().await?;
+
         Ok(())
     }
 
-    pub async fn forgot_password(&self, email: &str, locale: &str) -> Result<()> {
-        if let Some(user) = self.user_service.find_by_email(email).await? {
-            if user.provider != "local" {
-                return Err(AppError::Validation(self.i18n.get_message(
-                    locale,
-                    "oauth-password-reset-not-allowed",
-                    None,
-                )));
-            }
+    pub async fn forgot_password(&self, email: &str) -> Result<()> {
+        let Some(user) = self.user_service.find_by_email(email).await? else {
+            return Ok(());
+        };
+
+        if user.provider != "local" {
+            return Err(AppError::Validation(Message::new(
+                "oauth-password-reset-not-allowed",
+            )));
         }
 
-        if let Some(reset_token) = self.user_service.create_reset_token(email).await? {
-            let user = self.user_service.find_by_email(email).await?.unwrap();
-
-            self.email_service
-                .send_password_reset_email(
-                    &user.email,
-                    &user.username,
-                    &reset_token,
-                    &self.config.frontend_url,
-                )
-                .await?;
+        let mut tx = self.db.pool().begin().await?;
+
+        if let Some(reset_token) = self.user_service.create_reset_token(&mut tx, email).await? {
+            let email = self.email_service.password_reset_email(
+                &user,
+                &reset_token,
+                &self.config.frontend_url,
+            )?;
+            self.email_outbox.enqueue(&mut tx, &email).await?;
         }
 
+        tx.commit().await?;
+
         Ok(())
     }
 
-    pub async fn reset_password(
-        &self,
-        token: &str,
-        new_password: &str,
-        locale: &str,
-    ) -> Result<()> {
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<()> {
         if new_password.len() < 8 {
-            return Err(AppError::Validation(self.i18n.get_message(
-                locale,
-                "password-too-short",
-                None,
-            )));
+            return Err(AppError::Validation(
+                Message::new("password-too-short").arg("min", 8),
+            ));
         }
 
         let success = self
@@ -189,11 +198,7 @@ impl AuthService {
             .await?;
 
         if !success {
-            return Err(AppError::Authentication(self.i18n.get_message(
-                locale,
-                "invalid-token",
-                None,
-            )));
+            return Err(AppError::Authentication(Message::new("invalid-token")));
         }
 
         Ok(())
@@ -206,13 +211,25 @@ impl AuthService {
             .user_service
             .find_by_id(claims.user_id)
             .await?
-            .ok_or_else(|| AppError::Authentication("User not found".to_string()))?;
+            .ok_or_else(|| AppError::Authentication(Message::new("user-not-found")))?;
 
         Ok(user)
     }
 
-    pub async fn update_locale(&self, user_id: uuid::Uuid, locale: &str) -> Result<crate::models::UserResponse> {
-        let user = self.user_service.update_locale(user_id, locale).await?;
+    pub async fn update_locale(
+        &self,
+        user_id: uuid::Uuid,
+        new_locale: &str,
+    ) -> Result<crate::models::UserResponse> {
+        let new_locale = self
+            .i18n
+            .resolve(new_locale)
+            .ok_or_else(|| AppError::Validation(Message::new("unsupported-locale")))?;
+
+        let user = self
+            .user_service
+            .update_locale(user_id, &new_locale)
+            .await?;
         Ok(user.into())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,262 @@
+use crate::database::Database;
+use crate::error::{AppError, Message, Result};
+use crate::middleware::{PropagateRequestId, RequestId};
+use crate::services::MediaStorage;
+use image::imageops::FilterType;
+use image::{ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Span};
+use uuid::Uuid;
+
+const MAX_AVATAR_BYTES: usize = 5 * 1024 * 1024;
+const MAX_SOURCE_DIMENSION: u32 = 4096;
+const AVATAR_SIZE: u32 = 256;
+
+/// Mirrors OAuth provider avatars into our own media storage so we never
+/// hotlink provider CDNs.
+#[derive(Clone)]
+pub struct AvatarService {
+    db: Database,
+    storage: MediaStorage,
+    http_client: reqwest::Client,
+}
+
+#[derive(sqlx::FromRow)]
+struct AvatarState {
+    avatar_url: Option<String>,
+    avatar_upstream_url: Option<String>,
+    avatar_provider: Option<String>,
+}
+
+impl AvatarService {
+    pub fn new(db: Database, storage: MediaStorage, http_client: reqwest::Client) -> Self {
+        Self {
+            db,
+            storage,
+            http_client,
+        }
+    }
+
+    /// Mirrors the avatar in the background. Failures are logged and leave
+    /// the current avatar untouched.
+    pub fn schedule_sync(&self, user_id: Uuid, provider: &str, upstream_url: Option<&str>) {
+        let Some(upstream_url) = upstream_url.map(str::to_string) else {
+            return;
+        };
+
+        let service = self.clone();
+        let provider = provider.to_string();
+        let sync = async move {
+            if let Err(e) = service.sync(user_id, &provider, &upstream_url).await {
+                tracing::warn!(
+                    "Failed to mirror {} avatar for {}: {}",
+                    provider,
+                    user_id,
+                    e
+                );
+            }
+        };
+
+        // Logged and fetched as part of the request that triggered it.
+        let request_id = RequestId::current();
+        tokio::spawn(
+            async move {
+                match request_id {
+                    Some(request_id) => request_id.scope(sync).await,
+                    None => sync.await,
+                }
+            }
+            .instrument(Span::current()),
+        );
+    }
+
+    /// Mirrors the avatars that have an upstream URL but were never copied,
+    /// such as the provider URLs kept when mirroring was introduced.
+    pub fn spawn_backfill(self, shutdown: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            match self.mirror_pending(&shutdown).await {
+                Ok(0) => {}
+                Ok(mirrored) => tracing::info!("Mirrored {} pending avatars", mirrored),
+                Err(e) => tracing::error!("Avatar backfill failed: {:?}", e),
+            }
+        })
+    }
+
+    /// Each pending avatar is tried once; after a failure it waits for the
+    /// user's next login. Returns how many were mirrored.
+    pub async fn mirror_pending(&self, shutdown: &CancellationToken) -> Result<usize> {
+        let pending = sqlx::query_as::<_, (Uuid, String, String)>(
+            r#"
+            SELECT id, avatar_provider, avatar_upstream_url FROM users
+            WHERE avatar_url IS NULL AND avatar_synced_at IS NULL
+              AND avatar_upstream_url IS NOT NULL AND avatar_provider IS NOT NULL
+            "#,
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut mirrored = 0;
+        for (user_id, provider, upstream_url) in pending {
+            if shutdown.is_cancelled() {
+                break;
+            }
+
+            match self.sync(user_id, &provider, &upstream_url).await {
+                Ok(()) => mirrored += 1,
+                Err(e) => {
+                    tracing::warn!("Failed to mirror pending avatar for {}: {}", user_id, e);
+                    sqlx::query("UPDATE users SET avatar_synced_at = NOW() WHERE id = $1")
+                        .bind(user_id)
+                        .execute(self.db.pool())
+                        .await?;
+                }
+            }
+        }
+
+        Ok(mirrored)
+    }
+
+    /// Downloads, validates and stores the upstream avatar unless it is
+    /// unchanged since the last sync. An avatar mirrored from one provider is
+    /// not replaced by logins through another.
+    pub async fn sync(&self, user_id: Uuid, provider: &str, upstream_url: &str) -> Result<()> {
+        let state = sqlx::query_as::<_, AvatarState>(
+            "SELECT avatar_url, avatar_upstream_url, avatar_provider FROM users WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.pool())
+        .await?
+        .ok_or_else(|| AppError::NotFound(Message::new("user-not-found")))?;
+
+        if state
+            .avatar_provider
+            .as_deref()
+            .is_some_and(|current| current != provider)
+        {
+            return Ok(());
+        }
+
+        if state.avatar_url.is_some() && state.avatar_upstream_url.as_deref() == Some(upstream_url)
+        {
+            return Ok(());
+        }
+
+        let source = self.download(upstream_url).await?;
+        let avatar = tokio::task::spawn_blocking(move || process_avatar(&source))
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Avatar task failed: {e}")))??;
+
+        let key = format!("avatars/{}/{}.png", user_id, Uuid::new_v4());
+        let avatar_url = self.storage.put(&key, &avatar).await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE users
+            SET avatar_url = $1, avatar_upstream_url = $2, avatar_provider = $3,
+                avatar_synced_at = NOW()
+            WHERE id = $4 AND (avatar_provider IS NULL OR avatar_provider = $3)
+            "#,
+        )
+        .bind(&avatar_url)
+        .bind(upstream_url)
+        .bind(provider)
+        .bind(user_id)
+        .execute(self.db.pool())
+        .await?;
+
+        let stale_url = if updated.rows_affected() > 0 {
+            state.avatar_url
+        } else {
+            Some(avatar_url)
+        };
+
+        if let Some(stale_url) = stale_url {
+            self.storage.delete_url(&stale_url).await?;
+        }
+
+        tracing::info!("Mirrored {} avatar for user {}", provider, user_id);
+        Ok(())
+    }
+
+    async fn download(&self, url: &str) -> Result<Vec<u8>> {
+        let mut response = self
+            .http_client
+            .get(url)
+            .propagate_request_id()
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::Internal(anyhow::anyhow!(
+                "Avatar download failed: {}",
+                response.status()
+            )));
+        }
+
+        let is_image = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("image/"));
+
+        if !is_image {
+            return Err(AppError::Internal(anyhow::anyhow!(
+                "Avatar response is not an image"
+            )));
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if bytes.len() + chunk.len() > MAX_AVATAR_BYTES {
+                return Err(AppError::Internal(anyhow::anyhow!(
+                    "Avatar exceeds {} bytes",
+                    MAX_AVATAR_BYTES
+                )));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Decodes an untrusted image, crops it to a square and re-encodes it as PNG.
+pub fn process_avatar(source: &[u8]) -> Result<Vec<u8>> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        AppError::Internal(anyhow::anyhow!("Invalid avatar image: {e}"))
+    };
+
+    let mut reader = ImageReader::new(Cursor::new(source))
+        .with_guessed_format()
+        .map_err(|e| invalid(&e))?;
+
+    if !matches!(
+        reader.format(),
+        Some(ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP)
+    ) {
+        return Err(invalid(&"unsupported format"));
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    reader.limits(limits);
+
+    let image = reader.decode().map_err(|e| invalid(&e))?;
+    // Crop before scaling: `resize_to_fill` would first scale a 4096x1
+    // image to a million pixels wide.
+    let (width, height) = (image.width(), image.height());
+    let side = width.min(height);
+    let avatar = image
+        .crop_imm((width - side) / 2, (height - side) / 2, side, side)
+        .resize_exact(AVATAR_SIZE, AVATAR_SIZE, FilterType::Lanczos3);
+
+    let mut output = Cursor::new(Vec::new());
+    avatar
+        .write_to(&mut output, ImageFormat::Png)
+        .map_err(|e| invalid(&e))?;
+
+    Ok(output.into_inner())
+}
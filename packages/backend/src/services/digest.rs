@@ -0,0 +1,238 @@
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::{DigestChapter, DigestEntry, DigestSeries, EmailCategory, User};
+use crate::services::EmailOutbox;
+use crate::utils::{EmailService, SuppressionList};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Timelike, Utc};
+use chrono_tz::Tz;
+use sqlx::FromRow;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Local hour from which a user's digest goes out on their digest day.
+pub const DIGEST_HOUR: u32 = 9;
+const POLL_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// Chapters listed per series; the rest are summarised as "and N more".
+const MAX_CHAPTERS_PER_SERIES: usize = 5;
+/// How far back the first digest looks.
+const FIRST_DIGEST_WINDOW_DAYS: i64 = 7;
+
+#[derive(FromRow)]
+struct DigestRecipient {
+    #[sqlx(flatten)]
+    user: User,
+    last_run_at: Option<DateTime<Utc>>,
+}
+
+/// Whether a digest is due at `now` for a user in `timezone` who wants it on
+/// ISO weekday `digest_day`: it must be that day, at or after
+/// [`DIGEST_HOUR`] local time, and the digest must not have run yet on that
+/// local date. Unknown time zones fall back to UTC.
+pub fn is_digest_due(
+    now: DateTime<Utc>,
+    timezone: &str,
+    digest_day: i16,
+    last_run_at: Option<DateTime<Utc>>,
+) -> bool {
+    let tz: Tz = timezone.parse().unwrap_or(Tz::UTC);
+    let local_now = now.with_timezone(&tz);
+
+    if local_now.weekday().number_from_monday() != digest_day as u32
+        || local_now.hour() < DIGEST_HOUR
+    {
+        return false;
+    }
+
+    last_run_at.is_none_or(|last| last.with_timezone(&tz).date_naive() != local_now.date_naive())
+}
+
+/// Background task that sends each user a weekly summary of new chapters in
+/// the series they follow. Digests are queued in the outbox like any other
+/// email.
+pub struct WeeklyDigestScheduler {
+    db: Database,
+    outbox: EmailOutbox,
+    email_service: EmailService,
+    suppressions: SuppressionList,
+    frontend_url: String,
+}
+
+impl WeeklyDigestScheduler {
+    pub fn new(db: Database, email_service: EmailService, frontend_url: String) -> Self {
+        Self {
+            outbox: EmailOutbox::new(db.clone()),
+            suppressions: SuppressionList::new(db.clone()),
+            db,
+            email_service,
+            frontend_url,
+        }
+    }
+
+    /// Runs until `shutdown` is cancelled. A run in progress is finished
+    /// first, so no digest is left half-recorded.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.run(Utc::now()).await {
+                    tracing::error!("Weekly digest run failed: {:?}", e);
+                }
+
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+
+            tracing::info!("Weekly digest scheduler stopped");
+        })
+    }
+
+    /// Sends every digest that is due at `now`. Returns how many were queued.
+    pub async fn run(&self, now: DateTime<Utc>) -> Result<usize> {
+        // Digests run at most once a day, so anyone who ran within the last
+        // 20 hours can be skipped before looking at time zones.
+        let recipients = sqlx::query_as::<_, DigestRecipient>(
+            r#"
+            SELECT u.*, d.last_run_at
+            FROM users u
+            LEFT JOIN email_digests d ON d.user_id = u.id
+            WHERE u.is_verified
+              AND (d.last_run_at IS NULL OR d.last_run_at < $1 - INTERVAL '20 hours')
+              AND EXISTS (SELECT 1 FROM series_follows f WHERE f.user_id = u.id)
+            "#,
+        )
+        .bind(now)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut queued = 0;
+        for recipient in recipients {
+            if !is_digest_due(
+                now,
+                &recipient.user.timezone,
+                recipient.user.digest_day,
+                recipient.last_run_at,
+            ) {
+                continue;
+            }
+
+            match self.send_digest(&recipient, now).await {
+                Ok(true) => queued += 1,
+                Ok(false) => {}
+                Err(e) => tracing::error!(
+                    "Failed to build digest for user {}: {:?}",
+                    recipient.user.id,
+                    e
+                ),
+            }
+        }
+
+        Ok(queued)
+    }
+
+    async fn send_digest(&self, recipient: &DigestRecipient, now: DateTime<Utc>) -> Result<bool> {
+        let user = &recipient.user;
+        let mut tx = self.db.pool().begin().await?;
+
+        // Record the run first, and only if nobody else did since we read
+        // it, so two schedulers never send the same digest.
+        let claimed = sqlx::query(
+            r#"
+            INSERT INTO email_digests (user_id, last_run_at)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET last_run_at = EXCLUDED.last_run_at
+            WHERE email_digests.last_run_at IS NOT DISTINCT FROM $3
+            "#,
+        )
+        .bind(user.id)
+        .bind(now)
+        .bind(recipient.last_run_at)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            == 1;
+
+        if !claimed {
+            return Ok(false);
+        }
+
+        if !self
+            .suppressions
+            .allows(&user.email, EmailCategory::Digests)
+            .await?
+        {
+            tx.commit().await?;
+            return Ok(false);
+        }
+
+        let since = recipient
+            .last_run_at
+            .unwrap_or(now - ChronoDuration::days(FIRST_DIGEST_WINDOW_DAYS));
+
+        // Chapters released before the user followed a series are not news.
+        let chapters = sqlx::query_as::<_, DigestChapter>(
+            r#"
+            SELECT s.title AS series_title, s.slug AS series_slug,
+                   c.number, c.title, c.published_at
+            FROM series_follows f
+            JOIN series s ON s.id = f.series_id
+            JOIN chapters c ON c.series_id = s.id
+            WHERE f.user_id = $1
+              AND c.published_at > GREATEST($2, f.created_at)
+              AND c.published_at <= $3
+            ORDER BY s.title, s.slug, c.number
+            "#,
+        )
+        .bind(user.id)
+        .bind(since)
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if chapters.is_empty() {
+            tx.commit().await?;
+            return Ok(false);
+        }
+
+        let series = group_by_series(chapters, &self.frontend_url);
+        let email = self
+            .email_service
+            .digest_email(user, &series, &self.frontend_url)?;
+        self.outbox.enqueue(&mut tx, &email).await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+}
+
+/// Groups chapters, already ordered by series, into digest sections.
+fn group_by_series(chapters: Vec<DigestChapter>, frontend_url: &str) -> Vec<DigestSeries> {
+    let mut series: Vec<DigestSeries> = Vec::new();
+
+    for chapter in chapters {
+        let url = format!("{frontend_url}/series/{}", chapter.series_slug);
+        let section = match series.last_mut() {
+            Some(last) if last.url == url => last,
+            _ => {
+                series.push(DigestSeries {
+                    title: chapter.series_title,
+                    url,
+                    chapters: Vec::new(),
+                    more: 0,
+                });
+                series.last_mut().expect("just pushed")
+            }
+        };
+
+        if section.chapters.len() < MAX_CHAPTERS_PER_SERIES {
+            section.chapters.push(DigestEntry {
+                number: chapter.number.to_string(),
+                title: chapter.title,
+            });
+        } else {
+            section.more += 1;
+        }
+    }
+
+    series
+}
@@ -0,0 +1,193 @@
+use crate::config::Config;
+use crate::database::Database;
+use crate::error::{AppError, Message, Result};
+use crate::models::{
+    EmailCategory, EmailPreference, EmailSuppression, UpdateDigestScheduleRequest, User,
+};
+use crate::utils::{validate_request, UnsubscribeTokens};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Per-user, per-category opt-outs and the global suppression list.
+/// Categories without a stored row fall back to their default.
+#[derive(Clone)]
+pub struct EmailPreferenceService {
+    db: Database,
+    tokens: UnsubscribeTokens,
+}
+
+impl EmailPreferenceService {
+    pub fn new(db: Database, config: &Config) -> Self {
+        Self {
+            db,
+            tokens: UnsubscribeTokens::new(&config.mail.unsubscribe_secret),
+        }
+    }
+
+    pub async fn get(&self, user_id: Uuid) -> Result<Vec<EmailPreference>> {
+        let stored: HashMap<EmailCategory, bool> = sqlx::query_as::<_, (EmailCategory, bool)>(
+            "SELECT category, enabled FROM email_preferences WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(self.db.pool())
+        .await?
+        .into_iter()
+        .collect();
+
+        Ok(EmailCategory::ALL
+            .iter()
+            .map(|&category| EmailPreference {
+                category,
+                enabled: stored
+                    .get(&category)
+                    .copied()
+                    .unwrap_or_else(|| category.enabled_by_default()),
+                locked: !category.can_unsubscribe(),
+            })
+            .collect())
+    }
+
+    pub async fn update(
+        &self,
+        user_id: Uuid,
+        preferences: &HashMap<EmailCategory, bool>,
+    ) -> Result<Vec<EmailPreference>> {
+        if preferences
+            .iter()
+            .any(|(category, enabled)| !category.can_unsubscribe() && !enabled)
+        {
+            return Err(AppError::Validation(Message::new("email-category-locked")));
+        }
+
+        let mut tx = self.db.pool().begin().await?;
+        for (&category, &enabled) in preferences {
+            self.set(&mut tx, user_id, category, enabled).await?;
+        }
+        tx.commit().await?;
+
+        self.get(user_id).await
+    }
+
+    /// Sets the weekday and time zone the weekly digest is sent in.
+    pub async fn update_digest_schedule(
+        &self,
+        user_id: Uuid,
+        request: &UpdateDigestScheduleRequest,
+    ) -> Result<User> {
+        validate_request(request)?;
+
+        if request.timezone.parse::<Tz>().is_err() {
+            return Err(AppError::Validation(Message::new("invalid-timezone")));
+        }
+
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET digest_day = $1, timezone = $2 WHERE id = $3 RETURNING *",
+        )
+        .bind(request.day)
+        .bind(&request.timezone)
+        .bind(user_id)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(user)
+    }
+
+    /// Handles a one-click unsubscribe link. Returns the category that was
+    /// turned off.
+    pub async fn unsubscribe(&self, token: &str) -> Result<EmailCategory> {
+        let invalid = || AppError::Validation(Message::new("invalid-unsubscribe-link"));
+
+        let (user_id, category) = self.tokens.verify(token).ok_or_else(invalid)?;
+        if !category.can_unsubscribe() {
+            return Err(invalid());
+        }
+
+        let mut tx = self.db.pool().begin().await?;
+        let result = self.set(&mut tx, user_id, category, false).await;
+        match result {
+            Ok(()) => tx.commit().await?,
+            // The account was deleted after the email was sent.
+            Err(AppError::Database(sqlx::Error::Database(e))) if e.is_foreign_key_violation() => {
+                return Err(invalid())
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(category)
+    }
+
+    async fn set(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_id: Uuid,
+        category: EmailCategory,
+        enabled: bool,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_preferences (user_id, category, enabled)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, category)
+            DO UPDATE SET enabled = EXCLUDED.enabled, updated_at = NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(category)
+        .bind(enabled)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_suppressions(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<EmailSuppression>> {
+        let suppressions = sqlx::query_as::<_, EmailSuppression>(
+            r#"
+            SELECT * FROM email_suppressions
+            ORDER BY created_at DESC
+            LIMIT $1 OFFSET $2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(suppressions)
+    }
+
+    pub async fn suppress(&self, email: &str, reason: &str) -> Result<EmailSuppression> {
+        let suppression = sqlx::query_as::<_, EmailSuppression>(
+            r#"
+            INSERT INTO email_suppressions (email, reason)
+            VALUES (LOWER($1), $2)
+            ON CONFLICT (email) DO UPDATE SET reason = EXCLUDED.reason
+            RETURNING *
+            "#,
+        )
+        .bind(email)
+        .bind(reason)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(suppression)
+    }
+
+    pub async fn unsuppress(&self, email: &str) -> Result<()> {
+        let result = sqlx::query("DELETE FROM email_suppressions WHERE email = LOWER($1)")
+            .bind(email)
+            .execute(self.db.pool())
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(Message::new("suppression-not-found")));
+        }
+
+        Ok(())
+    }
+}
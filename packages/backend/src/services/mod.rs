@@ -1,7 +1,23 @@
 pub mod auth;
+pub mod avatar;
+pub mod digest;
+pub mod email_preferences;
 pub mod oauth;
+pub mod oauth_state;
+pub mod oidc;
+pub mod outbox;
+pub mod rate_limit;
+pub mod storage;
 pub mod user;
 
 pub use auth::*;
+pub use avatar::*;
+pub use digest::*;
+pub use email_preferences::*;
 pub use oauth::*;
+pub use oauth_state::*;
+pub use oidc::*;
+pub use outbox::*;
+pub use rate_limit::*;
+pub use storage::*;
 pub use user::*;
@@ -1,198 +1,360 @@
-use crate::config::Config;
+use crate::config::{Config, DiscordConfig, GoogleConfig};
 use crate::database::Database;
-use crate::error::{AppError, Result};
-use crate::models::AuthResponse;
-use crate::services::UserService;
-use crate::utils::JwtService;
-use oauth2::basic::BasicClient;
-use oauth2::reqwest::async_http_client;
+use crate::error::{AppError, Message, Result};
+use crate::middleware::PropagateRequestId;
+use crate::models::{
+    AccountLinkRequest, AuthResponse, OAuthAuthorization, OAuthLoginOutcome, OAuthProfile,
+    PendingLinkResponse, User,
+};
+use crate::services::{
+    AvatarService, EmailOutbox, JwksSource, MediaStorage, OAuthStateService, OidcVerifier,
+    UserService,
+};
+use crate::telemetry::record_oauth_exchange;
+use crate::utils::{verify_password, EmailService, JwtService};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use oauth2::basic::{
+    BasicClient, BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
+    BasicTokenType,
+};
 use oauth2::{
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope,
+    AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken, ExtraTokenFields,
+    HttpRequest, HttpResponse, RedirectUrl, Scope, StandardRevocableToken, StandardTokenResponse,
     TokenResponse, TokenUrl,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Instant;
 
-#[derive(Debug, Deserialize)]
-struct GoogleUserInfo {
-    id: String,
-    email: Option<String>,
-    name: Option<String>,
-    picture: Option<String>,
-    given_name: Option<String>,
-    _family_name: Option<String>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IdTokenFields {
+    id_token: Option<String>,
 }
 
+impl ExtraTokenFields for IdTokenFields {}
+
+type OidcClient = Client<
+    BasicErrorResponse,
+    StandardTokenResponse<IdTokenFields, BasicTokenType>,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
 #[derive(Debug, Deserialize)]
 struct DiscordUserInfo {
     id: String,
     username: String,
     email: Option<String>,
+    verified: Option<bool>,
     avatar: Option<String>,
     global_name: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct OAuthService {
+    db: Database,
     user_service: UserService,
     jwt_service: JwtService,
-    _config: Config,
-    google_client: BasicClient,
-    discord_client: BasicClient,
+    email_service: EmailService,
+    email_outbox: EmailOutbox,
+    config: Config,
+    state_service: OAuthStateService,
+    /// `None` when media storage is not configured; avatars are then left
+    /// as they are.
+    avatar_service: Option<AvatarService>,
+    http_client: reqwest::Client,
+    google: Option<GoogleProvider>,
+    discord: Option<DiscordProvider>,
 }
 
-impl OAuthService {
-    pub fn new(db: Database, config: Config) -> Result<Self> {
-        let user_service = UserService::new(db);
-        let jwt_service = JwtService::new(&config.jwt_secret);
-
-        let google_client = BasicClient::new(
-            ClientId::new(config.google_client_id.clone()),
-            Some(ClientSecret::new(config.google_client_secret.clone())),
-            AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".to_string()).map_err(
-                |e| AppError::Internal(anyhow::anyhow!("Invalid Google auth URL: {}", e)),
-            )?,
+#[derive(Clone)]
+struct GoogleProvider {
+    client: OidcClient,
+    verifier: OidcVerifier,
+}
+
+#[derive(Clone)]
+struct DiscordProvider {
+    client: BasicClient,
+    userinfo_url: String,
+    cdn_url: String,
+}
+
+impl GoogleProvider {
+    fn new(
+        config: &GoogleConfig,
+        backend_url: &str,
+        http_client: &reqwest::Client,
+    ) -> Result<Self> {
+        let client = OidcClient::new(
+            ClientId::new(config.client_id.clone()),
+            Some(ClientSecret::new(config.client_secret.clone())),
+            AuthUrl::new(config.endpoints.auth_url.clone()).map_err(|e| {
+                AppError::Internal(anyhow::anyhow!("Invalid Google auth URL: {}", e))
+            })?,
             Some(
-                TokenUrl::new("https://www.googleapis.com/oauth2/v4/token".to_string()).map_err(
-                    |e| AppError::Internal(anyhow::anyhow!("Invalid Google token URL: {}", e)),
-                )?,
+                TokenUrl::new(config.endpoints.token_url.clone()).map_err(|e| {
+                    AppError::Internal(anyhow::anyhow!("Invalid Google token URL: {}", e))
+                })?,
             ),
         )
         .set_redirect_uri(
-            RedirectUrl::new(format!(
-                "{}/api/v1/auth/google/callback",
-                config.backend_url
-            ))
-            .map_err(|e| {
-                AppError::Internal(anyhow::anyhow!("Invalid Google redirect URL: {}", e))
-            })?,
+            RedirectUrl::new(format!("{backend_url}/api/v1/auth/google/callback")).map_err(
+                |e| AppError::Internal(anyhow::anyhow!("Invalid Google redirect URL: {}", e)),
+            )?,
         );
 
-        let discord_client = BasicClient::new(
-            ClientId::new(config.discord_client_id.clone()),
-            Some(ClientSecret::new(config.discord_client_secret.clone())),
-            AuthUrl::new("https://discord.com/api/oauth2/authorize".to_string()).map_err(|e| {
+        let verifier = OidcVerifier::new(
+            config.endpoints.issuers.clone(),
+            config.client_id.clone(),
+            JwksSource::Remote {
+                url: config.endpoints.jwks_url.clone(),
+                http_client: http_client.clone(),
+            },
+        );
+
+        Ok(Self { client, verifier })
+    }
+}
+
+impl DiscordProvider {
+    fn new(config: &DiscordConfig, backend_url: &str) -> Result<Self> {
+        let client = BasicClient::new(
+            ClientId::new(config.client_id.clone()),
+            Some(ClientSecret::new(config.client_secret.clone())),
+            AuthUrl::new(config.endpoints.auth_url.clone()).map_err(|e| {
                 AppError::Internal(anyhow::anyhow!("Invalid Discord auth URL: {}", e))
             })?,
             Some(
-                TokenUrl::new("https://discord.com/api/oauth2/token".to_string()).map_err(|e| {
+                TokenUrl::new(config.endpoints.token_url.clone()).map_err(|e| {
                     AppError::Internal(anyhow::anyhow!("Invalid Discord token URL: {}", e))
                 })?,
             ),
         )
         .set_redirect_uri(
-            RedirectUrl::new(format!(
-                "{}/api/v1/auth/discord/callback",
-                config.backend_url
-            ))
-            .map_err(|e| {
-                AppError::Internal(anyhow::anyhow!("Invalid Discord redirect URL: {}", e))
-            })?,
+            RedirectUrl::new(format!("{backend_url}/api/v1/auth/discord/callback")).map_err(
+                |e| AppError::Internal(anyhow::anyhow!("Invalid Discord redirect URL: {}", e)),
+            )?,
         );
 
         Ok(Self {
+            client,
+            userinfo_url: config.endpoints.userinfo_url.clone(),
+            cdn_url: config.endpoints.cdn_url.clone(),
+        })
+    }
+}
+
+impl OAuthService {
+    pub fn new(
+        db: Database,
+        config: Config,
+        http_client: reqwest::Client,
+        user_service: UserService,
+        jwt_service: JwtService,
+        email_service: EmailService,
+    ) -> Result<Self> {
+        let state_service = OAuthStateService::new(db.clone());
+        let email_outbox = EmailOutbox::new(db.clone());
+        let avatar_service = config.storage.as_ref().map(|storage| {
+            AvatarService::new(db.clone(), MediaStorage::new(storage), http_client.clone())
+        });
+
+        let google = config
+            .google
+            .as_ref()
+            .map(|google| GoogleProvider::new(google, &config.backend_url, &http_client))
+            .transpose()?;
+        let discord = config
+            .discord
+            .as_ref()
+            .map(|discord| DiscordProvider::new(discord, &config.backend_url))
+            .transpose()?;
+
+        Ok(Self {
+            db,
             user_service,
             jwt_service,
-            _config: config,
-            google_client,
-            discord_client,
+            email_service,
+            email_outbox,
+            config,
+            state_service,
+            avatar_service,
+            http_client,
+            google,
+            discord,
         })
     }
 
-    pub fn get_google_auth_url(&self) -> (String, String) {
-        let (auth_url, csrf_token) = self
-            .google_client
-            .authorize_url(CsrfToken::new_random)
+    pub fn google_enabled(&self) -> bool {
+        self.google.is_some()
+    }
+
+    pub fn discord_enabled(&self) -> bool {
+        self.discord.is_some()
+    }
+
+    fn google(&self) -> Result<&GoogleProvider> {
+        self.google
+            .as_ref()
+            .ok_or_else(|| provider_disabled("Google"))
+    }
+
+    fn discord(&self) -> Result<&DiscordProvider> {
+        self.discord
+            .as_ref()
+            .ok_or_else(|| provider_disabled("Discord"))
+    }
+
+    pub async fn get_google_auth_url(&self) -> Result<OAuthAuthorization> {
+        let google = self.google()?;
+        let state = CsrfToken::new_random();
+        let nonce = self.state_service.create("google", state.secret()).await?;
+        let binding = state_binding(state.secret());
+
+        let (auth_url, _) = google
+            .client
+            .authorize_url(|| state)
+            .add_scope(Scope::new("openid".to_string()))
             .add_scope(Scope::new("email".to_string()))
             .add_scope(Scope::new("profile".to_string()))
+            .add_extra_param("nonce", nonce)
             .url();
 
-        (auth_url.to_string(), csrf_token.secret().clone())
+        Ok(OAuthAuthorization {
+            url: auth_url.to_string(),
+            binding,
+        })
     }
 
-    pub fn get_discord_auth_url(&self) -> (String, String) {
-        let (auth_url, csrf_token) = self
-            .discord_client
-            .authorize_url(CsrfToken::new_random)
+    pub async fn get_discord_auth_url(&self) -> Result<OAuthAuthorization> {
+        let discord = self.discord()?;
+        let state = CsrfToken::new_random();
+        self.state_service.create("discord", state.secret()).await?;
+        let binding = state_binding(state.secret());
+
+        let (auth_url, _) = discord
+            .client
+            .authorize_url(|| state)
             .add_scope(Scope::new("identify".to_string()))
             .add_scope(Scope::new("email".to_string()))
             .url();
 
-        (auth_url.to_string(), csrf_token.secret().clone())
+        Ok(OAuthAuthorization {
+            url: auth_url.to_string(),
+            binding,
+        })
+    }
+
+    pub async fn handle_google_callback(
+        &self,
+        code: &str,
+        state: Option<&str>,
+        binding: Option<&str>,
+        locale: &str,
+    ) -> Result<OAuthLoginOutcome> {
+        let nonce = self.consume_state("google", state, binding).await?;
+        let profile = self.fetch_google_profile(code, &nonce, locale).await?;
+
+        self.complete_login(profile).await
     }
 
-    pub async fn handle_google_callback(&self, code: &str, locale: &str) -> Result<AuthResponse> {
-        let token_result = self
-            .google_client
+    /// Exchanges the authorization code and builds the profile from the
+    /// verified ID token.
+    pub async fn fetch_google_profile(
+        &self,
+        code: &str,
+        nonce: &str,
+        locale: &str,
+    ) -> Result<OAuthProfile> {
+        let google = self.google()?;
+        let started = Instant::now();
+        let token_result = google
+            .client
             .exchange_code(AuthorizationCode::new(code.to_string()))
-            .request_async(async_http_client)
-            .await
+            .request_async(|request| send_oauth_request(&self.http_client, request))
+            .await;
+        record_oauth_exchange("google", token_result.is_ok(), started);
+
+        let token_result = token_result
             .map_err(|e| AppError::OAuth(format!("Failed to exchange Google code: {e}")))?;
 
-        let access_token = token_result.access_token().secret();
+        let id_token = token_result
+            .extra_fields()
+            .id_token
+            .as_deref()
+            .ok_or_else(|| AppError::OAuth("Google did not return an ID token".to_string()))?;
 
-        let client = reqwest::Client::new();
-        let user_info: GoogleUserInfo = client
-            .get("https://www.googleapis.com/oauth2/v2/userinfo")
-            .bearer_auth(access_token)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let claims = google.verifier.verify(id_token, nonce).await?;
 
-        let email = user_info.email.ok_or_else(|| {
+        let email = claims.email.ok_or_else(|| {
             tracing::error!("Google account has no verified email");
             AppError::OAuth("Google account must have a verified email".to_string())
         })?;
 
-        let username = user_info
+        let username = claims
             .given_name
-            .or(user_info.name.clone())
-            .unwrap_or_else(|| format!("user_{}", &user_info.id[..8]));
-
-        let avatar_url = user_info.picture;
-        let display_name = user_info.name;
-
-        let user = self
-            .user_service
-            .find_or_create_oauth_user(
-                &email,
-                &username,
-                display_name,
-                avatar_url,
-                "google",
-                &user_info.id,
-                Some(locale.to_string()),
-            )
-            .await?;
-
-        let token = self.jwt_service.generate_token(user.id, &user.email)?;
-
-        Ok(AuthResponse {
-            user: user.into(),
-            token,
+            .or(claims.name.clone())
+            .unwrap_or_else(|| format!("user_{}", &claims.sub[..8.min(claims.sub.len())]));
+
+        Ok(OAuthProfile {
+            provider: "google".to_string(),
+            provider_id: claims.sub,
+            email,
+            email_verified: claims.email_verified,
+            username,
+            display_name: claims.name,
+            avatar_url: claims.picture,
+            locale: Some(locale.to_string()),
         })
     }
 
-    pub async fn handle_discord_callback(&self, code: &str, locale: &str) -> Result<AuthResponse> {
-        tracing::info!("Starting Discord OAuth callback with code: {}", &code[..10]);
+    pub async fn handle_discord_callback(
+        &self,
+        code: &str,
+        state: Option<&str>,
+        binding: Option<&str>,
+        locale: &str,
+    ) -> Result<OAuthLoginOutcome> {
+        self.consume_state("discord", state, binding).await?;
+
+        tracing::info!("Starting Discord OAuth callback");
+        let profile = self.fetch_discord_profile(code, locale).await?;
+
+        tracing::info!("Completing Discord login for email: {}", profile.email);
+        self.complete_login(profile).await.map_err(|e| {
+            tracing::error!("Failed to complete Discord login: {}", e);
+            e
+        })
+    }
 
-        let token_result = self
-            .discord_client
+    /// Exchanges the authorization code and builds the profile from the
+    /// Discord user endpoint.
+    pub async fn fetch_discord_profile(&self, code: &str, locale: &str) -> Result<OAuthProfile> {
+        let discord = self.discord()?;
+        let started = Instant::now();
+        let token_result = discord
+            .client
             .exchange_code(AuthorizationCode::new(code.to_string()))
-            .request_async(async_http_client)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to exchange Discord code: {}", e);
-                AppError::OAuth(format!("Failed to exchange Discord code: {e}"))
-            })?;
+            .request_async(|request| send_oauth_request(&self.http_client, request))
+            .await;
+        record_oauth_exchange("discord", token_result.is_ok(), started);
+
+        let token_result = token_result.map_err(|e| {
+            tracing::error!("Failed to exchange Discord code: {}", e);
+            AppError::OAuth(format!("Failed to exchange Discord code: {e}"))
+        })?;
 
         let access_token = token_result.access_token().secret();
         tracing::info!("Successfully obtained Discord access token");
 
-        let client = reqwest::Client::new();
-        let response = client
-            .get("https://discord.com/api/users/@me")
+        let response = self
+            .http_client
+            .get(&discord.userinfo_url)
             .bearer_auth(access_token)
+            .propagate_request_id()
             .send()
             .await
             .map_err(|e| {
@@ -230,39 +392,226 @@ impl OAuthService {
 
         let avatar_url = user_info.avatar.map(|avatar| {
             format!(
-                "https://cdn.discordapp.com/avatars/{}/{}.png",
-                user_info.id, avatar
+                "{}/avatars/{}/{}.png",
+                discord.cdn_url, user_info.id, avatar
             )
         });
 
         let display_name = user_info.global_name.or(Some(user_info.username));
 
-        tracing::info!("Creating/finding user with email: {}", email);
+        Ok(OAuthProfile {
+            provider: "discord".to_string(),
+            provider_id: user_info.id,
+            email,
+            email_verified: user_info.verified.unwrap_or(false),
+            username,
+            display_name,
+            avatar_url,
+            locale: Some(locale.to_string()),
+        })
+    }
+
+    /// Consumes the `state` returned by the provider and yields the nonce
+    /// that was bound to it when the authorization request was created. The
+    /// browser must present the binding issued with that state, so a
+    /// callback URL planted by someone else is refused.
+    async fn consume_state(
+        &self,
+        provider: &str,
+        state: Option<&str>,
+        binding: Option<&str>,
+    ) -> Result<String> {
+        let state =
+            state.ok_or_else(|| AppError::OAuth("Missing OAuth state parameter".to_string()))?;
+
+        if binding != Some(state_binding(state).as_str()) {
+            return Err(AppError::OAuth(
+                "OAuth state was not issued to this browser".to_string(),
+            ));
+        }
+
+        self.state_service
+            .consume(provider, state)
+            .await?
+            .ok_or_else(|| AppError::OAuth("Invalid or expired OAuth state".to_string()))
+    }
+
+    /// Signs the user in, creating or linking the account as needed. An
+    /// identity is only attached to an existing account automatically when
+    /// both the account and the provider have verified the email address.
+    async fn complete_login(&self, profile: OAuthProfile) -> Result<OAuthLoginOutcome> {
+        let linked_user = self
+            .user_service
+            .find_by_identity(&profile.provider, &profile.provider_id)
+            .await?;
+
+        let user = match linked_user {
+            Some(user) => user,
+            None => match self.user_service.find_by_email(&profile.email).await? {
+                None => self.user_service.create_oauth_user(&profile).await?,
+                Some(existing) if existing.is_verified && profile.email_verified => {
+                    self.user_service
+                        .link_identity(existing.id, &profile)
+                        .await?
+                }
+                Some(existing) => {
+                    let request = self
+                        .user_service
+                        .create_account_link_request(existing.id, &profile)
+                        .await?;
+
+                    tracing::info!(
+                        "OAuth email matches existing account {}, linking requires confirmation",
+                        existing.id
+                    );
+
+                    let link_code = self
+                        .state_service
+                        .create_link_code(&request.link_token)
+                        .await?;
+
+                    return Ok(OAuthLoginOutcome::LinkRequired { link_code });
+                }
+            },
+        };
+
+        if let Some(avatar_service) = &self.avatar_service {
+            avatar_service.schedule_sync(user.id, &profile.provider, profile.avatar_url.as_deref());
+        }
+
+        let exchange_code = self.state_service.create_exchange_code(user.id).await?;
+
+        Ok(OAuthLoginOutcome::Authenticated { exchange_code })
+    }
+
+    /// Trades a one-time code issued by an OAuth callback for a session.
+    pub async fn exchange_code(&self, code: &str) -> Result<AuthResponse> {
+        let invalid_code = || AppError::Authentication(Message::new("invalid-token"));
+
+        let user_id = self
+            .state_service
+            .consume_exchange_code(code)
+            .await?
+            .ok_or_else(invalid_code)?;
+
         let user = self
             .user_service
-            .find_or_create_oauth_user(
-                &email,
-                &username,
-                display_name,
-                avatar_url,
-                "discord",
-                &user_info.id,
-                Some(locale.to_string()),
-            )
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to find or create OAuth user: {}", e);
-                e
-            })?;
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(invalid_code)?;
 
-        tracing::info!("Generating JWT token for user: {}", user.id);
-        let token = self
-            .jwt_service
-            .generate_token(user.id, &user.email)
-            .map_err(|e| {
-                tracing::error!("Failed to generate JWT token: {}", e);
-                e
-            })?;
+        self.authenticated(user)
+    }
+
+    /// Trades a one-time code issued by an OAuth callback for the pending
+    /// link request it stands for.
+    pub async fn exchange_link_code(&self, code: &str) -> Result<PendingLinkResponse> {
+        let link_token = self
+            .state_service
+            .consume_link_code(code)
+            .await?
+            .ok_or_else(|| AppError::Authentication(Message::new("invalid-token")))?;
+        let request = self.find_link_request(&link_token).await?;
+
+        Ok(PendingLinkResponse {
+            token: request.link_token,
+            email: request.provider_email,
+        })
+    }
+
+    pub async fn link_with_password(
+        &self,
+        link_token: &str,
+        password: &str,
+    ) -> Result<AuthResponse> {
+        let request = self.find_link_request(link_token).await?;
+
+        let user = self
+            .user_service
+            .find_by_id(request.user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(Message::new("user-not-found")))?;
+
+        let password_hash = user
+            .password_hash
+            .as_ref()
+            .ok_or_else(|| AppError::Validation(Message::new("account-has-no-password")))?;
+
+        if !verify_password(password, password_hash)? {
+            return Err(AppError::Authentication(Message::new(
+                "invalid-credentials",
+            )));
+        }
+
+        let user = self
+            .user_service
+            .complete_account_link(&request, request.provider_email_verified)
+            .await?;
+
+        if let Some(avatar_service) = &self.avatar_service {
+            avatar_service.schedule_sync(user.id, &request.provider, request.avatar_url.as_deref());
+        }
+
+        self.authenticated(user)
+    }
+
+    pub async fn send_link_confirmation(&self, link_token: &str) -> Result<()> {
+        let request = self.find_link_request(link_token).await?;
+
+        let user = self
+            .user_service
+            .find_by_id(request.user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(Message::new("user-not-found")))?;
+
+        let mut tx = self.db.pool().begin().await?;
+
+        let email_token = self
+            .user_service
+            .update_account_link_email_token(&mut tx, request.id)
+            .await?;
+
+        let email = self.email_service.account_link_email(
+            &user,
+            &request.provider,
+            &email_token,
+            &self.config.frontend_url,
+        )?;
+        self.email_outbox.enqueue(&mut tx, &email).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn confirm_link_email(&self, email_token: &str) -> Result<AuthResponse> {
+        let request = self
+            .user_service
+            .find_account_link_request_by_email_token(email_token)
+            .await?
+            .ok_or_else(|| AppError::Authentication(Message::new("invalid-token")))?;
+
+        let user = self
+            .user_service
+            .complete_account_link(&request, true)
+            .await?;
+
+        if let Some(avatar_service) = &self.avatar_service {
+            avatar_service.schedule_sync(user.id, &request.provider, request.avatar_url.as_deref());
+        }
+
+        self.authenticated(user)
+    }
+
+    async fn find_link_request(&self, link_token: &str) -> Result<AccountLinkRequest> {
+        self.user_service
+            .find_account_link_request(link_token)
+            .await?
+            .ok_or_else(|| AppError::Authentication(Message::new("invalid-token")))
+    }
+
+    fn authenticated(&self, user: User) -> Result<AuthResponse> {
+        let token = self.jwt_service.generate_token(user.id, &user.email)?;
 
         Ok(AuthResponse {
             user: user.into(),
@@ -270,3 +619,38 @@ impl OAuthService {
         })
     }
 }
+
+/// Sends an `oauth2` token request through the shared HTTP client instead of
+/// the per-request client used by `oauth2::reqwest::async_http_client`.
+async fn send_oauth_request(
+    http_client: &reqwest::Client,
+    request: HttpRequest,
+) -> std::result::Result<HttpResponse, reqwest::Error> {
+    let response = http_client
+        .request(request.method, request.url.as_str())
+        .headers(request.headers)
+        .body(request.body)
+        .propagate_request_id()
+        .send()
+        .await?;
+
+    let status_code = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?.to_vec();
+
+    Ok(HttpResponse {
+        status_code,
+        headers,
+        body,
+    })
+}
+
+/// The value the browser keeps for `state`: a hash, so the cookie never
+/// holds the state itself.
+fn state_binding(state: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(state.as_bytes()))
+}
+
+fn provider_disabled(provider: &str) -> AppError {
+    AppError::NotFound(Message::new("oauth-provider-disabled").arg("provider", provider))
+}
@@ -0,0 +1,142 @@
+use crate::database::Database;
+use crate::error::Result;
+use crate::utils::generate_verification_token;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+/// Persists the short-lived values of the OAuth redirect flow: the `state` and
+/// `nonce` sent with an authorization request, and the code the frontend
+/// exchanges for a session afterwards. Every value can be consumed only once.
+#[derive(Clone)]
+pub struct OAuthStateService {
+    db: Database,
+}
+
+impl OAuthStateService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Stores a new state for `provider` and returns the nonce bound to it.
+    pub async fn create(&self, provider: &str, state: &str) -> Result<String> {
+        let nonce = generate_verification_token();
+        let expires_at = Utc::now() + Duration::minutes(10);
+
+        sqlx::query("DELETE FROM oauth_states WHERE expires_at < NOW()")
+            .execute(self.db.pool())
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_states (state, provider, nonce, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(state)
+        .bind(provider)
+        .bind(&nonce)
+        .bind(expires_at)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(nonce)
+    }
+
+    /// Removes the state and returns its nonce if it was issued for
+    /// `provider` and has not expired.
+    pub async fn consume(&self, provider: &str, state: &str) -> Result<Option<String>> {
+        let nonce = sqlx::query_scalar::<_, String>(
+            r#"
+            DELETE FROM oauth_states
+            WHERE state = $1 AND provider = $2 AND expires_at > NOW()
+            RETURNING nonce
+            "#,
+        )
+        .bind(state)
+        .bind(provider)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(nonce)
+    }
+
+    /// Issues a one-time code that the frontend exchanges for a session
+    /// token, so the token itself never appears in a redirect URL.
+    pub async fn create_exchange_code(&self, user_id: Uuid) -> Result<String> {
+        let code = generate_verification_token();
+        let expires_at = Utc::now() + Duration::seconds(60);
+
+        sqlx::query("DELETE FROM oauth_exchange_codes WHERE expires_at < NOW()")
+            .execute(self.db.pool())
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_exchange_codes (code, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(&code)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(code)
+    }
+
+    pub async fn consume_exchange_code(&self, code: &str) -> Result<Option<Uuid>> {
+        let user_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            DELETE FROM oauth_exchange_codes
+            WHERE code = $1 AND expires_at > NOW()
+            RETURNING user_id
+            "#,
+        )
+        .bind(code)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(user_id)
+    }
+
+    /// Issues a one-time code for a pending link request, for the same
+    /// reason as `create_exchange_code`.
+    pub async fn create_link_code(&self, link_token: &str) -> Result<String> {
+        let code = generate_verification_token();
+        let expires_at = Utc::now() + Duration::seconds(60);
+
+        sqlx::query("DELETE FROM oauth_link_codes WHERE expires_at < NOW()")
+            .execute(self.db.pool())
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_link_codes (code, link_token, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(&code)
+        .bind(link_token)
+        .bind(expires_at)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(code)
+    }
+
+    pub async fn consume_link_code(&self, code: &str) -> Result<Option<String>> {
+        let link_token = sqlx::query_scalar::<_, String>(
+            r#"
+            DELETE FROM oauth_link_codes
+            WHERE code = $1 AND expires_at > NOW()
+            RETURNING link_token
+            "#,
+        )
+        .bind(code)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(link_token)
+    }
+}
@@ -0,0 +1,231 @@
+use crate::error::{AppError, Result};
+use crate::middleware::PropagateRequestId;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Deserializer};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+const DEFAULT_JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+const MIN_JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+const SUPPORTED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_email_verified")]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub picture: Option<String>,
+    pub locale: Option<String>,
+    pub nonce: Option<String>,
+}
+
+/// Where the verifier gets the provider's signing keys from.
+#[derive(Clone)]
+pub enum JwksSource {
+    Remote {
+        url: String,
+        http_client: reqwest::Client,
+    },
+    Static(JwkSet),
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+    ttl: Duration,
+}
+
+impl CachedJwks {
+    fn is_fresh(&self) -> bool {
+        self.fetched_at.elapsed() < self.ttl
+    }
+}
+
+/// Validates ID tokens issued by an OpenID Connect provider. Signing keys are
+/// cached and refetched when they expire or when a token references a key id
+/// the cache does not know yet, so key rotation is picked up automatically.
+#[derive(Clone)]
+pub struct OidcVerifier {
+    issuers: Vec<String>,
+    audience: String,
+    source: JwksSource,
+    cache: Arc<RwLock<Option<CachedJwks>>>,
+}
+
+impl OidcVerifier {
+    pub fn new(issuers: Vec<String>, audience: String, source: JwksSource) -> Self {
+        let cache = match &source {
+            JwksSource::Static(keys) => Some(CachedJwks {
+                keys: keys.clone(),
+                fetched_at: Instant::now(),
+                ttl: Duration::MAX,
+            }),
+            JwksSource::Remote { .. } => None,
+        };
+
+        Self {
+            issuers,
+            audience,
+            source,
+            cache: Arc::new(RwLock::new(cache)),
+        }
+    }
+
+    /// Checks the signature, `iss`, `aud`, `exp` and `nonce` of `id_token` and
+    /// requires any email claim to be verified by the provider.
+    pub async fn verify(&self, id_token: &str, expected_nonce: &str) -> Result<IdTokenClaims> {
+        let header = decode_header(id_token)
+            .map_err(|e| AppError::OAuth(format!("Malformed ID token: {e}")))?;
+
+        if !SUPPORTED_ALGORITHMS.contains(&header.alg) {
+            return Err(AppError::OAuth(format!(
+                "Unsupported ID token algorithm: {:?}",
+                header.alg
+            )));
+        }
+
+        let kid = header
+            .kid
+            .ok_or_else(|| AppError::OAuth("ID token has no key id".to_string()))?;
+
+        let jwk = self.find_key(&kid).await?;
+
+        if let Some(key_algorithm) = jwk.common.key_algorithm {
+            if key_algorithm.to_string().parse::<Algorithm>().ok() != Some(header.alg) {
+                return Err(AppError::OAuth(
+                    "ID token algorithm does not match its signing key".to_string(),
+                ));
+            }
+        }
+
+        let decoding_key = DecodingKey::from_jwk(&jwk)
+            .map_err(|e| AppError::OAuth(format!("Unusable signing key {kid}: {e}")))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&self.issuers);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+            .map_err(|e| AppError::OAuth(format!("Invalid ID token: {e}")))?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(expected_nonce) {
+            return Err(AppError::OAuth("ID token nonce mismatch".to_string()));
+        }
+
+        if claims.email.is_some() && !claims.email_verified {
+            return Err(AppError::OAuth(
+                "ID token email is not verified".to_string(),
+            ));
+        }
+
+        Ok(claims)
+    }
+
+    async fn find_key(&self, kid: &str) -> Result<Jwk> {
+        {
+            let cache = self.cache.read().await;
+            if let Some(cached) = cache.as_ref().filter(|cached| cached.is_fresh()) {
+                if let Some(jwk) = cached.keys.find(kid) {
+                    return Ok(jwk.clone());
+                }
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+
+        let needs_refresh = match cache.as_ref() {
+            None => true,
+            Some(cached) if !cached.is_fresh() => true,
+            Some(cached) => {
+                cached.keys.find(kid).is_none()
+                    && cached.fetched_at.elapsed() >= MIN_JWKS_REFRESH_INTERVAL
+            }
+        };
+
+        if needs_refresh {
+            if let Some(fetched) = self.fetch_keys().await? {
+                *cache = Some(fetched);
+            }
+        }
+
+        cache
+            .as_ref()
+            .and_then(|cached| cached.keys.find(kid))
+            .cloned()
+            .ok_or_else(|| AppError::OAuth(format!("Unknown ID token signing key: {kid}")))
+    }
+
+    async fn fetch_keys(&self) -> Result<Option<CachedJwks>> {
+        let JwksSource::Remote { url, http_client } = &self.source else {
+            return Ok(None);
+        };
+
+        let response = http_client.get(url).propagate_request_id().send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            tracing::error!("Failed to fetch JWKS from {}: {}", url, status);
+            return Err(AppError::OAuth(format!("JWKS request failed: {status}")));
+        }
+
+        let ttl = response
+            .headers()
+            .get(reqwest::header::CACHE_CONTROL)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_max_age)
+            .unwrap_or(DEFAULT_JWKS_TTL);
+
+        let keys: JwkSet = response.json().await?;
+        tracing::debug!("Fetched {} signing keys from {}", keys.keys.len(), url);
+
+        Ok(Some(CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+            ttl,
+        }))
+    }
+}
+
+fn parse_max_age(cache_control: &str) -> Option<Duration> {
+    cache_control
+        .split(',')
+        .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+        .find_map(|seconds| seconds.parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// Some providers send `email_verified` as the string `"true"`.
+fn deserialize_email_verified<'de, D>(deserializer: D) -> std::result::Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match BoolOrString::deserialize(deserializer)? {
+        BoolOrString::Bool(value) => value,
+        BoolOrString::String(value) => value.eq_ignore_ascii_case("true"),
+    })
+}
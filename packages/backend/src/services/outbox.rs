@@ -0,0 +1,309 @@
+use crate::database::Database;
+use crate::error::{AppError, Message, Result};
+use crate::middleware::RequestId;
+use crate::models::{EmailStatus, OutboxEmail, OutgoingEmail};
+use crate::telemetry::record_email_delivery;
+use crate::utils::{DeliveryOutcome, EmailService};
+use chrono::Utc;
+use sqlx::{Postgres, Transaction};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Delivery attempts before a message is moved to the dead-letter state.
+pub const MAX_ATTEMPTS: i32 = 8;
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+/// How long a claimed message stays invisible to other workers. Messages left
+/// in `sending` by a crashed worker are picked up again after this, until
+/// they run out of attempts.
+const CLAIM_LEASE_SECS: i32 = 5 * 60;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const BATCH_SIZE: i64 = 20;
+
+/// Postgres-backed queue of outgoing emails. Messages are written in the same
+/// transaction as the change that triggers them, so a rolled back request
+/// never sends mail and a committed one always does.
+#[derive(Clone)]
+pub struct EmailOutbox {
+    db: Database,
+}
+
+impl EmailOutbox {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub async fn enqueue(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        email: &OutgoingEmail,
+    ) -> Result<Uuid> {
+        let id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO email_outbox
+                (to_email, subject, html_body, text_body, category, unsubscribe_url, request_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+        )
+        .bind(&email.to)
+        .bind(&email.subject)
+        .bind(&email.html_body)
+        .bind(&email.text_body)
+        .bind(email.category)
+        .bind(&email.unsubscribe_url)
+        .bind(RequestId::current().map(String::from))
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(id)
+    }
+
+    pub async fn list(
+        &self,
+        status: Option<EmailStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<OutboxEmail>> {
+        let emails = sqlx::query_as::<_, OutboxEmail>(
+            r#"
+            SELECT * FROM email_outbox
+            WHERE $1::email_status IS NULL OR status = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(emails)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<OutboxEmail>> {
+        let email = sqlx::query_as::<_, OutboxEmail>("SELECT * FROM email_outbox WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self.db.pool())
+            .await?;
+
+        Ok(email)
+    }
+
+    /// Puts a dead-lettered message back in the queue with a fresh set of
+    /// attempts.
+    pub async fn retry(&self, id: Uuid) -> Result<OutboxEmail> {
+        let email = sqlx::query_as::<_, OutboxEmail>(
+            r#"
+            UPDATE email_outbox
+            SET status = 'pending', attempts = 0, next_attempt_at = NOW(), locked_until = NULL
+            WHERE id = $1 AND status = 'failed'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        match email {
+            Some(email) => Ok(email),
+            None if self.find_by_id(id).await?.is_some() => {
+                Err(AppError::Conflict(Message::new("outbox-retry-not-failed")))
+            }
+            None => Err(AppError::NotFound(Message::new("outbox-email-not-found"))),
+        }
+    }
+
+    /// Claims due messages for delivery. `SKIP LOCKED` lets several workers
+    /// share the queue without sending a message twice.
+    pub async fn claim_due(&self) -> Result<Vec<OutboxEmail>> {
+        // A message whose lease keeps expiring, e.g. one that crashes the
+        // worker, is dead-lettered once it has used up its attempts.
+        sqlx::query(
+            r#"
+            UPDATE email_outbox
+            SET status = 'failed', locked_until = NULL,
+                last_error = COALESCE(last_error, 'delivery did not finish before the lease expired')
+            WHERE status = 'sending' AND locked_until < NOW() AND attempts >= $1
+            "#,
+        )
+        .bind(MAX_ATTEMPTS)
+        .execute(self.db.pool())
+        .await?;
+
+        let emails = sqlx::query_as::<_, OutboxEmail>(
+            r#"
+            UPDATE email_outbox
+            SET status = 'sending', attempts = attempts + 1,
+                locked_until = NOW() + $2 * INTERVAL '1 second'
+            WHERE id IN (
+                SELECT id FROM email_outbox
+                WHERE (status = 'pending' AND next_attempt_at <= NOW())
+                   OR (status = 'sending' AND locked_until < NOW() AND attempts < $3)
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(BATCH_SIZE)
+        .bind(CLAIM_LEASE_SECS)
+        .bind(MAX_ATTEMPTS)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(emails)
+    }
+
+    async fn mark_sent(&self, id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE email_outbox
+            SET status = 'sent', sent_at = NOW(), locked_until = NULL, last_error = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn mark_suppressed(&self, id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE email_outbox
+            SET status = 'suppressed', locked_until = NULL, last_error = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Schedules the next attempt of a claimed message, or dead-letters it
+    /// once it has used up `MAX_ATTEMPTS`.
+    pub async fn mark_failed(&self, email: &OutboxEmail, error: &str) -> Result<EmailStatus> {
+        let status = if email.attempts >= MAX_ATTEMPTS {
+            EmailStatus::Failed
+        } else {
+            EmailStatus::Pending
+        };
+
+        let next_attempt_at = Utc::now() + retry_delay(email.attempts);
+
+        sqlx::query(
+            r#"
+            UPDATE email_outbox
+            SET status = $1, next_attempt_at = $2, last_error = $3, locked_until = NULL
+            WHERE id = $4
+            "#,
+        )
+        .bind(status)
+        .bind(next_attempt_at)
+        .bind(error)
+        .bind(email.id)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(status)
+    }
+}
+
+/// Exponential backoff: 30s, 1m, 2m, ... capped at six hours.
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    let delay = BASE_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(exponent))
+        .min(MAX_RETRY_DELAY);
+
+    chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero())
+}
+
+/// Background task that drains the outbox.
+pub struct EmailOutboxWorker {
+    outbox: EmailOutbox,
+    email_service: EmailService,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(db: Database, email_service: EmailService) -> Self {
+        Self {
+            outbox: EmailOutbox::new(db),
+            email_service,
+        }
+    }
+
+    /// Runs until `shutdown` is cancelled. The batch being delivered is
+    /// finished first, so no email is cut off mid-send; anything left
+    /// claimed is picked up again once its lease expires.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            while !shutdown.is_cancelled() {
+                let claimed = match self.process_batch().await {
+                    Ok(claimed) => claimed,
+                    Err(e) => {
+                        tracing::error!("Email outbox worker failed: {:?}", e);
+                        0
+                    }
+                };
+
+                if claimed < BATCH_SIZE as usize {
+                    tokio::select! {
+                        _ = shutdown.cancelled() => break,
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    }
+                }
+            }
+
+            tracing::info!("Email outbox worker stopped");
+        })
+    }
+
+    async fn process_batch(&self) -> Result<usize> {
+        let emails = self.outbox.claim_due().await?;
+
+        for email in &emails {
+            match self.email_service.send(&email.into()).await {
+                Ok(DeliveryOutcome::Sent) => {
+                    record_email_delivery(email.category, "sent");
+                    self.outbox.mark_sent(email.id).await?;
+                    tracing::info!("Sent email {} to {}", email.id, email.to_email);
+                }
+                Ok(DeliveryOutcome::Suppressed) => {
+                    record_email_delivery(email.category, "suppressed");
+                    self.outbox.mark_suppressed(email.id).await?;
+                    tracing::info!("Suppressed email {} to {}", email.id, email.to_email);
+                }
+                Err(e) => {
+                    record_email_delivery(email.category, "failed");
+                    let status = self.outbox.mark_failed(email, &e.to_string()).await?;
+                    if status == EmailStatus::Failed {
+                        tracing::error!(
+                            "Email {} dead-lettered after {} attempts: {}",
+                            email.id,
+                            email.attempts,
+                            e
+                        );
+                    } else {
+                        tracing::warn!(
+                            "Email {} attempt {} failed: {}",
+                            email.id,
+                            email.attempts,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(emails.len())
+    }
+}
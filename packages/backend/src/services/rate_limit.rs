@@ -0,0 +1,454 @@
+use crate::config::{
+    RateLimitConfig, RateLimitKey, RateLimitPolicy, RateLimitPolicyName, RateLimitStoreKind,
+};
+use crate::database::Database;
+use crate::error::{AppError, Message, Result};
+use crate::middleware::RateLimitLayer;
+use crate::models::RateLimitExemption;
+use crate::utils::JwtService;
+use async_trait::async_trait;
+use axum::extract::{ConnectInfo, Request};
+use axum::http::header::AUTHORIZATION;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// How often idle buckets are dropped from the store.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+/// How stale the cached exemption list may get. Changes made through
+/// another instance show up after this long.
+const EXEMPTIONS_TTL: Duration = Duration::from_secs(30);
+
+/// A bucket after a request tried to take a token from it.
+#[derive(Debug, Clone, Copy)]
+pub struct Acquired {
+    pub allowed: bool,
+    /// Tokens left, fractional while refilling.
+    pub tokens: f64,
+}
+
+/// Keeps token buckets by key.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Refills the bucket for the time since its last use, then takes one
+    /// token if there is one. Unknown keys start full.
+    async fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<Acquired>;
+
+    /// Drops buckets unused for `idle`. They would have refilled by now, so
+    /// this only frees space.
+    async fn prune(&self, idle: Duration) -> Result<()>;
+}
+
+fn capacity(policy: &RateLimitPolicy) -> f64 {
+    f64::from(policy.requests)
+}
+
+/// Tokens per second.
+fn refill_rate(policy: &RateLimitPolicy) -> f64 {
+    f64::from(policy.requests) / policy.per_secs as f64
+}
+
+/// Buckets in this process only; each instance limits on its own.
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<Acquired> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let (tokens, updated_at) = buckets
+            .entry(key.to_string())
+            .or_insert((capacity(policy), now));
+
+        let refilled = (*tokens
+            + now.duration_since(*updated_at).as_secs_f64() * refill_rate(policy))
+        .min(capacity(policy));
+        let allowed = refilled >= 1.0;
+        *tokens = if allowed { refilled - 1.0 } else { refilled };
+        *updated_at = now;
+
+        Ok(Acquired {
+            allowed,
+            tokens: *tokens,
+        })
+    }
+
+    async fn prune(&self, idle: Duration) -> Result<()> {
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, (_, updated_at)| updated_at.elapsed() < idle);
+        Ok(())
+    }
+}
+
+/// Buckets in `rate_limit_buckets`, shared by every instance. Each request
+/// is one upsert, which serializes concurrent requests for the same key.
+pub struct PostgresStore {
+    db: Database,
+}
+
+impl PostgresStore {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for PostgresStore {
+    async fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<Acquired> {
+        // Every SET expression sees the row as it was, so the refilled
+        // amount is spelled out in each.
+        let (tokens, allowed) = sqlx::query_as::<_, (f64, bool)>(
+            r#"
+            INSERT INTO rate_limit_buckets AS b (key, tokens, allowed)
+            VALUES ($1, $2 - 1, TRUE)
+            ON CONFLICT (key) DO UPDATE SET
+                tokens = CASE
+                    WHEN LEAST($2, b.tokens + EXTRACT(EPOCH FROM NOW() - b.updated_at)::DOUBLE PRECISION * $3) >= 1
+                    THEN LEAST($2, b.tokens + EXTRACT(EPOCH FROM NOW() - b.updated_at)::DOUBLE PRECISION * $3) - 1
+                    ELSE LEAST($2, b.tokens + EXTRACT(EPOCH FROM NOW() - b.updated_at)::DOUBLE PRECISION * $3)
+                END,
+                allowed = LEAST($2, b.tokens + EXTRACT(EPOCH FROM NOW() - b.updated_at)::DOUBLE PRECISION * $3) >= 1,
+                updated_at = NOW()
+            RETURNING tokens, allowed
+            "#,
+        )
+        .bind(key)
+        .bind(capacity(policy))
+        .bind(refill_rate(policy))
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(Acquired { allowed, tokens })
+    }
+
+    async fn prune(&self, idle: Duration) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM rate_limit_buckets WHERE updated_at < NOW() - make_interval(secs => $1)",
+        )
+        .bind(idle.as_secs_f64())
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// What the limiter decided for one request, in the terms of the
+/// `RateLimit-*` headers.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub window_secs: u64,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until the next request would be allowed; zero if this one
+    /// was.
+    pub retry_after_secs: u64,
+}
+
+impl RateLimitDecision {
+    fn new(policy: &RateLimitPolicy, acquired: Acquired) -> Self {
+        let rate = refill_rate(policy);
+        let secs_until = |tokens: f64| ((tokens - acquired.tokens).max(0.0) / rate).ceil() as u64;
+
+        Self {
+            allowed: acquired.allowed,
+            limit: policy.requests,
+            window_secs: policy.per_secs,
+            remaining: acquired.tokens.floor() as u32,
+            reset_secs: secs_until(capacity(policy)),
+            retry_after_secs: if acquired.allowed {
+                0
+            } else {
+                secs_until(1.0).max(1)
+            },
+        }
+    }
+}
+
+/// Applies the configured policies. Routes attach one with `layer`; see
+/// `docs/rate-limiting.md`.
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: Arc<RateLimitConfig>,
+    store: Arc<dyn RateLimitStore>,
+    exemptions: RateLimitExemptions,
+    jwt_service: JwtService,
+    last_prune: Arc<Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig, db: Database, jwt_service: JwtService) -> Self {
+        let store: Arc<dyn RateLimitStore> = match config.store {
+            RateLimitStoreKind::Memory => Arc::new(MemoryStore::default()),
+            RateLimitStoreKind::Postgres => Arc::new(PostgresStore::new(db.clone())),
+        };
+
+        Self::with_store(config, store, db, jwt_service)
+    }
+
+    pub fn with_store(
+        config: &RateLimitConfig,
+        store: Arc<dyn RateLimitStore>,
+        db: Database,
+        jwt_service: JwtService,
+    ) -> Self {
+        Self {
+            config: Arc::new(config.clone()),
+            store,
+            exemptions: RateLimitExemptions::new(db),
+            jwt_service,
+            last_prune: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    pub fn layer(&self, policy: RateLimitPolicyName) -> RateLimitLayer {
+        RateLimitLayer::new(self.clone(), policy)
+    }
+
+    pub fn exemptions(&self) -> &RateLimitExemptions {
+        &self.exemptions
+    }
+
+    /// The bucket the request draws from, or `None` if it isn't limited
+    /// because the limiter is off or the user is exempt.
+    pub fn key(&self, name: RateLimitPolicyName, request: &Request) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let bearer = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "));
+        let user_id = bearer
+            .and_then(|token| self.jwt_service.verify_token(token).ok())
+            .map(|claims| claims.user_id);
+
+        if user_id.is_some_and(|user_id| self.exemptions.contains(user_id)) {
+            return None;
+        }
+
+        let subject = match (self.config.policy(name).key, user_id, bearer) {
+            (RateLimitKey::User, Some(user_id), _) => format!("user:{user_id}"),
+            // Unverified tokens would let a client mint a fresh bucket per
+            // request, so only a verified one gets its own.
+            (RateLimitKey::Token, Some(_), Some(token)) => {
+                format!("token:{}", URL_SAFE_NO_PAD.encode(Sha256::digest(token)))
+            }
+            _ => format!("ip:{}", self.client_ip(request)),
+        };
+
+        Some(format!("{}:{subject}", name.as_str()))
+    }
+
+    /// Takes a token from the bucket at `key`. If the store fails the
+    /// request is let through rather than turned away, and `None` is
+    /// returned.
+    pub async fn acquire(&self, name: RateLimitPolicyName, key: &str) -> Option<RateLimitDecision> {
+        let policy = self.config.policy(name);
+        self.prune_if_due();
+
+        match self.store.acquire(key, policy).await {
+            Ok(acquired) => Some(RateLimitDecision::new(policy, acquired)),
+            Err(e) => {
+                tracing::warn!("Rate limit store failed, not limiting {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    /// The peer address, or behind a trusted proxy the address it saw.
+    /// Requests served without connection info share one bucket.
+    fn client_ip(&self, request: &Request) -> String {
+        let forwarded = self
+            .config
+            .trust_forwarded_for
+            .then(|| request.headers().get("x-forwarded-for"))
+            .flatten()
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+
+        forwarded
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|info| info.0.ip())
+            })
+            .map_or_else(|| "unknown".to_string(), |ip| ip.to_string())
+    }
+
+    fn prune_if_due(&self) {
+        {
+            let mut last_prune = self.last_prune.lock().unwrap();
+            if last_prune.elapsed() < PRUNE_INTERVAL {
+                return;
+            }
+            *last_prune = Instant::now();
+        }
+
+        let idle = self
+            .config
+            .policies
+            .values()
+            .map(|policy| Duration::from_secs(policy.per_secs))
+            .max()
+            .unwrap_or(PRUNE_INTERVAL);
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            if let Err(e) = store.prune(idle).await {
+                tracing::warn!("Failed to prune rate limit buckets: {}", e);
+            }
+        });
+    }
+}
+
+/// Users who are never limited. Requests check a cached set, refreshed in
+/// the background, so they never wait on the database.
+#[derive(Clone)]
+pub struct RateLimitExemptions {
+    db: Database,
+    cache: Arc<RwLock<ExemptionCache>>,
+}
+
+#[derive(Default)]
+struct ExemptionCache {
+    users: HashSet<Uuid>,
+    refreshed_at: Option<Instant>,
+    refreshing: bool,
+}
+
+impl RateLimitExemptions {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            cache: Arc::default(),
+        }
+    }
+
+    pub fn contains(&self, user_id: Uuid) -> bool {
+        let (exempt, stale) = {
+            let cache = self.cache.read().unwrap();
+            let stale = !cache.refreshing
+                && cache
+                    .refreshed_at
+                    .is_none_or(|at| at.elapsed() > EXEMPTIONS_TTL);
+            (cache.users.contains(&user_id), stale)
+        };
+
+        if stale {
+            self.refresh_in_background();
+        }
+
+        exempt
+    }
+
+    /// Updates this instance's cache without touching the database; `add`
+    /// and `remove` do both.
+    pub fn remember(&self, user_id: Uuid, exempt: bool) {
+        let mut cache = self.cache.write().unwrap();
+        if exempt {
+            cache.users.insert(user_id);
+        } else {
+            cache.users.remove(&user_id);
+        }
+    }
+
+    fn refresh_in_background(&self) {
+        {
+            let mut cache = self.cache.write().unwrap();
+            if cache.refreshing {
+                return;
+            }
+            cache.refreshing = true;
+        }
+
+        let exemptions = self.clone();
+        tokio::spawn(async move {
+            let users = sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM rate_limit_exemptions")
+                .fetch_all(exemptions.db.pool())
+                .await;
+
+            let mut cache = exemptions.cache.write().unwrap();
+            cache.refreshing = false;
+            cache.refreshed_at = Some(Instant::now());
+            match users {
+                Ok(users) => cache.users = users.into_iter().collect(),
+                Err(e) => tracing::warn!("Failed to load rate limit exemptions: {}", e),
+            }
+        });
+    }
+
+    pub async fn list(&self, limit: i64, offset: i64) -> Result<Vec<RateLimitExemption>> {
+        let exemptions = sqlx::query_as::<_, RateLimitExemption>(
+            r#"
+            SELECT * FROM rate_limit_exemptions
+            ORDER BY created_at DESC
+            LIMIT $1 OFFSET $2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(exemptions)
+    }
+
+    pub async fn add(
+        &self,
+        user_id: Uuid,
+        reason: &str,
+        created_by: Uuid,
+    ) -> Result<RateLimitExemption> {
+        let exemption = sqlx::query_as::<_, RateLimitExemption>(
+            r#"
+            INSERT INTO rate_limit_exemptions (user_id, reason, created_by)
+            SELECT id, $2, $3 FROM users WHERE id = $1
+            ON CONFLICT (user_id) DO UPDATE SET reason = EXCLUDED.reason
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(reason)
+        .bind(created_by)
+        .fetch_optional(self.db.pool())
+        .await?
+        .ok_or_else(|| AppError::NotFound(Message::new("user-not-found")))?;
+
+        self.remember(user_id, true);
+        Ok(exemption)
+    }
+
+    pub async fn remove(&self, user_id: Uuid) -> Result<()> {
+        let result = sqlx::query("DELETE FROM rate_limit_exemptions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(self.db.pool())
+            .await?;
+
+        self.remember(user_id, false);
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(Message::new(
+                "rate-limit-exemption-not-found",
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::config::StorageConfig;
+use crate::error::{AppError, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Stores user-facing media on local disk. Keys are relative paths such as
+/// `avatars/<user-id>/<file>.png` and map 1:1 to public URLs.
+#[derive(Clone)]
+pub struct MediaStorage {
+    root: PathBuf,
+    public_url: String,
+}
+
+impl MediaStorage {
+    pub fn new(config: &StorageConfig) -> Self {
+        Self {
+            root: PathBuf::from(&config.media_dir),
+            public_url: config.public_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Writes `bytes` under `key` and returns the public URL of the file.
+    pub async fn put(&self, key: &str, bytes: &[u8]) -> Result<String> {
+        let path = self.path_for(key)?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| {
+                AppError::Internal(anyhow::anyhow!("Failed to create {parent:?}: {e}"))
+            })?;
+        }
+
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to write {path:?}: {e}")))?;
+
+        Ok(format!("{}/{}", self.public_url, key))
+    }
+
+    /// Deletes the file behind `url` if it lives in this storage. URLs that
+    /// point elsewhere are ignored.
+    pub async fn delete_url(&self, url: &str) -> Result<()> {
+        let Some(key) = self.key_for_url(url) else {
+            return Ok(());
+        };
+
+        match tokio::fs::remove_file(self.path_for(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(AppError::Internal(anyhow::anyhow!(
+                "Failed to delete {key}: {e}"
+            ))),
+        }
+    }
+
+    pub fn key_for_url<'a>(&self, url: &'a str) -> Option<&'a str> {
+        url.strip_prefix(&self.public_url)?.strip_prefix('/')
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(AppError::Internal(anyhow::anyhow!(
+                "Invalid media key: {key}"
+            )));
+        }
+
+        Ok(self.root.join(relative))
+    }
+}
@@ -1,8 +1,10 @@
 use crate::database::Database;
-use crate::error::{AppError, Result};
-use crate::models::{RegisterRequest, User};
+use crate::error::{AppError, Message, Result};
+use crate::middleware::RequestId;
+use crate::models::{AccountLinkRequest, OAuthProfile, RegisterRequest, User};
 use crate::utils::{generate_verification_token, hash_password};
 use chrono::{Duration, Utc};
+use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -15,23 +17,27 @@ impl UserService {
         Self { db }
     }
 
-    pub async fn create_user(&self, request: RegisterRequest) -> Result<User> {
+    pub async fn create_user(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        request: RegisterRequest,
+    ) -> Result<User> {
         let existing_email = sqlx::query("SELECT id FROM users WHERE email = $1")
             .bind(&request.email)
-            .fetch_optional(self.db.pool())
+            .fetch_optional(&mut **tx)
             .await?;
 
         if existing_email.is_some() {
-            return Err(AppError::Conflict("Email already exists".to_string()));
+            return Err(AppError::Conflict(Message::new("email-already-exists")));
         }
 
         let existing_username = sqlx::query("SELECT id FROM users WHERE username = $1")
             .bind(&request.username)
-            .fetch_optional(self.db.pool())
+            .fetch_optional(&mut **tx)
             .await?;
 
         if existing_username.is_some() {
-            return Err(AppError::Conflict("Username already exists".to_string()));
+            return Err(AppError::Conflict(Message::new("username-already-exists")));
         }
 
         let password_hash = hash_password(&request.password)?;
@@ -56,7 +62,7 @@ impl UserService {
         .bind(&verification_token)
         .bind(verification_expires_at)
         .bind(&locale)
-        .fetch_one(self.db.pool())
+        .fetch_one(&mut **tx)
         .await?;
 
         Ok(user)
@@ -106,7 +112,11 @@ impl UserService {
         Ok(())
     }
 
-    pub async fn update_verification_token(&self, user_id: Uuid) -> Result<String> {
+    pub async fn update_verification_token(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Uuid,
+    ) -> Result<String> {
         let verification_token = generate_verification_token();
         let verification_expires_at = Utc::now() + Duration::hours(24);
 
@@ -120,13 +130,17 @@ impl UserService {
         .bind(&verification_token)
         .bind(verification_expires_at)
         .bind(user_id)
-        .execute(self.db.pool())
+        .execute(&mut **tx)
         .await?;
 
         Ok(verification_token)
     }
 
-    pub async fn create_reset_token(&self, email: &str) -> Result<Option<String>> {
+    pub async fn create_reset_token(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        email: &str,
+    ) -> Result<Option<String>> {
         let reset_token = generate_verification_token();
         let reset_expires_at = Utc::now() + Duration::hours(1);
 
@@ -140,7 +154,7 @@ impl UserService {
         .bind(&reset_token)
         .bind(reset_expires_at)
         .bind(email)
-        .execute(self.db.pool())
+        .execute(&mut **tx)
         .await?;
 
         if result.rows_affected() > 0 {
@@ -168,91 +182,283 @@ impl UserService {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn find_or_create_oauth_user(
+    pub async fn find_by_identity(
         &self,
-        email: &str,
-        username: &str,
-        display_name: Option<String>,
-        avatar_url: Option<String>,
         provider: &str,
         provider_id: &str,
-        locale: Option<String>,
-    ) -> Result<User> {
-        if let Some(user) = self.find_by_email(email).await? {
-            if user.provider == provider && user.provider_id.as_deref() == Some(provider_id) {
-                return Ok(user);
-            }
-
-            if user.provider == "local" || user.provider != provider {
-                let user_locale = locale.unwrap_or_else(|| user.locale.clone());
-                let updated_user = sqlx::query_as::<_, User>(
-                    r#"
-                    UPDATE users 
-                    SET provider = $1, provider_id = $2, is_verified = true,
-                        display_name = COALESCE($3, display_name),
-                        avatar_url = COALESCE($4, avatar_url),
-                        locale = $6
-                    WHERE id = $5
-                    RETURNING *
-                    "#,
-                )
-                .bind(provider)
-                .bind(provider_id)
-                .bind(&display_name)
-                .bind(&avatar_url)
-                .bind(user.id)
-                .bind(&user_locale)
-                .fetch_one(self.db.pool())
-                .await?;
+    ) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            SELECT users.* FROM users
+            JOIN user_identities ON user_identities.user_id = users.id
+            WHERE user_identities.provider = $1 AND user_identities.provider_id = $2
+            "#,
+        )
+        .bind(provider)
+        .bind(provider_id)
+        .fetch_optional(self.db.pool())
+        .await?;
 
-                return Ok(updated_user);
-            }
-        }
+        Ok(user)
+    }
 
-        let mut final_username = username.to_string();
+    pub async fn create_oauth_user(&self, profile: &OAuthProfile) -> Result<User> {
+        let mut final_username = profile.username.clone();
         let mut counter = 1;
-        
-        while let Some(_) = sqlx::query("SELECT id FROM users WHERE username = $1")
+
+        while sqlx::query("SELECT id FROM users WHERE username = $1")
             .bind(&final_username)
             .fetch_optional(self.db.pool())
-            .await? {
-            final_username = format!("{}{}", username, counter);
+            .await?
+            .is_some()
+        {
+            final_username = format!("{}{}", profile.username, counter);
             counter += 1;
         }
 
-        let user_locale = locale.unwrap_or_else(|| "en".to_string());
+        let user_locale = profile.locale.clone().unwrap_or_else(|| "en".to_string());
+
+        let mut tx = self.db.pool().begin().await?;
+
         let user = sqlx::query_as::<_, User>(
             r#"
             INSERT INTO users (
-                email, username, display_name, avatar_url, 
+                email, username, display_name,
                 is_verified, provider, provider_id, locale
             )
-            VALUES ($1, $2, $3, $4, true, $5, $6, $7)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             RETURNING *
             "#,
         )
-        .bind(email)
+        .bind(&profile.email)
         .bind(&final_username)
-        .bind(&display_name)
-        .bind(&avatar_url)
-        .bind(provider)
-        .bind(provider_id)
+        .bind(&profile.display_name)
+        .bind(profile.email_verified)
+        .bind(&profile.provider)
+        .bind(&profile.provider_id)
         .bind(&user_locale)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Self::insert_identity(
+            &mut tx,
+            user.id,
+            &profile.provider,
+            &profile.provider_id,
+            &profile.email,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    /// Attaches an OAuth identity to an existing account. Only call this once
+    /// ownership of the account has been established.
+    pub async fn link_identity(&self, user_id: Uuid, profile: &OAuthProfile) -> Result<User> {
+        let mut tx = self.db.pool().begin().await?;
+
+        Self::insert_identity(
+            &mut tx,
+            user_id,
+            &profile.provider,
+            &profile.provider_id,
+            &profile.email,
+        )
+        .await?;
+
+        let user =
+            Self::apply_linked_profile(&mut tx, user_id, profile.display_name.as_deref(), false)
+                .await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    pub async fn create_account_link_request(
+        &self,
+        user_id: Uuid,
+        profile: &OAuthProfile,
+    ) -> Result<AccountLinkRequest> {
+        let link_token = generate_verification_token();
+        let expires_at = Utc::now() + Duration::minutes(30);
+
+        let request = sqlx::query_as::<_, AccountLinkRequest>(
+            r#"
+            INSERT INTO account_link_requests (
+                user_id, link_token, provider, provider_id, provider_email,
+                provider_email_verified, display_name, avatar_url, expires_at, request_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(&link_token)
+        .bind(&profile.provider)
+        .bind(&profile.provider_id)
+        .bind(&profile.email)
+        .bind(profile.email_verified)
+        .bind(&profile.display_name)
+        .bind(&profile.avatar_url)
+        .bind(expires_at)
+        .bind(RequestId::current().map(String::from))
         .fetch_one(self.db.pool())
         .await?;
 
+        Ok(request)
+    }
+
+    pub async fn find_account_link_request(
+        &self,
+        link_token: &str,
+    ) -> Result<Option<AccountLinkRequest>> {
+        let request = sqlx::query_as::<_, AccountLinkRequest>(
+            "SELECT * FROM account_link_requests WHERE link_token = $1 AND expires_at > NOW()",
+        )
+        .bind(link_token)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(request)
+    }
+
+    pub async fn find_account_link_request_by_email_token(
+        &self,
+        email_token: &str,
+    ) -> Result<Option<AccountLinkRequest>> {
+        let request = sqlx::query_as::<_, AccountLinkRequest>(
+            "SELECT * FROM account_link_requests WHERE email_token = $1 AND expires_at > NOW()",
+        )
+        .bind(email_token)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(request)
+    }
+
+    pub async fn update_account_link_email_token(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        request_id: Uuid,
+    ) -> Result<String> {
+        let email_token = generate_verification_token();
+
+        sqlx::query("UPDATE account_link_requests SET email_token = $1 WHERE id = $2")
+            .bind(&email_token)
+            .bind(request_id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(email_token)
+    }
+
+    /// Attaches the identity from a confirmed link request and consumes the
+    /// request. `mark_verified` is set when the email address itself has been
+    /// confirmed, either through our own mail or by the provider.
+    pub async fn complete_account_link(
+        &self,
+        request: &AccountLinkRequest,
+        mark_verified: bool,
+    ) -> Result<User> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let deleted = sqlx::query("DELETE FROM account_link_requests WHERE id = $1")
+            .bind(request.id)
+            .execute(&mut *tx)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(AppError::Authentication(Message::new("invalid-token")));
+        }
+
+        Self::insert_identity(
+            &mut tx,
+            request.user_id,
+            &request.provider,
+            &request.provider_id,
+            &request.provider_email,
+        )
+        .await?;
+
+        let user = Self::apply_linked_profile(
+            &mut tx,
+            request.user_id,
+            request.display_name.as_deref(),
+            mark_verified,
+        )
+        .await?;
+
+        tx.commit().await?;
+
         Ok(user)
     }
 
-    pub async fn update_locale(&self, user_id: Uuid, locale: &str) -> Result<User> {
+    async fn insert_identity(
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Uuid,
+        provider: &str,
+        provider_id: &str,
+        email: &str,
+    ) -> Result<()> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO user_identities (user_id, provider, provider_id, email, request_id)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (provider, provider_id) DO NOTHING
+            "#,
+        )
+        .bind(user_id)
+        .bind(provider)
+        .bind(provider_id)
+        .bind(email)
+        .bind(RequestId::current().map(String::from))
+        .execute(&mut **tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::Conflict(Message::new("oauth-account-conflict")));
+        }
+
+        Ok(())
+    }
+
+    async fn apply_linked_profile(
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Uuid,
+        display_name: Option<&str>,
+        mark_verified: bool,
+    ) -> Result<User> {
         let user = sqlx::query_as::<_, User>(
-            "UPDATE users SET locale = $1 WHERE id = $2 RETURNING *",
+            r#"
+            UPDATE users 
+            SET display_name = COALESCE(display_name, $1),
+                is_verified = is_verified OR $2,
+                verification_token = CASE WHEN $2 THEN NULL ELSE verification_token END,
+                verification_expires_at = CASE WHEN $2 THEN NULL ELSE verification_expires_at END
+            WHERE id = $3
+            RETURNING *
+            "#,
         )
-        .bind(locale)
+        .bind(display_name)
+        .bind(mark_verified)
         .bind(user_id)
-        .fetch_one(self.db.pool())
+        .fetch_one(&mut **tx)
         .await?;
 
         Ok(user)
     }
+
+    pub async fn update_locale(&self, user_id: Uuid, locale: &str) -> Result<User> {
+        let user =
+            sqlx::query_as::<_, User>("UPDATE users SET locale = $1 WHERE id = $2 RETURNING *")
+                .bind(locale)
+                .bind(user_id)
+                .fetch_one(self.db.pool())
+                .await?;
+
+        Ok(user)
+    }
 }
@@ -0,0 +1,89 @@
+use crate::config::Config;
+use crate::database::Database;
+use crate::error::Result;
+use crate::i18n::I18n;
+use crate::services::auth::AuthService;
+use crate::services::oauth::OAuthService;
+use crate::services::{EmailOutbox, EmailPreferenceService, RateLimiter, UserService};
+use crate::utils::{EmailService, JwtService};
+
+/// Everything the routers share, built once by `create_app`. Route modules
+/// take it as their axum state; tests can build one around their own
+/// config, database, HTTP client or mail transport and pass it to
+/// `create_app_with_state`.
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Config,
+    pub db: Database,
+    /// For calls to OAuth providers and avatar downloads; cloning shares its
+    /// connection pool.
+    pub http_client: reqwest::Client,
+    pub i18n: I18n,
+    /// Renders emails. Sending is left to the outbox worker.
+    pub email_service: EmailService,
+    pub jwt_service: JwtService,
+    pub user_service: UserService,
+    pub email_outbox: EmailOutbox,
+    pub email_preferences: EmailPreferenceService,
+    pub auth_service: AuthService,
+    pub oauth_service: OAuthService,
+    /// Shared by every route, so all of them draw on the same buckets.
+    pub rate_limiter: RateLimiter,
+}
+
+impl AppState {
+    pub fn new(config: Config, db: Database) -> Result<Self> {
+        let i18n = I18n::load(&config.i18n)?;
+        let email_service = EmailService::new(&config.mail)?.with_i18n(i18n.clone());
+
+        Self::from_parts(config, db, reqwest::Client::new(), i18n, email_service)
+    }
+
+    /// Builds the services around the given clients, e.g. an email service
+    /// with an in-memory transport.
+    pub fn from_parts(
+        config: Config,
+        db: Database,
+        http_client: reqwest::Client,
+        i18n: I18n,
+        email_service: EmailService,
+    ) -> Result<Self> {
+        let jwt_service = JwtService::new(&config.jwt_secret);
+        let user_service = UserService::new(db.clone());
+        let email_outbox = EmailOutbox::new(db.clone());
+
+        let auth_service = AuthService::new(
+            db.clone(),
+            config.clone(),
+            user_service.clone(),
+            jwt_service.clone(),
+            email_service.clone(),
+            i18n.clone(),
+        );
+        let oauth_service = OAuthService::new(
+            db.clone(),
+            config.clone(),
+            http_client.clone(),
+            user_service.clone(),
+            jwt_service.clone(),
+            email_service.clone(),
+        )?;
+
+        let rate_limiter = RateLimiter::new(&config.rate_limit, db.clone(), jwt_service.clone());
+
+        Ok(Self {
+            email_preferences: EmailPreferenceService::new(db.clone(), &config),
+            config,
+            db,
+            http_client,
+            i18n,
+            email_service,
+            jwt_service,
+            user_service,
+            email_outbox,
+            auth_service,
+            oauth_service,
+            rate_limiter,
+        })
+    }
+}
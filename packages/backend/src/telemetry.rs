@@ -0,0 +1,85 @@
+use crate::models::EmailCategory;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::PgPool;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Histogram buckets for every `*_seconds` metric, from 5ms to 10s.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static PROMETHEUS: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// The process-wide Prometheus recorder, installed on first use. Metrics
+/// recorded before that are dropped, so the app installs it at startup.
+pub fn prometheus() -> PrometheusHandle {
+    PROMETHEUS
+        .get_or_init(|| {
+            PrometheusBuilder::new()
+                .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), LATENCY_BUCKETS)
+                .expect("latency buckets are not empty")
+                .install_recorder()
+                .expect("no other metrics recorder is installed")
+        })
+        .clone()
+}
+
+/// Counts a handled request by its route template, not its concrete path,
+/// so ids don't explode the number of series.
+pub fn record_http_request(method: &str, route: &str, status: u16, started: Instant) {
+    let labels = [
+        ("method", method.to_string()),
+        ("route", route.to_string()),
+        ("status", status.to_string()),
+    ];
+
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(started.elapsed().as_secs_f64());
+}
+
+/// Counts a sign-in attempt. `provider` is `password`, `google` or
+/// `discord`; `outcome` is `success`, `failure` or, for OAuth,
+/// `link_required`.
+pub fn record_login(provider: &'static str, outcome: &'static str) {
+    counter!("auth_logins_total", "provider" => provider, "outcome" => outcome).increment(1);
+}
+
+/// Counts a request turned away with 429 by the named policy.
+pub fn record_rate_limited(policy: &'static str) {
+    counter!("rate_limit_rejections_total", "policy" => policy).increment(1);
+}
+
+/// Times the authorization code exchange with an OAuth provider.
+pub fn record_oauth_exchange(provider: &'static str, succeeded: bool, started: Instant) {
+    let outcome = if succeeded { "success" } else { "failure" };
+
+    histogram!(
+        "oauth_token_exchange_duration_seconds",
+        "provider" => provider,
+        "outcome" => outcome
+    )
+    .record(started.elapsed().as_secs_f64());
+}
+
+/// Counts a delivery attempt from the outbox. `outcome` is `sent`,
+/// `suppressed` or `failed`.
+pub fn record_email_delivery(category: EmailCategory, outcome: &'static str) {
+    counter!(
+        "email_deliveries_total",
+        "category" => category.as_str(),
+        "outcome" => outcome
+    )
+    .increment(1);
+}
+
+/// Samples the connection pool. Called on every scrape.
+pub fn record_pool(pool: &PgPool) {
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+
+    gauge!("db_pool_connections", "state" => "idle").set(idle);
+    gauge!("db_pool_connections", "state" => "in_use").set(size.saturating_sub(idle));
+    gauge!("db_pool_max_connections").set(pool.options().get_max_connections());
+}
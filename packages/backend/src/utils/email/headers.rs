@@ -0,0 +1,44 @@
+use lettre::message::header::{Header, HeaderName, HeaderValue};
+use std::error::Error;
+
+macro_rules! text_header {
+    ($(#[$attr:meta])* $type_name:ident, $header_name:literal) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $type_name(pub String);
+
+        impl Header for $type_name {
+            fn name() -> HeaderName {
+                HeaderName::new_from_ascii_str($header_name)
+            }
+
+            fn parse(s: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+                Ok(Self(s.to_string()))
+            }
+
+            fn display(&self) -> HeaderValue {
+                HeaderValue::new(Self::name(), self.0.clone())
+            }
+        }
+    };
+}
+
+text_header!(
+    /// RFC 3834. Marks the message as machine-generated so auto-responders
+    /// do not reply to it.
+    AutoSubmitted,
+    "Auto-Submitted"
+);
+
+text_header!(
+    /// RFC 2369. One or more `<uri>` entries, comma separated.
+    ListUnsubscribe,
+    "List-Unsubscribe"
+);
+
+text_header!(
+    /// RFC 8058. Tells mailbox providers the `List-Unsubscribe` URL accepts a
+    /// one-click POST.
+    ListUnsubscribePost,
+    "List-Unsubscribe-Post"
+);
@@ -0,0 +1,318 @@
+use crate::config::{DkimAlgorithm, DkimConfig, MailConfig};
+use crate::database::Database;
+use crate::error::{AppError, Message, Result};
+use crate::i18n::I18n;
+use crate::models::{DigestSeries, EmailCategory, OutgoingEmail, User};
+use crate::utils::UnsubscribeTokens;
+use lettre::message::dkim::{
+    DkimCanonicalization, DkimCanonicalizationType, DkimConfig as DkimSigner, DkimSigningAlgorithm,
+    DkimSigningKey,
+};
+use lettre::message::header::HeaderName;
+use lettre::message::{Mailbox, MultiPart, SinglePart};
+use serde_json::json;
+use std::sync::Arc;
+use uuid::Uuid;
+
+mod headers;
+mod suppression;
+mod templates;
+mod transport;
+
+pub use headers::*;
+pub use suppression::*;
+pub use templates::*;
+pub use transport::*;
+
+/// Headers covered by the DKIM signature. `Content-Type` is left out because
+/// lettre only adds it for multipart bodies while formatting, after signing,
+/// so listing it would break the signature.
+const DKIM_SIGNED_HEADERS: &[&str] = &[
+    "From",
+    "To",
+    "Subject",
+    "Date",
+    "Message-ID",
+    "MIME-Version",
+    "List-Unsubscribe",
+    "List-Unsubscribe-Post",
+    "Auto-Submitted",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Sent,
+    /// Not sent because the recipient is suppressed or opted out.
+    Suppressed,
+}
+
+/// Renders transactional emails and delivers them through the configured
+/// transport. Request handlers only render; delivery happens in the outbox
+/// worker.
+#[derive(Clone)]
+pub struct EmailService {
+    transport: Arc<dyn MailTransport>,
+    from_email: Mailbox,
+    dkim: Option<Arc<DkimSigner>>,
+    templates: EmailTemplates,
+    i18n: I18n,
+    unsubscribe_tokens: UnsubscribeTokens,
+    unsubscribe_url: String,
+    suppressions: Option<SuppressionList>,
+}
+
+impl EmailService {
+    pub fn new(config: &MailConfig) -> Result<Self> {
+        Self::with_transport(config, build_transport(&config.transport)?)
+    }
+
+    pub fn with_transport(config: &MailConfig, transport: Arc<dyn MailTransport>) -> Result<Self> {
+        let from_email = format!("{} <{}>", config.from_name, config.from_email)
+            .parse()
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid from email: {}", e)))?;
+
+        let dkim = config
+            .dkim
+            .as_ref()
+            .map(|dkim| load_dkim_signer(dkim).map(Arc::new))
+            .transpose()?;
+
+        let i18n = I18n::new();
+        let templates = EmailTemplates::new(i18n.clone());
+
+        Ok(Self {
+            transport,
+            from_email,
+            dkim,
+            templates,
+            i18n,
+            unsubscribe_tokens: UnsubscribeTokens::new(&config.unsubscribe_secret),
+            unsubscribe_url: config.unsubscribe_url.clone(),
+            suppressions: None,
+        })
+    }
+
+    /// Renders with `i18n` instead of the embedded catalogs.
+    pub fn with_i18n(mut self, i18n: I18n) -> Self {
+        self.templates = EmailTemplates::new(i18n.clone());
+        self.i18n = i18n;
+        self
+    }
+
+    /// Probes the transport, e.g. connects to the SMTP server.
+    pub async fn check_transport(&self) -> Result<()> {
+        self.transport.check().await
+    }
+
+    /// Checks the suppression list and the recipient's preferences before
+    /// every send. Without it, every message is delivered.
+    pub fn with_suppression_list(mut self, db: Database) -> Self {
+        self.suppressions = Some(SuppressionList::new(db));
+        self
+    }
+
+    pub async fn send(&self, email: &OutgoingEmail) -> Result<DeliveryOutcome> {
+        if let Some(suppressions) = &self.suppressions {
+            if !suppressions.allows(&email.to, email.category).await? {
+                return Ok(DeliveryOutcome::Suppressed);
+            }
+        }
+
+        let message = self.build_message(email)?;
+        self.transport.send(message).await?;
+
+        Ok(DeliveryOutcome::Sent)
+    }
+
+    /// Builds the MIME message for `email`, signed when DKIM is configured.
+    pub fn build_message(&self, email: &OutgoingEmail) -> Result<lettre::Message> {
+        let from_address = &self.from_email.email;
+
+        let message = lettre::Message::builder()
+            .from(self.from_email.clone())
+            .to(email
+                .to
+                .parse()
+                .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid to email: {}", e)))?)
+            .subject(&email.subject)
+            .date_now()
+            .message_id(Some(format!(
+                "<{}@{}>",
+                Uuid::new_v4(),
+                from_address.domain()
+            )))
+            .header(AutoSubmitted("auto-generated".to_string()));
+
+        let message = match &email.unsubscribe_url {
+            Some(url) => {
+                message
+                    .header(ListUnsubscribe(format!("<{url}>")))
+                    .header(ListUnsubscribePost(
+                        "List-Unsubscribe=One-Click".to_string(),
+                    ))
+            }
+            None => message,
+        };
+
+        let html = SinglePart::html(email.html_body.clone());
+        let mut message = match &email.text_body {
+            Some(text_body) => message.multipart(
+                MultiPart::alternative()
+                    .singlepart(SinglePart::plain(text_body.clone()))
+                    .singlepart(html),
+            )?,
+            None => message.singlepart(html)?,
+        };
+
+        if let Some(dkim) = &self.dkim {
+            message.sign(dkim);
+        }
+
+        Ok(message)
+    }
+
+    /// Renders `template` in the recipient's stored locale. Categories users
+    /// can opt out of get a signed one-click unsubscribe link.
+    fn render(
+        &self,
+        user: &User,
+        category: EmailCategory,
+        template: &str,
+        subject: Message,
+        context: serde_json::Value,
+    ) -> Result<OutgoingEmail> {
+        let subject = subject.render(&self.i18n, &user.locale);
+
+        let unsubscribe_url = category.can_unsubscribe().then(|| {
+            format!(
+                "{}?token={}",
+                self.unsubscribe_url,
+                self.unsubscribe_tokens.sign(user.id, category)
+            )
+        });
+
+        let mut context = context;
+        context["username"] = json!(user.username);
+        context["subject"] = json!(subject);
+        context["unsubscribe_url"] = json!(unsubscribe_url);
+
+        let rendered = self.templates.render(template, &user.locale, context)?;
+
+        Ok(OutgoingEmail {
+            to: user.email.clone(),
+            subject,
+            html_body: rendered.html,
+            text_body: Some(rendered.text),
+            category,
+            unsubscribe_url,
+        })
+    }
+
+    pub fn verification_email(
+        &self,
+        user: &User,
+        verification_token: &str,
+        frontend_url: &str,
+    ) -> Result<OutgoingEmail> {
+        let verification_url = format!("{frontend_url}/verify-email?token={verification_token}");
+
+        self.render(
+            user,
+            EmailCategory::Security,
+            "verification",
+            Message::new("email-verification-subject"),
+            json!({ "verification_url": verification_url }),
+        )
+    }
+
+    pub fn password_reset_email(
+        &self,
+        user: &User,
+        reset_token: &str,
+        frontend_url: &str,
+    ) -> Result<OutgoingEmail> {
+        let reset_url = format!("{frontend_url}/reset-password?token={reset_token}");
+
+        self.render(
+            user,
+            EmailCategory::Security,
+            "password_reset",
+            Message::new("email-password-reset-subject"),
+            json!({ "reset_url": reset_url }),
+        )
+    }
+
+    pub fn account_link_email(
+        &self,
+        user: &User,
+        provider: &str,
+        link_token: &str,
+        frontend_url: &str,
+    ) -> Result<OutgoingEmail> {
+        let confirm_url = format!("{frontend_url}/auth/link-account?confirm={link_token}");
+
+        self.render(
+            user,
+            EmailCategory::Security,
+            "account_link",
+            Message::new("email-account-link-subject"),
+            json!({ "confirm_url": confirm_url, "provider": provider }),
+        )
+    }
+
+    pub fn digest_email(
+        &self,
+        user: &User,
+        series: &[DigestSeries],
+        frontend_url: &str,
+    ) -> Result<OutgoingEmail> {
+        let chapter_count: usize = series
+            .iter()
+            .map(|item| item.chapters.len() + item.more)
+            .sum();
+
+        self.render(
+            user,
+            EmailCategory::Digests,
+            "digest",
+            Message::new("email-digest-subject"),
+            json!({
+                "series": series,
+                "chapter_count": chapter_count,
+                "site_url": frontend_url,
+            }),
+        )
+    }
+}
+
+fn load_dkim_signer(config: &DkimConfig) -> Result<DkimSigner> {
+    let private_key = std::fs::read_to_string(&config.private_key_file).map_err(|e| {
+        AppError::Internal(anyhow::anyhow!(
+            "Failed to read DKIM key {}: {}",
+            config.private_key_file,
+            e
+        ))
+    })?;
+
+    let algorithm = match config.algorithm {
+        DkimAlgorithm::Rsa => DkimSigningAlgorithm::Rsa,
+        DkimAlgorithm::Ed25519 => DkimSigningAlgorithm::Ed25519,
+    };
+
+    let signing_key = DkimSigningKey::new(private_key.trim(), algorithm)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid DKIM key: {:?}", e)))?;
+
+    Ok(DkimSigner::new(
+        config.selector.clone(),
+        config.domain.clone(),
+        signing_key,
+        DKIM_SIGNED_HEADERS
+            .iter()
+            .map(|name| HeaderName::new_from_ascii_str(name))
+            .collect(),
+        DkimCanonicalization {
+            header: DkimCanonicalizationType::Relaxed,
+            body: DkimCanonicalizationType::Relaxed,
+        },
+    ))
+}
@@ -0,0 +1,52 @@
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::EmailCategory;
+
+/// Decides whether a message may be sent. Addresses on the suppression list
+/// get nothing, since they bounce or complain. Users who opted out of a
+/// category get nothing from it; security mail cannot be opted out of
+/// because it is requested by the user.
+#[derive(Clone)]
+pub struct SuppressionList {
+    db: Database,
+}
+
+impl SuppressionList {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub async fn allows(&self, email: &str, category: EmailCategory) -> Result<bool> {
+        let suppressed = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM email_suppressions WHERE email = LOWER($1))",
+        )
+        .bind(email)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        if suppressed {
+            return Ok(false);
+        }
+        if !category.can_unsubscribe() {
+            return Ok(true);
+        }
+
+        let preference = sqlx::query_scalar::<_, Option<bool>>(
+            r#"
+            SELECT p.enabled
+            FROM users u
+            LEFT JOIN email_preferences p ON p.user_id = u.id AND p.category = $2
+            WHERE LOWER(u.email) = LOWER($1)
+            "#,
+        )
+        .bind(email)
+        .bind(category)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(match preference {
+            Some(enabled) => enabled.unwrap_or_else(|| category.enabled_by_default()),
+            None => true,
+        })
+    }
+}
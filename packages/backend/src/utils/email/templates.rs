@@ -0,0 +1,105 @@
+use crate::error::{AppError, Result};
+use crate::i18n::I18n;
+use fluent_bundle::{FluentArgs, FluentValue};
+use minijinja::value::Kwargs;
+use minijinja::{Environment, State, Value};
+use serde::Serialize;
+use std::sync::Arc;
+
+macro_rules! embed_templates {
+    ($($name:literal),* $(,)?) => {
+        &[$(($name, include_str!(concat!("templates/", $name)))),*]
+    };
+}
+
+const TEMPLATES: &[(&str, &str)] = embed_templates![
+    "layout.html",
+    "layout.txt",
+    "macros.html",
+    "verification.html",
+    "verification.txt",
+    "password_reset.html",
+    "password_reset.txt",
+    "account_link.html",
+    "account_link.txt",
+    "digest.html",
+    "digest.txt",
+];
+
+/// A template rendered in one locale, with both alternative parts.
+pub struct RenderedTemplate {
+    pub html: String,
+    pub text: String,
+}
+
+/// Email templates. `.html` templates are auto-escaped; every piece of copy
+/// comes from the Fluent bundles through the `t(id, **args)` function, using
+/// the `locale` the template is rendered with.
+#[derive(Clone)]
+pub struct EmailTemplates {
+    env: Arc<Environment<'static>>,
+}
+
+impl EmailTemplates {
+    pub fn new(i18n: I18n) -> Self {
+        let mut env = Environment::new();
+
+        for (name, source) in TEMPLATES {
+            env.add_template(name, source)
+                .expect("Failed to parse email template");
+        }
+
+        env.add_function("t", move |state: &State, id: &str, kwargs: Kwargs| {
+            translate(&i18n, state, id, kwargs)
+        });
+
+        Self { env: Arc::new(env) }
+    }
+
+    /// Renders `{name}.html` and `{name}.txt` with `context` plus `locale`.
+    pub fn render<S: Serialize>(
+        &self,
+        name: &str,
+        locale: &str,
+        context: S,
+    ) -> Result<RenderedTemplate> {
+        let context = minijinja::context! { locale => locale, ..Value::from_serialize(context) };
+
+        let render = |extension: &str| {
+            self.env
+                .get_template(&format!("{name}.{extension}"))
+                .and_then(|template| template.render(&context))
+                .map_err(|e| {
+                    AppError::Internal(anyhow::anyhow!("Failed to render {name} email: {e}"))
+                })
+        };
+
+        Ok(RenderedTemplate {
+            html: render("html")?,
+            text: render("txt")?,
+        })
+    }
+}
+
+fn translate(
+    i18n: &I18n,
+    state: &State,
+    id: &str,
+    kwargs: Kwargs,
+) -> std::result::Result<String, minijinja::Error> {
+    let locale = state
+        .lookup("locale")
+        .and_then(|locale| locale.as_str().map(str::to_string))
+        .unwrap_or_else(|| "en".to_string());
+
+    let mut args = FluentArgs::new();
+    for key in kwargs.args() {
+        let value: Value = kwargs.get(key)?;
+        match value.as_i64() {
+            Some(number) => args.set(key.to_string(), FluentValue::from(number)),
+            None => args.set(key.to_string(), FluentValue::from(value.to_string())),
+        }
+    }
+
+    Ok(i18n.get_message(&locale, id, Some(&args)))
+}
@@ -0,0 +1,220 @@
+use crate::config::{MailTransportConfig, SmtpConfig, SmtpSecurity};
+use crate::error::{AppError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::message::header::Subject;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+/// Delivers fully built messages. `EmailService` renders and builds the
+/// message; the transport only decides where it goes.
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, message: Message) -> Result<()>;
+
+    /// Checks that mail can currently be handed off, without sending any.
+    async fn check(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub fn build_transport(config: &MailTransportConfig) -> Result<Arc<dyn MailTransport>> {
+    Ok(match config {
+        MailTransportConfig::Smtp(smtp) => Arc::new(SmtpMailTransport::new(smtp)?),
+        MailTransportConfig::File { dir } => Arc::new(FileMailTransport::new(dir)),
+        MailTransportConfig::Maildir { dir } => Arc::new(MaildirMailTransport::new(dir)),
+        MailTransportConfig::Stdout => Arc::new(StdoutMailTransport),
+        MailTransportConfig::Memory => Arc::new(MemoryMailTransport::default()),
+    })
+}
+
+pub struct SmtpMailTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailTransport {
+    pub fn new(config: &SmtpConfig) -> Result<Self> {
+        let builder = match config.security {
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            }
+        };
+
+        let mut builder = builder.port(config.port);
+
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            mailer: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl MailTransport for SmtpMailTransport {
+    async fn send(&self, message: Message) -> Result<()> {
+        self.mailer.send(message).await?;
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<()> {
+        if self.mailer.test_connection().await? {
+            Ok(())
+        } else {
+            Err(AppError::Internal(anyhow::anyhow!(
+                "SMTP server did not accept the connection"
+            )))
+        }
+    }
+}
+
+/// Writes each message to `{dir}/{timestamp}-{id}.eml`.
+pub struct FileMailTransport {
+    dir: PathBuf,
+}
+
+impl FileMailTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl MailTransport for FileMailTransport {
+    async fn send(&self, message: Message) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(io_error)?;
+
+        let path = self.dir.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            Uuid::new_v4()
+        ));
+        tokio::fs::write(&path, message.formatted())
+            .await
+            .map_err(io_error)?;
+
+        tracing::debug!("Wrote email to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Delivers into a maildir: the message is written to `tmp/` and then moved
+/// to `new/`, so readers never see a partial file.
+pub struct MaildirMailTransport {
+    dir: PathBuf,
+}
+
+impl MaildirMailTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl MailTransport for MaildirMailTransport {
+    async fn send(&self, message: Message) -> Result<()> {
+        for subdir in ["tmp", "new", "cur"] {
+            tokio::fs::create_dir_all(self.dir.join(subdir))
+                .await
+                .map_err(io_error)?;
+        }
+
+        let name = format!(
+            "{}.{}.formangareaders",
+            Utc::now().timestamp(),
+            Uuid::new_v4().simple()
+        );
+        let tmp_path = self.dir.join("tmp").join(&name);
+        let new_path = self.dir.join("new").join(&name);
+
+        tokio::fs::write(&tmp_path, message.formatted())
+            .await
+            .map_err(io_error)?;
+        tokio::fs::rename(&tmp_path, &new_path)
+            .await
+            .map_err(io_error)?;
+
+        tracing::debug!("Delivered email to {}", new_path.display());
+        Ok(())
+    }
+}
+
+/// Prints every message to stdout instead of sending it.
+pub struct StdoutMailTransport;
+
+#[async_trait]
+impl MailTransport for StdoutMailTransport {
+    async fn send(&self, message: Message) -> Result<()> {
+        let mut output = message.formatted();
+        output.extend_from_slice(b"\n\n");
+
+        let mut stdout = tokio::io::stdout();
+        stdout.write_all(&output).await.map_err(io_error)?;
+        stdout.flush().await.map_err(io_error)?;
+
+        Ok(())
+    }
+}
+
+/// A message captured by `MemoryMailTransport`.
+#[derive(Debug, Clone)]
+pub struct CapturedEmail {
+    pub to: Vec<String>,
+    pub subject: Option<String>,
+    pub raw: String,
+}
+
+/// Keeps sent messages in memory so tests can assert on them. Clones share
+/// the same mailbox.
+#[derive(Clone, Default)]
+pub struct MemoryMailTransport {
+    messages: Arc<Mutex<Vec<CapturedEmail>>>,
+}
+
+impl MemoryMailTransport {
+    pub fn messages(&self) -> Vec<CapturedEmail> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.messages.lock().unwrap().clear();
+    }
+}
+
+#[async_trait]
+impl MailTransport for MemoryMailTransport {
+    async fn send(&self, message: Message) -> Result<()> {
+        let captured = CapturedEmail {
+            to: message
+                .envelope()
+                .to()
+                .iter()
+                .map(|address| address.to_string())
+                .collect(),
+            subject: message
+                .headers()
+                .get::<Subject>()
+                .map(|subject| subject.as_ref().to_string()),
+            raw: String::from_utf8_lossy(&message.formatted()).into_owned(),
+        };
+
+        self.messages.lock().unwrap().push(captured);
+        Ok(())
+    }
+}
+
+fn io_error(e: std::io::Error) -> AppError {
+    AppError::Internal(anyhow::anyhow!("Failed to write email: {e}"))
+}
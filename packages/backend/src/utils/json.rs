@@ -0,0 +1,113 @@
+use crate::error::{AppError, FieldError, Message};
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header::CONTENT_TYPE, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::error::Category;
+
+/// `axum::Json`, except that a body that isn't the expected JSON is rejected
+/// with a problem document like any other invalid request. Failing fields
+/// are reported by path in `errors`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    /// Failures to read the body, such as exceeding the size limit, keep
+    /// axum's response.
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !has_json_content_type(req.headers()) {
+            return Err(
+                AppError::Validation(Message::new("json-content-type-required")).into_response(),
+            );
+        }
+
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        parse(&bytes).map(Json).map_err(IntoResponse::into_response)
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// Deserializes a JSON body, naming the field at fault when it is well-formed
+/// but doesn't match `T`.
+fn parse<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, AppError> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(bytes);
+    let value = serde_path_to_error::deserialize(&mut *deserializer).map_err(|err| {
+        let path = err.path().to_string();
+        let inner = err.into_inner();
+        if inner.classify() != Category::Data {
+            return AppError::Validation(Message::new("malformed-json"));
+        }
+
+        AppError::InvalidFields(vec![field_error(path, &inner)])
+    })?;
+    deserializer
+        .end()
+        .map_err(|_| AppError::Validation(Message::new("malformed-json")))?;
+
+    Ok(value)
+}
+
+fn field_error(path: String, err: &serde_json::Error) -> FieldError {
+    // serde reports a missing field against the object that lacks it.
+    let text = err.to_string();
+    if let Some(name) = text
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next())
+    {
+        let field = match path.as_str() {
+            "." => name.to_string(),
+            parent => format!("{parent}.{name}"),
+        };
+        return FieldError {
+            field,
+            message: Message::new("validation-required"),
+        };
+    }
+
+    let message = if text.starts_with("invalid type") {
+        Message::new("validation-type")
+    } else {
+        Message::new("validation-invalid")
+    };
+
+    FieldError {
+        field: path,
+        message,
+    }
+}
+
+fn has_json_content_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    essence == "application/json"
+        || essence
+            .strip_prefix("application/")
+            .is_some_and(|subtype| subtype.ends_with("+json"))
+}
@@ -1,7 +1,11 @@
 pub mod auth;
 pub mod email;
+pub mod json;
+pub mod unsubscribe;
 pub mod validation;
 
 pub use auth::*;
 pub use email::*;
+pub use json::*;
+pub use unsubscribe::*;
 pub use validation::*;
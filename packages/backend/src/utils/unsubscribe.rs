@@ -0,0 +1,51 @@
+use crate::models::EmailCategory;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs and checks one-click unsubscribe tokens. A token names a user and a
+/// category and never expires, so links in old emails keep working.
+#[derive(Clone)]
+pub struct UnsubscribeTokens {
+    secret: Vec<u8>,
+}
+
+impl UnsubscribeTokens {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            secret: secret.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn sign(&self, user_id: Uuid, category: EmailCategory) -> String {
+        let payload = format!("{}:{}", user_id, category.as_str());
+        let signature = self.mac(&payload).finalize().into_bytes();
+
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    pub fn verify(&self, token: &str) -> Option<(Uuid, EmailCategory)> {
+        let (payload, signature) = token.split_once('.')?;
+        let payload = String::from_utf8(URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+        self.mac(&payload).verify_slice(&signature).ok()?;
+
+        let (user_id, category) = payload.split_once(':')?;
+        Some((user_id.parse().ok()?, EmailCategory::parse(category)?))
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key size");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
@@ -1,29 +1,52 @@
-use crate::error::{AppError, Result};
-use validator::Validate;
+use crate::error::{AppError, FieldError, Message, MessageArg, Result};
+use validator::{Validate, ValidationError};
 
+/// Validates `request`, reporting each failing field with a message id.
+/// Validators should set `code` to a message id; the built-in codes map to
+/// generic `validation-*` messages.
 pub fn validate_request<T: Validate>(request: &T) -> Result<()> {
     request.validate().map_err(|errors| {
-        let error_messages: Vec<String> = errors
+        let mut field_errors: Vec<FieldError> = errors
             .field_errors()
-            .iter()
+            .into_iter()
             .flat_map(|(field, errors)| {
-                errors.iter().map(move |error| {
-                    format!(
-                        "{}: {}",
-                        field,
-                        error.message.as_ref().unwrap_or(&"Invalid value".into())
-                    )
+                errors.iter().map(move |error| FieldError {
+                    field: field.to_string(),
+                    message: field_message(error),
                 })
             })
             .collect();
+        field_errors.sort_by(|a, b| a.field.cmp(&b.field));
 
-        AppError::Validation(error_messages.join(", "))
+        AppError::InvalidFields(field_errors)
     })
 }
 
-pub fn is_valid_locale(locale: &str) -> bool {
-    matches!(
-        locale,
-        "en" | "tr" | "es" | "fr" | "de" | "ja" | "ko" | "zh"
-    )
+fn field_message(error: &ValidationError) -> Message {
+    let message = match error.code.as_ref() {
+        "email" => Message::new("validation-email"),
+        "length" => Message::new("validation-length"),
+        "range" => Message::new("validation-range"),
+        "required" => Message::new("validation-required"),
+        code => Message::new(code.to_string()),
+    };
+
+    // The rejected value is never echoed back; it may be a password.
+    let mut params: Vec<_> = error
+        .params
+        .iter()
+        .filter(|(name, _)| name.as_ref() != "value")
+        .collect();
+    params.sort_by(|a, b| a.0.cmp(b.0));
+
+    params.into_iter().fold(message, |message, (name, value)| {
+        let arg = match value {
+            serde_json::Value::Number(number) => {
+                MessageArg::Number(number.as_f64().unwrap_or_default())
+            }
+            serde_json::Value::String(text) => MessageArg::Text(text.clone()),
+            other => MessageArg::Text(other.to_string()),
+        };
+        message.arg(name.clone(), arg)
+    })
 }
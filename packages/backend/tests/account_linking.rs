@@ -0,0 +1,270 @@
+mod common;
+
+use backend::error::AppError;
+use backend::models::OAuthLoginOutcome;
+use backend::services::OAuthService;
+use backend::utils::hash_password;
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state_with, TestDatabase};
+use uuid::Uuid;
+
+const EMAIL: &str = "reader@example.com";
+const PASSWORD: &str = "correct horse battery";
+
+struct Fixture {
+    _idp: MockIdp,
+    db: TestDatabase,
+    service: OAuthService,
+}
+
+async fn fixture(scenario: MockScenario) -> Option<Fixture> {
+    let db = TestDatabase::create().await?;
+    let idp = MockIdp::start(scenario).await;
+    let mut config = test_config(&idp);
+    config.storage = None;
+    let service = test_state_with(config, &db).oauth_service;
+
+    Some(Fixture {
+        _idp: idp,
+        db,
+        service,
+    })
+}
+
+impl Fixture {
+    async fn local_user(&self, verified: bool) -> Uuid {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO users (email, username, password_hash, is_verified)
+            VALUES ($1, 'reader', $2, $3)
+            RETURNING id
+            "#,
+        )
+        .bind(EMAIL)
+        .bind(hash_password(PASSWORD).unwrap())
+        .bind(verified)
+        .fetch_one(self.db.pool())
+        .await
+        .unwrap()
+    }
+
+    /// Runs the Discord redirect flow against the mock provider.
+    async fn sign_in(&self) -> Result<OAuthLoginOutcome, AppError> {
+        let authorization = self.service.get_discord_auth_url().await.unwrap();
+        let state = reqwest::Url::parse(&authorization.url)
+            .unwrap()
+            .query_pairs()
+            .find(|(name, _)| name == "state")
+            .map(|(_, value)| value.into_owned())
+            .unwrap();
+
+        self.service
+            .handle_discord_callback(
+                "auth-code",
+                Some(&state),
+                Some(&authorization.binding),
+                "en",
+            )
+            .await
+    }
+
+    async fn link_code(&self) -> String {
+        match self.sign_in().await.unwrap() {
+            OAuthLoginOutcome::LinkRequired { link_code } => link_code,
+            outcome => panic!("expected a link request, got {outcome:?}"),
+        }
+    }
+
+    /// Signs in and exchanges the link code, as the link-account page does.
+    async fn link_token(&self) -> String {
+        let pending = self
+            .service
+            .exchange_link_code(&self.link_code().await)
+            .await
+            .unwrap();
+        assert_eq!(pending.email, EMAIL);
+
+        pending.token
+    }
+
+    async fn identities(&self, user_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM user_identities WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(self.db.pool())
+            .await
+            .unwrap()
+    }
+}
+
+fn is_invalid(err: &AppError, id: &str) -> bool {
+    matches!(err, AppError::Authentication(message) if message.id == id)
+}
+
+#[tokio::test]
+async fn verified_on_both_sides_links_automatically() {
+    let Some(fixture) = fixture(MockScenario::Success).await else {
+        return;
+    };
+    let user_id = fixture.local_user(true).await;
+
+    let outcome = fixture.sign_in().await.unwrap();
+
+    assert!(matches!(outcome, OAuthLoginOutcome::Authenticated { .. }));
+    assert_eq!(fixture.identities(user_id).await, 1);
+}
+
+#[tokio::test]
+async fn unverified_local_account_requires_confirmation() {
+    let Some(fixture) = fixture(MockScenario::Success).await else {
+        return;
+    };
+    let user_id = fixture.local_user(false).await;
+
+    fixture.link_token().await;
+
+    assert_eq!(fixture.identities(user_id).await, 0);
+}
+
+#[tokio::test]
+async fn unverified_provider_email_requires_confirmation() {
+    let Some(fixture) = fixture(MockScenario::UnverifiedEmail).await else {
+        return;
+    };
+    let user_id = fixture.local_user(true).await;
+
+    fixture.link_token().await;
+
+    assert_eq!(fixture.identities(user_id).await, 0);
+}
+
+#[tokio::test]
+async fn linking_with_the_wrong_password_is_rejected() {
+    let Some(fixture) = fixture(MockScenario::Success).await else {
+        return;
+    };
+    let user_id = fixture.local_user(false).await;
+    let token = fixture.link_token().await;
+
+    let err = fixture
+        .service
+        .link_with_password(&token, "not the password")
+        .await
+        .unwrap_err();
+
+    assert!(is_invalid(&err, "invalid-credentials"));
+    assert_eq!(fixture.identities(user_id).await, 0);
+
+    // The request survives the failed attempt.
+    let linked = fixture
+        .service
+        .link_with_password(&token, PASSWORD)
+        .await
+        .unwrap();
+    assert_eq!(linked.user.email, EMAIL);
+    assert_eq!(fixture.identities(user_id).await, 1);
+}
+
+#[tokio::test]
+async fn expired_link_tokens_are_rejected() {
+    let Some(fixture) = fixture(MockScenario::Success).await else {
+        return;
+    };
+    let user_id = fixture.local_user(false).await;
+    let token = fixture.link_token().await;
+
+    sqlx::query(
+        "UPDATE account_link_requests SET expires_at = NOW() - INTERVAL '1 minute' WHERE link_token = $1",
+    )
+    .bind(&token)
+    .execute(fixture.db.pool())
+    .await
+    .unwrap();
+
+    let err = fixture
+        .service
+        .link_with_password(&token, PASSWORD)
+        .await
+        .unwrap_err();
+
+    assert!(is_invalid(&err, "invalid-token"));
+    assert_eq!(fixture.identities(user_id).await, 0);
+}
+
+#[tokio::test]
+async fn link_tokens_cannot_be_reused() {
+    let Some(fixture) = fixture(MockScenario::Success).await else {
+        return;
+    };
+    fixture.local_user(false).await;
+    let token = fixture.link_token().await;
+
+    fixture
+        .service
+        .link_with_password(&token, PASSWORD)
+        .await
+        .unwrap();
+    let err = fixture
+        .service
+        .link_with_password(&token, PASSWORD)
+        .await
+        .unwrap_err();
+
+    assert!(is_invalid(&err, "invalid-token"));
+}
+
+#[tokio::test]
+async fn confirmation_email_links_once() {
+    let Some(fixture) = fixture(MockScenario::UnverifiedEmail).await else {
+        return;
+    };
+    let user_id = fixture.local_user(false).await;
+    let token = fixture.link_token().await;
+
+    fixture
+        .service
+        .send_link_confirmation(&token)
+        .await
+        .unwrap();
+    let email_token: String =
+        sqlx::query_scalar("SELECT email_token FROM account_link_requests WHERE link_token = $1")
+            .bind(&token)
+            .fetch_one(fixture.db.pool())
+            .await
+            .unwrap();
+
+    let linked = fixture
+        .service
+        .confirm_link_email(&email_token)
+        .await
+        .unwrap();
+    assert!(linked.user.is_verified);
+    assert_eq!(fixture.identities(user_id).await, 1);
+
+    let err = fixture
+        .service
+        .confirm_link_email(&email_token)
+        .await
+        .unwrap_err();
+    assert!(is_invalid(&err, "invalid-token"));
+}
+
+#[tokio::test]
+async fn link_codes_can_only_be_exchanged_once() {
+    let Some(fixture) = fixture(MockScenario::Success).await else {
+        return;
+    };
+    fixture.local_user(false).await;
+    let code = fixture.link_code().await;
+
+    fixture.service.exchange_link_code(&code).await.unwrap();
+    let err = fixture.service.exchange_link_code(&code).await.unwrap_err();
+    assert!(is_invalid(&err, "invalid-token"));
+
+    let code = fixture.link_code().await;
+    sqlx::query("UPDATE oauth_link_codes SET expires_at = NOW() - INTERVAL '1 second'")
+        .execute(fixture.db.pool())
+        .await
+        .unwrap();
+    let err = fixture.service.exchange_link_code(&code).await.unwrap_err();
+    assert!(is_invalid(&err, "invalid-token"));
+}
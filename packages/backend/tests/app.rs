@@ -0,0 +1,43 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use backend::create_app_with_state;
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn get(app: Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn every_router_uses_the_injected_state() {
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let mut config = test_config(&idp);
+    config.discord = None;
+    let app = create_app_with_state(test_state(config));
+
+    let (status, body) = get(app.clone(), "/api/v1/auth/providers").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "google": true, "discord": false }));
+
+    let (status, body) = get(app.clone(), "/api/v1/auth/discord").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "oauth.provider_disabled");
+    assert_eq!(body["detail"], "Sign-in with Discord is not available");
+
+    let (status, _) = get(app, "/healthz").await;
+    assert_eq!(status, StatusCode::OK);
+}
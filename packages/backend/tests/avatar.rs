@@ -0,0 +1,153 @@
+mod common;
+
+use axum::http::HeaderValue;
+use backend::middleware::RequestId;
+use backend::services::{process_avatar, AvatarService, MediaStorage};
+use common::mock_idp::{png, MockIdp, MockScenario};
+use common::{test_config, TestDatabase};
+use image::{GenericImageView, ImageFormat};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+#[test]
+fn avatars_are_cropped_to_a_square_png() {
+    let avatar = process_avatar(&png(600, 300)).unwrap();
+
+    assert_eq!(image::guess_format(&avatar).unwrap(), ImageFormat::Png);
+    let image = image::load_from_memory(&avatar).unwrap();
+    assert_eq!(image.dimensions(), (256, 256));
+}
+
+#[test]
+fn small_avatars_are_scaled_up() {
+    let avatar = process_avatar(&png(32, 48)).unwrap();
+
+    let image = image::load_from_memory(&avatar).unwrap();
+    assert_eq!(image.dimensions(), (256, 256));
+}
+
+#[test]
+fn oversized_images_are_not_decoded() {
+    assert!(process_avatar(&png(4097, 1)).is_err());
+    assert!(process_avatar(&png(1, 4097)).is_err());
+    assert!(process_avatar(&png(4096, 1)).is_ok());
+}
+
+#[test]
+fn non_images_are_rejected() {
+    assert!(process_avatar(b"<html>not an avatar</html>").is_err());
+    assert!(process_avatar(&[]).is_err());
+
+    // A PNG signature does not make the rest of the file an image.
+    let mut truncated = png(64, 64);
+    truncated.truncate(40);
+    assert!(process_avatar(&truncated).is_err());
+}
+
+#[test]
+fn unsupported_formats_are_rejected() {
+    let mut bmp = std::io::Cursor::new(Vec::new());
+    image::RgbImage::new(8, 8)
+        .write_to(&mut bmp, ImageFormat::Bmp)
+        .ok();
+
+    assert!(process_avatar(bmp.get_ref()).is_err());
+}
+
+#[tokio::test]
+async fn pending_upstream_avatars_are_mirrored_once() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let config = test_config(&idp);
+    let storage = MediaStorage::new(config.storage.as_ref().unwrap());
+    let service = AvatarService::new((*db).clone(), storage, reqwest::Client::new());
+
+    let insert = |username: &'static str, upstream_url: String| {
+        let pool = db.pool().clone();
+        async move {
+            sqlx::query_scalar::<_, Uuid>(
+                r#"
+                INSERT INTO users (email, username, avatar_upstream_url, avatar_provider)
+                VALUES ($1 || '@example.com', $1, $2, 'discord')
+                RETURNING id
+                "#,
+            )
+            .bind(username)
+            .bind(upstream_url)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        }
+    };
+    let carried_over = insert("carried", idp.url("/cdn/avatars/1/a.png")).await;
+    let broken = insert("broken", idp.url("/missing.png")).await;
+
+    let mirrored = service
+        .mirror_pending(&CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!(mirrored, 1);
+
+    let avatar = |user_id: Uuid| {
+        let pool = db.pool().clone();
+        async move {
+            sqlx::query_as::<_, (Option<String>, bool)>(
+                "SELECT avatar_url, avatar_synced_at IS NOT NULL FROM users WHERE id = $1",
+            )
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        }
+    };
+    let (url, synced) = avatar(carried_over).await;
+    assert!(url
+        .unwrap()
+        .starts_with("http://localhost:8000/media/avatars/"));
+    assert!(synced);
+    let (url, synced) = avatar(broken).await;
+    assert!(url.is_none());
+    assert!(synced);
+
+    // Failures are not retried until the next login.
+    let mirrored = service
+        .mirror_pending(&CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!(mirrored, 0);
+}
+
+#[tokio::test]
+async fn scheduled_syncs_carry_the_request_id() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let config = test_config(&idp);
+    let storage = MediaStorage::new(config.storage.as_ref().unwrap());
+    let service = AvatarService::new((*db).clone(), storage, reqwest::Client::new());
+    let user_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO users (email, username) VALUES ('a@example.com', 'a') RETURNING id",
+    )
+    .fetch_one(db.pool())
+    .await
+    .unwrap();
+
+    let request_id = RequestId::parse(&HeaderValue::from_static("req-avatar")).unwrap();
+    request_id
+        .scope(async {
+            service.schedule_sync(user_id, "discord", Some(&idp.url("/cdn/avatars/1/a.png")));
+        })
+        .await;
+
+    for _ in 0..100 {
+        if !idp.cdn_request_ids().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(idp.cdn_request_ids(), vec![Some("req-avatar".to_string())]);
+}
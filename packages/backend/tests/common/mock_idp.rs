@@ -0,0 +1,182 @@
+use super::{id_token_claims, sign_id_token, test_jwks, TEST_CLIENT_ID, TEST_KEY_ID};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+pub const MOCK_NONCE: &str = "mock-nonce";
+pub const MOCK_ACCESS_TOKEN: &str = "mock-access-token";
+
+/// How the mock identity provider answers the OAuth flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockScenario {
+    Success,
+    MissingEmail,
+    /// The provider returns the address but has not verified it.
+    UnverifiedEmail,
+    TokenExchangeFailure,
+    UserinfoFailure,
+}
+
+struct MockState {
+    scenario: MockScenario,
+    issuer: String,
+    jwks_requests: AtomicUsize,
+    cdn_request_ids: Mutex<Vec<Option<String>>>,
+}
+
+/// An OAuth2/OpenID Connect provider served from a local port. It issues
+/// ID tokens signed with the fixture key and serves a Discord-style user
+/// endpoint.
+pub struct MockIdp {
+    pub base_url: String,
+    state: Arc<MockState>,
+}
+
+impl MockIdp {
+    pub async fn start(scenario: MockScenario) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let state = Arc::new(MockState {
+            scenario,
+            issuer: base_url.clone(),
+            jwks_requests: AtomicUsize::new(0),
+            cdn_request_ids: Mutex::default(),
+        });
+
+        let app = Router::new()
+            .route("/authorize", get(|| async { StatusCode::OK }))
+            .route("/token", post(token))
+            .route("/userinfo", get(userinfo))
+            .route("/jwks", get(jwks))
+            .route("/cdn/*path", get(cdn))
+            .with_state(state.clone());
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self { base_url, state }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.state.issuer
+    }
+
+    pub fn jwks_requests(&self) -> usize {
+        self.state.jwks_requests.load(Ordering::SeqCst)
+    }
+
+    /// The `X-Request-Id` of each CDN request, in order.
+    pub fn cdn_request_ids(&self) -> Vec<Option<String>> {
+        self.state.cdn_request_ids.lock().unwrap().clone()
+    }
+
+    /// Signs an ID token the way the token endpoint does.
+    pub fn id_token(&self, nonce: &str) -> String {
+        issue_id_token(&self.state, nonce)
+    }
+}
+
+fn issue_id_token(state: &MockState, nonce: &str) -> String {
+    let mut claims = id_token_claims(nonce);
+    claims["iss"] = state.issuer.clone().into();
+    claims["aud"] = TEST_CLIENT_ID.into();
+
+    if state.scenario == MockScenario::MissingEmail {
+        let claims = claims.as_object_mut().unwrap();
+        claims.remove("email");
+        claims.remove("email_verified");
+    }
+    if state.scenario == MockScenario::UnverifiedEmail {
+        claims["email_verified"] = false.into();
+    }
+
+    sign_id_token(&claims, TEST_KEY_ID)
+}
+
+async fn token(State(state): State<Arc<MockState>>) -> Response {
+    if state.scenario == MockScenario::TokenExchangeFailure {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "invalid_grant",
+                "error_description": "Authorization code expired"
+            })),
+        )
+            .into_response();
+    }
+
+    Json(json!({
+        "access_token": MOCK_ACCESS_TOKEN,
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "id_token": issue_id_token(&state, MOCK_NONCE)
+    }))
+    .into_response()
+}
+
+async fn userinfo(State(state): State<Arc<MockState>>) -> Response {
+    match state.scenario {
+        MockScenario::UserinfoFailure => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Internal Server Error" })),
+        )
+            .into_response(),
+        MockScenario::MissingEmail => Json(json!({
+            "id": "80351110224678912",
+            "username": "reader",
+            "global_name": "Test Reader",
+            "avatar": "8342729096ea3675442027381ff50dfe"
+        }))
+        .into_response(),
+        scenario => Json(json!({
+            "id": "80351110224678912",
+            "username": "reader",
+            "global_name": "Test Reader",
+            "email": "reader@example.com",
+            "verified": scenario != MockScenario::UnverifiedEmail,
+            "avatar": "8342729096ea3675442027381ff50dfe"
+        }))
+        .into_response(),
+    }
+}
+
+async fn jwks(State(state): State<Arc<MockState>>) -> impl IntoResponse {
+    state.jwks_requests.fetch_add(1, Ordering::SeqCst);
+    (
+        [("cache-control", "public, max-age=3600")],
+        Json(test_jwks()),
+    )
+}
+
+/// Serves every CDN path as a 300x200 PNG.
+async fn cdn(State(state): State<Arc<MockState>>, headers: HeaderMap) -> impl IntoResponse {
+    let request_id = headers
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    state.cdn_request_ids.lock().unwrap().push(request_id);
+
+    ([("content-type", "image/png")], png(300, 200))
+}
+
+/// A solid PNG of the given size.
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = image::RgbImage::from_pixel(width, height, image::Rgb([200, 80, 40]));
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, image::ImageFormat::Png)
+        .expect("encodes PNG");
+    bytes.into_inner()
+}
@@ -0,0 +1,254 @@
+#![allow(dead_code)]
+
+pub mod mock_idp;
+
+use backend::config::{
+    CorsConfig, CorsPolicy, DiscordConfig, DiscordEndpoints, GoogleConfig, GoogleEndpoints,
+    HealthConfig, I18nConfig, MailConfig, MailTransportConfig, MetricsConfig, RateLimitConfig,
+    StorageConfig,
+};
+use backend::models::{User, UserRole};
+use backend::{AppState, Config, Database};
+use chrono::Utc;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use mock_idp::MockIdp;
+use serde_json::{json, Value};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{ConnectOptions, Connection, PgConnection};
+use std::ops::Deref;
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub const TEST_KEY_ID: &str = "test-key";
+pub const TEST_ISSUER: &str = "https://idp.test";
+pub const TEST_CLIENT_ID: &str = "test-client";
+
+const TEST_PRIVATE_KEY: &str = include_str!("../fixtures/oidc_test_key.pem");
+const TEST_KEY_MODULUS: &str = "phUnKfnQGDlnipfSDWjLJqhajMXgLPW3KQD8VGTKCRt7O2N4iteyJ10v0pEL8-0Yt1IVjvblDRVQDMlxW_DFU0Nh32PmNt_A8AIH1pcI8pVAyeytamY7JZTap-uVsgkf_Rjgpm57qHoIAGVavDOuihIfyXxZXiMFnDm7Knxr14NskT2hq9_7a50ijtqxUeUvvXSyh3yZr9J4FfKVUSy6UavOs0v0OJs5No0MauP2nwHN6qS9dB0fqwD0tL1gSxGvtJDR76S7m3qxI4MFY-PFl0ARcvoX7q8aM9owDGiVaNctuCOrXb-OZgeqPI_ps2uoXu5scFMDp_99sSRwyRptYw";
+
+/// JWK set holding the public half of the fixture key.
+pub fn test_jwks() -> JwkSet {
+    serde_json::from_value(json!({
+        "keys": [{
+            "kty": "RSA",
+            "use": "sig",
+            "alg": "RS256",
+            "kid": TEST_KEY_ID,
+            "n": TEST_KEY_MODULUS,
+            "e": "AQAB"
+        }]
+    }))
+    .expect("valid test JWKS")
+}
+
+/// Claims for a valid ID token; tests override individual fields.
+pub fn id_token_claims(nonce: &str) -> Value {
+    let now = Utc::now().timestamp();
+    json!({
+        "iss": TEST_ISSUER,
+        "aud": TEST_CLIENT_ID,
+        "sub": "1234567890",
+        "iat": now,
+        "exp": now + 300,
+        "nonce": nonce,
+        "email": "reader@example.com",
+        "email_verified": true,
+        "name": "Test Reader",
+        "given_name": "Test",
+        "picture": "https://idp.test/avatar.png"
+    })
+}
+
+pub fn sign_id_token(claims: &Value, kid: &str) -> String {
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(kid.to_string());
+
+    let key = EncodingKey::from_rsa_pem(TEST_PRIVATE_KEY.as_bytes()).expect("valid test key");
+    encode(&header, claims, &key).expect("signed test token")
+}
+
+/// Mail settings with the in-memory transport.
+pub fn mail_config() -> MailConfig {
+    MailConfig {
+        from_email: "noreply@example.com".to_string(),
+        from_name: "ForMangaReaders".to_string(),
+        transport: MailTransportConfig::Memory,
+        dkim: None,
+        unsubscribe_secret: "test-unsubscribe-secret".to_string(),
+        unsubscribe_url: "https://api.test/email/unsubscribe".to_string(),
+    }
+}
+
+/// A verified local account as it would be loaded from the database; tests
+/// override individual fields.
+pub fn test_user() -> User {
+    User {
+        id: Uuid::new_v4(),
+        email: "reader@example.com".to_string(),
+        username: "reader".to_string(),
+        password_hash: None,
+        display_name: None,
+        avatar_url: None,
+        role: UserRole::User,
+        is_verified: true,
+        verification_token: None,
+        verification_expires_at: None,
+        reset_token: None,
+        reset_expires_at: None,
+        provider: "local".to_string(),
+        provider_id: None,
+        locale: "en".to_string(),
+        timezone: "UTC".to_string(),
+        digest_day: 1,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+/// Configuration pointing both OAuth providers at the mock identity provider.
+pub fn test_config(idp: &MockIdp) -> Config {
+    Config {
+        port: 0,
+        shutdown_timeout_secs: 1,
+        database_url: "postgres://localhost/formangareaders_test".to_string(),
+        app_name: "ForMangaReaders".to_string(),
+        jwt_secret: "test-secret".to_string(),
+        google: Some(GoogleConfig {
+            client_id: TEST_CLIENT_ID.to_string(),
+            client_secret: "test-secret".to_string(),
+            endpoints: GoogleEndpoints {
+                auth_url: idp.url("/authorize"),
+                token_url: idp.url("/token"),
+                jwks_url: idp.url("/jwks"),
+                issuers: vec![idp.issuer().to_string()],
+            },
+        }),
+        discord: Some(DiscordConfig {
+            client_id: TEST_CLIENT_ID.to_string(),
+            client_secret: "test-secret".to_string(),
+            endpoints: DiscordEndpoints {
+                auth_url: idp.url("/authorize"),
+                token_url: idp.url("/token"),
+                userinfo_url: idp.url("/userinfo"),
+                cdn_url: idp.url("/cdn"),
+            },
+        }),
+        mail: mail_config(),
+        i18n: I18nConfig::default(),
+        metrics: MetricsConfig::default(),
+        health: HealthConfig::default(),
+        cors: CorsConfig {
+            policy: CorsPolicy::for_origins(vec!["http://localhost:3000".to_string()]),
+            overrides: Vec::new(),
+        },
+        rate_limit: RateLimitConfig::default(),
+        storage: Some(StorageConfig {
+            media_dir: std::env::temp_dir()
+                .join("formangareaders-test-media")
+                .to_string_lossy()
+                .into_owned(),
+            public_url: "http://localhost:8000/media".to_string(),
+        }),
+        frontend_url: "http://localhost:3000".to_string(),
+        backend_url: "http://localhost:8000".to_string(),
+    }
+}
+
+/// A database handle that never connects; enough for services whose tested
+/// code paths do not touch Postgres.
+pub fn lazy_database(config: &Config) -> Database {
+    Database {
+        pool: PgPoolOptions::new()
+            .connect_lazy(&config.database_url)
+            .expect("valid database URL"),
+    }
+}
+
+/// Application state over a lazy database, as `create_app` would build it.
+pub fn test_state(config: Config) -> AppState {
+    let db = lazy_database(&config);
+    AppState::new(config, db).expect("state builds")
+}
+
+/// A migrated database created for one test and dropped with the guard.
+pub struct TestDatabase {
+    admin_url: String,
+    name: String,
+    db: Database,
+}
+
+impl TestDatabase {
+    /// Creates a fresh database on the server named by `TEST_DATABASE_URL`.
+    /// Returns `None` when the variable is unset so tests needing Postgres
+    /// can skip themselves.
+    pub async fn create() -> Option<Self> {
+        let Ok(admin_url) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("TEST_DATABASE_URL is not set, skipping");
+            return None;
+        };
+        let name = format!("fmr_test_{}", Uuid::new_v4().simple());
+
+        let mut admin = PgConnection::connect(&admin_url)
+            .await
+            .expect("connect to TEST_DATABASE_URL");
+        sqlx::query(&format!("CREATE DATABASE {name}"))
+            .execute(&mut admin)
+            .await
+            .expect("create test database");
+        admin.close().await.ok();
+
+        let options = PgConnectOptions::from_str(&admin_url)
+            .expect("valid TEST_DATABASE_URL")
+            .database(&name)
+            .disable_statement_logging();
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .expect("connect to test database");
+        sqlx::migrate!().run(&pool).await.expect("run migrations");
+
+        Some(Self {
+            admin_url,
+            name,
+            db: Database { pool },
+        })
+    }
+}
+
+impl Deref for TestDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        let admin_url = self.admin_url.clone();
+        let statement = format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", self.name);
+
+        // The test runtime may already be shutting down, so drop the
+        // database from a runtime of our own.
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                if let Ok(mut admin) = PgConnection::connect(&admin_url).await {
+                    sqlx::query(&statement).execute(&mut admin).await.ok();
+                }
+            });
+        })
+        .join()
+        .ok();
+    }
+}
+
+/// Application state over a real test database.
+pub fn test_state_with(config: Config, db: &TestDatabase) -> AppState {
+    AppState::new(config, db.db.clone()).expect("state builds")
+}
@@ -0,0 +1,285 @@
+use backend::config::{
+    ConfigError, MailTransportConfig, RateLimitKey, RateLimitPolicyName, RateLimitStoreKind,
+    SmtpSecurity,
+};
+use backend::Config;
+use std::collections::HashMap;
+use std::path::Path;
+
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+    vars.iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn minimal_env() -> HashMap<String, String> {
+    env(&[
+        ("DATABASE_URL", "postgres://localhost/formangareaders"),
+        ("JWT_SECRET_KEY", SECRET),
+        ("FRONTEND_URL", "http://localhost:3000"),
+        ("BACKEND_URL", "http://localhost:8000/"),
+        ("MAIL_TRANSPORT", "stdout"),
+    ])
+}
+
+fn problems(result: Result<Config, ConfigError>) -> Vec<String> {
+    match result {
+        Err(ConfigError::Invalid(problems)) => {
+            problems.iter().map(|problem| problem.to_string()).collect()
+        }
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("configuration should be invalid"),
+    }
+}
+
+#[test]
+fn optional_integrations_are_disabled_by_default() {
+    let config = Config::from_sources(None, &minimal_env()).unwrap();
+
+    assert_eq!(config.port, 8000);
+    assert_eq!(config.backend_url, "http://localhost:8000");
+    assert!(config.google.is_none());
+    assert!(config.discord.is_none());
+    assert!(config.storage.is_none());
+    assert!(matches!(config.mail.transport, MailTransportConfig::Stdout));
+    assert_eq!(
+        config.mail.unsubscribe_url,
+        "http://localhost:8000/api/v1/email/unsubscribe"
+    );
+}
+
+#[test]
+fn a_mail_transport_must_be_chosen() {
+    let mut env = minimal_env();
+    env.remove("MAIL_TRANSPORT");
+
+    assert_eq!(
+        problems(Config::from_sources(None, &env)),
+        vec!["mail.transport (MAIL_TRANSPORT): is required without mail.smtp.host; set it to stdout to print mail instead"]
+    );
+
+    env.insert("SMTP_HOST".to_string(), "smtp.example.com".to_string());
+    env.insert(
+        "MAIL_FROM_EMAIL".to_string(),
+        "noreply@example.com".to_string(),
+    );
+    let config = Config::from_sources(None, &env).unwrap();
+    assert!(matches!(
+        config.mail.transport,
+        MailTransportConfig::Smtp(_)
+    ));
+}
+
+#[test]
+fn environment_overrides_the_file() {
+    let file = r#"
+        port = 9000
+        app_name = "Shelf"
+
+        [discord]
+        client_id = "file-id"
+        client_secret = "file-secret"
+
+        [mail.smtp]
+        host = "smtp.example.com"
+        security = "tls"
+
+        [mail]
+        from_email = "hello@example.com"
+
+        [storage]
+        media_dir = "/var/lib/shelf/media"
+    "#;
+    let mut vars = minimal_env();
+    vars.remove("MAIL_TRANSPORT");
+    vars.insert("PORT".to_string(), "9100".to_string());
+    vars.insert(
+        "DISCORD_CLIENT_SECRET".to_string(),
+        "env-secret".to_string(),
+    );
+
+    let config = Config::from_sources(Some((Path::new("config.toml"), file)), &vars).unwrap();
+
+    assert_eq!(config.port, 9100);
+    assert_eq!(config.app_name, "Shelf");
+    assert_eq!(config.mail.from_name, "Shelf");
+
+    let discord = config.discord.unwrap();
+    assert_eq!(discord.client_id, "file-id");
+    assert_eq!(discord.client_secret, "env-secret");
+    assert_eq!(discord.endpoints.cdn_url, "https://cdn.discordapp.com");
+
+    let MailTransportConfig::Smtp(smtp) = config.mail.transport else {
+        panic!("an SMTP host selects the SMTP transport");
+    };
+    assert_eq!(smtp.security, SmtpSecurity::Tls);
+    assert_eq!(smtp.port, 465);
+
+    assert_eq!(
+        config.storage.unwrap().public_url,
+        "http://localhost:8000/media"
+    );
+}
+
+#[test]
+fn every_problem_is_reported_at_once() {
+    let vars = env(&[
+        ("DATABASE_URL", "mysql://localhost/db"),
+        ("JWT_SECRET_KEY", "short"),
+        ("FRONTEND_URL", "localhost:3000"),
+        ("PORT", "eighty"),
+        ("GOOGLE_CLIENT_ID", "only-the-id"),
+        ("SMTP_HOST", "smtp.example.com"),
+    ]);
+
+    let problems = problems(Config::from_sources(None, &vars));
+
+    for expected in [
+        "port (PORT)",
+        "database_url (DATABASE_URL): must be a postgres:// URL",
+        "jwt_secret (JWT_SECRET_KEY): must be at least 32 characters",
+        "frontend_url (FRONTEND_URL)",
+        "backend_url (BACKEND_URL): is required",
+        "google.client_secret (GOOGLE_CLIENT_SECRET): is required",
+        "mail.from_email (MAIL_FROM_EMAIL): is required",
+    ] {
+        assert!(
+            problems.iter().any(|problem| problem.starts_with(expected)),
+            "missing {expected:?} in {problems:#?}"
+        );
+    }
+    assert_eq!(problems.len(), 7, "{problems:#?}");
+}
+
+#[test]
+fn unknown_keys_in_the_file_are_rejected() {
+    let file = "[google]\nclient_secrte = \"typo\"\n";
+
+    let result = Config::from_sources(Some((Path::new("config.toml"), file)), &minimal_env());
+
+    assert!(matches!(result, Err(ConfigError::Parse { .. })));
+}
+
+#[test]
+fn example_file_is_valid() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("config.example.toml");
+    let file = std::fs::read_to_string(&path).unwrap();
+
+    let config = Config::from_sources(Some((&path, &file)), &HashMap::new()).unwrap();
+
+    assert!(config.google.is_some());
+    assert!(config.discord.is_some());
+    assert!(config.storage.is_some());
+}
+
+#[test]
+fn cors_defaults_to_the_frontend_and_validates_overrides() {
+    let config = Config::from_sources(None, &minimal_env()).unwrap();
+    assert_eq!(
+        config.cors.policy.allowed_origins,
+        ["http://localhost:3000"]
+    );
+    assert!(!config.cors.policy.allow_credentials);
+
+    let file = r#"
+        [cors.policy]
+        allowed_origins = ["https://formangareaders.example/"]
+        allow_credentials = true
+
+        [[cors.overrides]]
+        path = "/api/v1/feeds/"
+        policy = { allowed_origins = ["*"], allow_credentials = false }
+
+        [[cors.overrides]]
+        path = "/api/v1/series"
+        policy = { allowed_origins = ["*"], allowed_methods = ["GE T"] }
+    "#;
+    let problems = problems(Config::from_sources(
+        Some((Path::new("config.toml"), file)),
+        &minimal_env(),
+    ));
+
+    assert_eq!(
+        problems,
+        [
+            "cors.overrides: /api/v1/series: allowed_methods \"GE T\" is not an HTTP method",
+            "cors.overrides: /api/v1/series: allow_credentials can't be combined with the \"*\" origin; list the origins instead",
+        ]
+    );
+}
+
+#[test]
+fn rate_limit_policies_override_the_built_in_ones() {
+    let file = r#"
+        [rate_limit]
+        store = "postgres"
+
+        [rate_limit.policies.auth-strict]
+        requests = 5
+        key = "token"
+    "#;
+    let mut vars = minimal_env();
+    vars.insert(
+        "RATE_LIMIT_TRUST_FORWARDED_FOR".to_string(),
+        "true".to_string(),
+    );
+    let config = Config::from_sources(Some((Path::new("config.toml"), file)), &vars).unwrap();
+
+    let rate_limit = &config.rate_limit;
+    assert!(rate_limit.enabled);
+    assert!(rate_limit.trust_forwarded_for);
+    assert_eq!(rate_limit.store, RateLimitStoreKind::Postgres);
+    let strict = rate_limit.policy(RateLimitPolicyName::AuthStrict);
+    assert_eq!((strict.requests, strict.per_secs), (5, 60));
+    assert_eq!(strict.key, RateLimitKey::Token);
+    assert_eq!(
+        rate_limit.policy(RateLimitPolicyName::Upload),
+        &RateLimitPolicyName::Upload.default_policy()
+    );
+
+    let file = r#"
+        [rate_limit.policies.search]
+        requests = 100
+
+        [rate_limit.policies.upload]
+        per_secs = 0
+        key = "session"
+    "#;
+    let mut vars = minimal_env();
+    vars.insert("RATE_LIMIT_STORE".to_string(), "redis".to_string());
+    let problems = problems(Config::from_sources(
+        Some((Path::new("config.toml"), file)),
+        &vars,
+    ));
+
+    assert_eq!(
+        problems,
+        [
+            "rate_limit.store (RATE_LIMIT_STORE): unknown store \"redis\", expected memory or postgres",
+            "rate_limit.policies: unknown policy \"search\", expected auth-strict, read-default or upload",
+            "rate_limit.policies: upload: per_secs must be at least 1",
+            "rate_limit.policies: upload: unknown key \"session\", expected ip, user or token",
+        ]
+    );
+}
+
+#[test]
+fn unsubscribe_links_are_not_signed_with_the_session_key() {
+    let config = Config::from_sources(None, &minimal_env()).unwrap();
+    let derived = config.mail.unsubscribe_secret;
+    assert_ne!(derived, SECRET);
+    assert_eq!(derived.len(), 43);
+
+    // Derivation is stable, so links in sent mail keep working.
+    let again = Config::from_sources(None, &minimal_env()).unwrap();
+    assert_eq!(again.mail.unsubscribe_secret, derived);
+
+    let mut vars = minimal_env();
+    vars.insert("UNSUBSCRIBE_SECRET".to_string(), SECRET.to_string());
+    assert_eq!(
+        problems(Config::from_sources(None, &vars)),
+        vec!["mail.unsubscribe_secret (UNSUBSCRIBE_SECRET): must differ from jwt_secret; leave it unset to derive one"]
+    );
+}
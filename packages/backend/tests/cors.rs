@@ -0,0 +1,81 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, Method, Request};
+use axum::Router;
+use backend::config::{CorsOverride, CorsPolicy};
+use backend::create_app_with_state;
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state};
+use tower::ServiceExt;
+
+const FRONTEND: &str = "http://localhost:3000";
+
+async fn app() -> Router {
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let mut config = test_config(&idp);
+    config.cors.policy.allow_credentials = true;
+    config.cors.overrides = vec![CorsOverride {
+        path: "/api/v1/openapi.json".to_string(),
+        policy: CorsPolicy::for_origins(vec!["*".to_string()]),
+    }];
+
+    create_app_with_state(test_state(config))
+}
+
+async fn preflight(app: Router, uri: &str, origin: &str) -> HeaderMap {
+    let request = Request::builder()
+        .method(Method::OPTIONS)
+        .uri(uri)
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization")
+        .body(Body::empty())
+        .unwrap();
+
+    app.oneshot(request).await.unwrap().headers().clone()
+}
+
+#[tokio::test]
+async fn only_the_frontend_may_call_the_api() {
+    let app = app().await;
+
+    let headers = preflight(app.clone(), "/api/v1/auth/me", FRONTEND).await;
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], FRONTEND);
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "3600");
+    assert!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS]
+        .to_str()
+        .unwrap()
+        .contains("authorization"));
+
+    let headers = preflight(app, "/api/v1/auth/me", "https://evil.example").await;
+    assert!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
+
+#[tokio::test]
+async fn overrides_apply_to_their_path_only() {
+    let app = app().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/openapi.json")
+                .header(header::ORIGIN, "https://tools.example")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let headers = response.headers();
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    assert!(headers.get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+    assert_eq!(
+        headers[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+        "x-request-id,ratelimit-limit,ratelimit-remaining,ratelimit-reset,ratelimit-policy,retry-after"
+    );
+
+    let headers = preflight(app, "/api/v1/openapi.jsonx", "https://tools.example").await;
+    assert!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
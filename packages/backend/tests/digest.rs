@@ -0,0 +1,113 @@
+mod common;
+
+use backend::models::{DigestEntry, DigestSeries, User};
+use backend::services::is_digest_due;
+use backend::utils::{EmailService, MemoryMailTransport};
+use chrono::{TimeZone, Utc};
+use common::{mail_config, test_user};
+use std::sync::Arc;
+
+fn service() -> EmailService {
+    EmailService::with_transport(&mail_config(), Arc::new(MemoryMailTransport::default())).unwrap()
+}
+
+fn user(locale: &str) -> User {
+    User {
+        locale: locale.to_string(),
+        timezone: "Europe/Istanbul".to_string(),
+        ..test_user()
+    }
+}
+
+#[test]
+fn digest_is_due_on_the_local_day_and_hour() {
+    // Monday 2026-10-19 06:00 UTC is 09:00 in Istanbul (UTC+3).
+    let monday_morning = Utc.with_ymd_and_hms(2026, 10, 19, 6, 0, 0).unwrap();
+
+    assert!(is_digest_due(monday_morning, "Europe/Istanbul", 1, None));
+    // Still 06:00 in London, before the digest hour.
+    assert!(!is_digest_due(monday_morning, "Europe/London", 1, None));
+    // Wrong weekday.
+    assert!(!is_digest_due(monday_morning, "Europe/Istanbul", 2, None));
+
+    // Sunday 23:00 UTC is already Monday 08:00 in Tokyo; two hours later it is due.
+    let sunday_night = Utc.with_ymd_and_hms(2026, 10, 18, 23, 0, 0).unwrap();
+    assert!(!is_digest_due(sunday_night, "Asia/Tokyo", 1, None));
+    assert!(is_digest_due(
+        sunday_night + chrono::Duration::hours(2),
+        "Asia/Tokyo",
+        1,
+        None
+    ));
+}
+
+#[test]
+fn digest_runs_once_per_local_day() {
+    let monday_morning = Utc.with_ymd_and_hms(2026, 10, 19, 6, 0, 0).unwrap();
+    let later_that_day = monday_morning + chrono::Duration::hours(8);
+    let last_week = monday_morning - chrono::Duration::days(7);
+
+    assert!(!is_digest_due(
+        later_that_day,
+        "Europe/Istanbul",
+        1,
+        Some(monday_morning)
+    ));
+    assert!(is_digest_due(
+        monday_morning,
+        "Europe/Istanbul",
+        1,
+        Some(last_week)
+    ));
+}
+
+#[test]
+fn unknown_timezone_falls_back_to_utc() {
+    let monday_morning = Utc.with_ymd_and_hms(2026, 10, 19, 9, 30, 0).unwrap();
+
+    assert!(is_digest_due(monday_morning, "Mars/Olympus_Mons", 1, None));
+}
+
+#[test]
+fn digest_email_lists_chapters_with_unsubscribe_link() {
+    let series = vec![DigestSeries {
+        title: "One <Piece>".to_string(),
+        url: "https://app.test/series/one-piece".to_string(),
+        chapters: vec![
+            DigestEntry {
+                number: "1100".to_string(),
+                title: Some("The Final Saga".to_string()),
+            },
+            DigestEntry {
+                number: "1100.5".to_string(),
+                title: None,
+            },
+        ],
+        more: 1,
+    }];
+
+    let email = service()
+        .digest_email(&user("en"), &series, "https://app.test")
+        .unwrap();
+
+    assert_eq!(email.subject, "Your weekly ForMangaReaders digest");
+    assert!(email.html_body.contains("One &lt;Piece&gt;"));
+    assert!(email
+        .html_body
+        .contains("There are 3 new chapters in the series you follow:"));
+    assert!(email.html_body.contains("Chapter 1100: The Final Saga"));
+    assert!(email.html_body.contains("and 1 more chapter"));
+
+    let unsubscribe_url = email.unsubscribe_url.as_deref().unwrap();
+    assert!(unsubscribe_url.starts_with("https://api.test/email/unsubscribe?token="));
+    assert!(email
+        .text_body
+        .as_deref()
+        .unwrap()
+        .contains(unsubscribe_url));
+
+    let tr = service()
+        .digest_email(&user("tr"), &series, "https://app.test")
+        .unwrap();
+    assert!(tr.html_body.contains("Bölüm 1100.5"));
+}
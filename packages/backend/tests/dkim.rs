@@ -0,0 +1,221 @@
+mod common;
+
+use backend::config::{DkimAlgorithm, DkimConfig, MailConfig};
+use backend::models::{EmailCategory, OutgoingEmail};
+use backend::utils::{EmailService, MemoryMailTransport};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use common::mail_config;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const KEY_FILE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/dkim_test_key.pem"
+);
+
+fn signed_message(subject: &str) -> String {
+    let config = MailConfig {
+        dkim: Some(DkimConfig {
+            domain: "example.com".to_string(),
+            selector: "test".to_string(),
+            private_key_file: KEY_FILE.to_string(),
+            algorithm: DkimAlgorithm::Rsa,
+        }),
+        ..mail_config()
+    };
+
+    let service = EmailService::with_transport(&config, Arc::new(MemoryMailTransport::default()))
+        .expect("service builds");
+
+    let message = service
+        .build_message(&OutgoingEmail {
+            to: "reader@example.com".to_string(),
+            subject: subject.to_string(),
+            html_body: "<p>Hello   reader</p>\n".to_string(),
+            text_body: Some("Hello   reader\n".to_string()),
+            category: EmailCategory::Digests,
+            unsubscribe_url: Some(
+                "https://example.com/api/v1/email/unsubscribe?token=abc.def".to_string(),
+            ),
+        })
+        .unwrap();
+
+    String::from_utf8(message.formatted()).unwrap()
+}
+
+fn public_key() -> RsaPublicKey {
+    let pem = std::fs::read_to_string(KEY_FILE).unwrap();
+    RsaPrivateKey::from_pkcs1_pem(&pem).unwrap().to_public_key()
+}
+
+/// Splits a raw message into unfolded-as-is header fields and the body.
+fn split_message(raw: &str) -> (Vec<(String, String)>, &str) {
+    let (header_block, body) = raw.split_once("\r\n\r\n").unwrap();
+
+    let mut fields: Vec<String> = Vec::new();
+    for line in header_block.split("\r\n") {
+        if line.starts_with([' ', '\t']) {
+            let last = fields.last_mut().unwrap();
+            last.push_str("\r\n");
+            last.push_str(line);
+        } else {
+            fields.push(line.to_string());
+        }
+    }
+
+    let headers = fields
+        .into_iter()
+        .map(|field| {
+            let (name, value) = field.split_once(':').unwrap();
+            (name.to_string(), value.to_string())
+        })
+        .collect();
+
+    (headers, body)
+}
+
+/// RFC 6376 section 3.4.2.
+fn relaxed_header(name: &str, value: &str) -> String {
+    let unfolded = value.replace("\r\n", "");
+    let collapsed = unfolded.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{}:{}", name.trim().to_lowercase(), collapsed)
+}
+
+/// RFC 6376 section 3.4.4.
+fn relaxed_body(body: &str) -> String {
+    let mut lines: Vec<String> = body
+        .split("\r\n")
+        .map(|line| {
+            let mut collapsed = String::new();
+            let mut in_whitespace = false;
+            for c in line.chars() {
+                if c == ' ' || c == '\t' {
+                    in_whitespace = true;
+                } else {
+                    if in_whitespace {
+                        collapsed.push(' ');
+                    }
+                    in_whitespace = false;
+                    collapsed.push(c);
+                }
+            }
+            collapsed
+        })
+        .collect();
+
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    if lines.is_empty() {
+        String::new()
+    } else {
+        lines.join("\r\n") + "\r\n"
+    }
+}
+
+fn verify_dkim(raw: &str, key: &RsaPublicKey) -> Result<(), String> {
+    let (headers, body) = split_message(raw);
+
+    let (signature_name, signature_value) = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("DKIM-Signature"))
+        .ok_or("missing DKIM-Signature")?;
+
+    let tags: HashMap<String, String> = signature_value
+        .replace("\r\n", "")
+        .split(';')
+        .filter_map(|tag| tag.split_once('='))
+        .map(|(name, value)| {
+            (
+                name.trim().to_string(),
+                value.split_whitespace().collect::<String>(),
+            )
+        })
+        .collect();
+
+    assert_eq!(tags["v"], "1");
+    assert_eq!(tags["a"], "rsa-sha256");
+    assert_eq!(tags["c"], "relaxed/relaxed");
+    assert_eq!(tags["d"], "example.com");
+    assert_eq!(tags["s"], "test");
+
+    let body_hash = STANDARD.encode(Sha256::digest(relaxed_body(body)));
+    if body_hash != tags["bh"] {
+        return Err("body hash mismatch".to_string());
+    }
+
+    let mut signed = String::new();
+    for name in tags["h"].split(':') {
+        let (name, value) = headers
+            .iter()
+            .rev()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name.trim()))
+            .ok_or(format!("signed header {name} is missing"))?;
+        signed.push_str(&relaxed_header(name, value));
+        signed.push_str("\r\n");
+    }
+
+    let unsigned_value = signature_value
+        .split(';')
+        .map(|tag| match tag.trim_start().strip_prefix("b=") {
+            Some(_) => &tag[..tag.find("b=").unwrap() + 2],
+            None => tag,
+        })
+        .collect::<Vec<_>>()
+        .join(";");
+    signed.push_str(&relaxed_header(signature_name, &unsigned_value));
+
+    let signature = STANDARD.decode(&tags["b"]).map_err(|e| e.to_string())?;
+
+    key.verify(
+        Pkcs1v15Sign::new::<Sha256>(),
+        &Sha256::digest(signed.as_bytes()),
+        &signature,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[test]
+fn signature_verifies_against_test_key() {
+    let raw = signed_message("Verify your email address");
+
+    verify_dkim(&raw, &public_key()).unwrap();
+}
+
+#[test]
+fn tampered_subject_fails_verification() {
+    let raw = signed_message("Verify your email address")
+        .replace("Subject: Verify your email address", "Subject: Free prizes");
+
+    assert!(verify_dkim(&raw, &public_key()).is_err());
+}
+
+#[test]
+fn deliverability_headers_are_set() {
+    let raw = signed_message("Verify your email address");
+    let (headers, _) = split_message(&raw);
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string())
+    };
+
+    assert!(header("Message-ID").is_some_and(|id| id.ends_with("@example.com>")));
+    assert!(header("Date").is_some());
+    assert_eq!(header("Auto-Submitted").as_deref(), Some("auto-generated"));
+    assert_eq!(
+        header("List-Unsubscribe").as_deref(),
+        Some("<https://example.com/api/v1/email/unsubscribe?token=abc.def>")
+    );
+    assert_eq!(
+        header("List-Unsubscribe-Post").as_deref(),
+        Some("List-Unsubscribe=One-Click")
+    );
+    assert!(header("Content-Type").is_some_and(|value| value.starts_with("multipart/alternative")));
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use backend::models::{EmailCategory, User};
+use backend::utils::{EmailService, MaildirMailTransport, MemoryMailTransport, UnsubscribeTokens};
+use common::{mail_config, test_user};
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn user(username: &str, locale: &str) -> User {
+    User {
+        username: username.to_string(),
+        locale: locale.to_string(),
+        is_verified: false,
+        ..test_user()
+    }
+}
+
+#[tokio::test]
+async fn memory_transport_captures_rendered_email() {
+    let transport = MemoryMailTransport::default();
+    let service =
+        EmailService::with_transport(&mail_config(), Arc::new(transport.clone())).unwrap();
+
+    let email = service
+        .verification_email(
+            &user("<b>reader</b>", "tr"),
+            "token-123",
+            "https://app.test",
+        )
+        .unwrap();
+    service.send(&email).await.unwrap();
+
+    let messages = transport.messages();
+    assert_eq!(messages.len(), 1);
+
+    let message = &messages[0];
+    assert_eq!(message.to, vec!["reader@example.com".to_string()]);
+    assert_eq!(
+        message.subject.as_deref(),
+        Some("E-posta adresinizi doğrulayın")
+    );
+    assert!(message.raw.contains("multipart/alternative"));
+    assert!(!message.raw.contains("List-Unsubscribe"));
+    assert!(email.html_body.contains("&lt;b&gt;reader&lt;&#x2f;b&gt;"));
+    assert!(email
+        .text_body
+        .as_deref()
+        .unwrap()
+        .contains("https://app.test/verify-email?token=token-123"));
+}
+
+#[tokio::test]
+async fn maildir_transport_delivers_into_new() {
+    let dir = std::env::temp_dir().join(format!("maildir-{}", Uuid::new_v4()));
+    let service =
+        EmailService::with_transport(&mail_config(), Arc::new(MaildirMailTransport::new(&dir)))
+            .unwrap();
+
+    let email = service
+        .password_reset_email(&user("reader", "en"), "token-456", "https://app.test")
+        .unwrap();
+    service.send(&email).await.unwrap();
+
+    let delivered: Vec<_> = std::fs::read_dir(dir.join("new")).unwrap().collect();
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(std::fs::read_dir(dir.join("tmp")).unwrap().count(), 0);
+
+    let raw = std::fs::read_to_string(delivered[0].as_ref().unwrap().path()).unwrap();
+    assert!(raw.contains("Subject: Reset your password"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn unsubscribe_tokens_round_trip() {
+    let tokens = UnsubscribeTokens::new("test-unsubscribe-secret");
+    let user_id = Uuid::new_v4();
+
+    let token = tokens.sign(user_id, EmailCategory::Digests);
+    assert_eq!(
+        tokens.verify(&token),
+        Some((user_id, EmailCategory::Digests))
+    );
+
+    let (payload, _) = token.split_once('.').unwrap();
+    let forged = format!("{payload}.{}", "A".repeat(43));
+    assert_eq!(tokens.verify(&forged), None);
+    assert_eq!(UnsubscribeTokens::new("other-secret").verify(&token), None);
+}
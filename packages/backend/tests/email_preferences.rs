@@ -0,0 +1,250 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use backend::create_app_with_state;
+use backend::error::AppError;
+use backend::models::EmailCategory;
+use backend::utils::{SuppressionList, UnsubscribeTokens};
+use backend::AppState;
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state_with, TestDatabase};
+use serde_json::Value;
+use std::collections::HashMap;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+const EMAIL: &str = "reader@example.com";
+
+struct Fixture {
+    _idp: MockIdp,
+    db: TestDatabase,
+    state: AppState,
+    user_id: Uuid,
+}
+
+async fn fixture() -> Option<Fixture> {
+    let db = TestDatabase::create().await?;
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let state = test_state_with(test_config(&idp), &db);
+    let user_id = sqlx::query_scalar(
+        "INSERT INTO users (email, username, is_verified) VALUES ($1, 'reader', TRUE) RETURNING id",
+    )
+    .bind(EMAIL)
+    .fetch_one(db.pool())
+    .await
+    .unwrap();
+
+    Some(Fixture {
+        _idp: idp,
+        db,
+        state,
+        user_id,
+    })
+}
+
+impl Fixture {
+    fn suppressions(&self) -> SuppressionList {
+        SuppressionList::new((*self.db).clone())
+    }
+
+    async fn enabled(&self, category: EmailCategory) -> bool {
+        self.state
+            .email_preferences
+            .get(self.user_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|preference| preference.category == category)
+            .unwrap()
+            .enabled
+    }
+
+    fn token(&self, category: EmailCategory) -> String {
+        UnsubscribeTokens::new(&self.state.config.mail.unsubscribe_secret)
+            .sign(self.user_id, category)
+    }
+
+    async fn one_click(&self, token: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/v1/email/unsubscribe?token={token}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = create_app_with_state(self.state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 64 * 1024)
+            .await
+            .unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+}
+
+#[tokio::test]
+async fn opted_out_categories_are_not_sent() {
+    let Some(fixture) = fixture().await else {
+        return;
+    };
+    let list = fixture.suppressions();
+
+    assert!(list
+        .allows(EMAIL, EmailCategory::NewChapters)
+        .await
+        .unwrap());
+    // Product news is opt-in.
+    assert!(!list
+        .allows(EMAIL, EmailCategory::ProductNews)
+        .await
+        .unwrap());
+
+    let preferences = HashMap::from([
+        (EmailCategory::NewChapters, false),
+        (EmailCategory::ProductNews, true),
+    ]);
+    fixture
+        .state
+        .email_preferences
+        .update(fixture.user_id, &preferences)
+        .await
+        .unwrap();
+
+    assert!(!list
+        .allows("Reader@Example.com", EmailCategory::NewChapters)
+        .await
+        .unwrap());
+    assert!(list
+        .allows(EMAIL, EmailCategory::ProductNews)
+        .await
+        .unwrap());
+    assert!(list.allows(EMAIL, EmailCategory::Security).await.unwrap());
+
+    // Addresses without an account, e.g. a changed email, only consult the
+    // suppression list.
+    assert!(list
+        .allows("stranger@example.com", EmailCategory::NewChapters)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn suppressed_addresses_get_no_mail_at_all() {
+    let Some(fixture) = fixture().await else {
+        return;
+    };
+    let list = fixture.suppressions();
+    fixture
+        .state
+        .email_preferences
+        .suppress("READER@example.com", "hard bounce")
+        .await
+        .unwrap();
+
+    for category in EmailCategory::ALL {
+        assert!(
+            !list.allows(EMAIL, category).await.unwrap(),
+            "{category:?} was allowed"
+        );
+    }
+
+    fixture
+        .state
+        .email_preferences
+        .unsuppress(EMAIL)
+        .await
+        .unwrap();
+    assert!(list.allows(EMAIL, EmailCategory::Security).await.unwrap());
+}
+
+#[tokio::test]
+async fn security_mail_cannot_be_turned_off() {
+    let Some(fixture) = fixture().await else {
+        return;
+    };
+    let preferences = fixture
+        .state
+        .email_preferences
+        .get(fixture.user_id)
+        .await
+        .unwrap();
+    let security = preferences
+        .iter()
+        .find(|preference| preference.category == EmailCategory::Security)
+        .unwrap();
+    assert!(security.locked && security.enabled);
+
+    let err = fixture
+        .state
+        .email_preferences
+        .update(
+            fixture.user_id,
+            &HashMap::from([
+                (EmailCategory::Replies, false),
+                (EmailCategory::Security, false),
+            ]),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Validation(message) if message.id == "email-category-locked"));
+    // Nothing from the rejected update was applied.
+    assert!(fixture.enabled(EmailCategory::Replies).await);
+
+    let (status, body) = fixture
+        .one_click(&fixture.token(EmailCategory::Security))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "email.invalid_unsubscribe_link");
+    assert!(fixture.enabled(EmailCategory::Security).await);
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_turns_the_category_off() {
+    let Some(fixture) = fixture().await else {
+        return;
+    };
+
+    let (status, body) = fixture
+        .one_click(&fixture.token(EmailCategory::Digests))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["category"], "digests");
+    assert!(!fixture.enabled(EmailCategory::Digests).await);
+    assert!(!fixture
+        .suppressions()
+        .allows(EMAIL, EmailCategory::Digests)
+        .await
+        .unwrap());
+
+    // Repeating the request is harmless.
+    let (status, _) = fixture
+        .one_click(&fixture.token(EmailCategory::Digests))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unsubscribe_links_must_be_signed_with_the_unsubscribe_key() {
+    let Some(fixture) = fixture().await else {
+        return;
+    };
+    let forged = UnsubscribeTokens::new(&fixture.state.config.jwt_secret)
+        .sign(fixture.user_id, EmailCategory::Digests);
+
+    let (status, body) = fixture.one_click(&forged).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "email.invalid_unsubscribe_link");
+
+    // A valid link for an account deleted since the mail was sent.
+    sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(fixture.user_id)
+        .execute(fixture.db.pool())
+        .await
+        .unwrap();
+    let (status, _) = fixture
+        .one_click(&fixture.token(EmailCategory::Digests))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
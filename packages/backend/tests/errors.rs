@@ -0,0 +1,186 @@
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::response::IntoResponse;
+use axum::{middleware, routing::post, Router};
+use backend::error::{AppError, Message, Result, PROBLEM_JSON};
+use backend::i18n::I18n;
+use backend::middleware::{locale_middleware, request_id_middleware};
+use backend::models::RegisterRequest;
+use backend::utils::{validate_request, Json};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn register(Json(request): Json<RegisterRequest>) -> Result<StatusCode> {
+    validate_request(&request)?;
+    Err(AppError::Conflict(Message::new("email-already-exists")))
+}
+
+async fn call(lang: &str, body: Value) -> (StatusCode, Value) {
+    send(lang, "application/json", body.to_string()).await
+}
+
+async fn send(lang: &str, content_type: &str, body: String) -> (StatusCode, Value) {
+    let app = Router::new()
+        .route("/register", post(register))
+        .layer(middleware::from_fn_with_state(
+            I18n::new(),
+            locale_middleware,
+        ))
+        .layer(middleware::from_fn(request_id_middleware));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/register")
+                .header(header::ACCEPT_LANGUAGE, lang)
+                .header(header::CONTENT_TYPE, content_type)
+                .header("x-request-id", "req-42")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn errors_are_problem_documents_in_the_negotiated_locale() {
+    let valid = json!({
+        "email": "reader@example.com",
+        "username": "reader",
+        "password": "correct horse",
+    });
+
+    let (status, body) = call("en", valid.clone()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        body,
+        json!({
+            "type": "about:blank",
+            "title": "Conflict",
+            "status": 409,
+            "code": "user.email_taken",
+            "detail": "Email already exists",
+            "request_id": "req-42",
+        })
+    );
+
+    let (_, body) = call("tr-TR", valid).await;
+    assert_eq!(body["code"], "user.email_taken");
+    assert_eq!(body["detail"], "E-posta zaten mevcut");
+}
+
+#[tokio::test]
+async fn validation_errors_are_reported_per_field() {
+    let invalid = json!({
+        "email": "not-an-email",
+        "username": "ab",
+        "password": "short",
+    });
+
+    let (status, body) = call("tr", invalid.clone()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "request.invalid_fields");
+    assert_eq!(body["detail"], "Bazı alanlar geçersiz");
+    assert_eq!(
+        body["errors"],
+        json!([
+            {
+                "field": "email",
+                "code": "validation.invalid_email",
+                "detail": "Geçersiz e-posta formatı",
+            },
+            {
+                "field": "password",
+                "code": "validation.password_too_short",
+                "detail": "Şifre en az 8 karakter olmalı",
+            },
+            {
+                "field": "username",
+                "code": "validation.username_length",
+                "detail": "Kullanıcı adı 3 ile 50 karakter arasında olmalı",
+            },
+        ])
+    );
+
+    let (_, body) = call("en", invalid).await;
+    assert_eq!(
+        body["errors"][2]["detail"],
+        "Username must be between 3 and 50 characters"
+    );
+}
+
+#[tokio::test]
+async fn bodies_that_do_not_match_the_request_are_problem_documents() {
+    let (status, body) = call(
+        "en",
+        json!({ "email": "reader@example.com", "username": "reader" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "request.invalid_fields");
+    assert_eq!(body["request_id"], "req-42");
+    assert_eq!(
+        body["errors"],
+        json!([{
+            "field": "password",
+            "code": "validation.required",
+            "detail": "This field is required",
+        }])
+    );
+
+    let wrong_type = json!({ "email": "reader@example.com", "username": 7, "password": "x" });
+    let (status, body) = call("tr", wrong_type).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["field"], "username");
+    assert_eq!(body["errors"][0]["code"], "validation.invalid_type");
+    assert_eq!(body["errors"][0]["detail"], "Değerin türü yanlış");
+
+    for malformed in [
+        "{\"email\":",
+        "not json",
+        r#"{"email": "reader@example.com", "username": "reader", "password": "x"} {}"#,
+    ] {
+        let (status, body) = send("en", "application/json", malformed.to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{malformed}");
+        assert_eq!(body["code"], "request.malformed_json", "{malformed}");
+    }
+
+    let (status, body) = send("en", "text/plain", "{}".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "request.json_required");
+}
+
+#[tokio::test]
+async fn errors_outside_a_request_use_the_default_locale() {
+    let response = AppError::NotFound(Message::new("user-not-found")).into_response();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["code"], "user.not_found");
+    assert_eq!(body["detail"], "User not found");
+    assert!(body.get("request_id").is_none());
+}
+
+#[test]
+fn server_errors_have_generic_codes() {
+    let error = AppError::Internal(anyhow::anyhow!("disk on fire"));
+    assert_eq!(error.code(), "server.internal");
+    assert_eq!(
+        error.into_response().status(),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+
+    let error = AppError::Validation(Message::new("something-new"));
+    assert_eq!(error.code(), "request.invalid");
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use backend::routes::monitoring::create_monitoring_routes;
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state};
+use serde_json::Value;
+use tower::ServiceExt;
+
+async fn get(app: Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn monitoring_app(check_mail: bool) -> Router {
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let mut config = test_config(&idp);
+    // Nothing listens here, so every database check fails.
+    config.database_url = "postgres://postgres@127.0.0.1:1/backend_test".to_string();
+    config.health.check_mail = check_mail;
+
+    create_monitoring_routes(&test_state(config))
+}
+
+#[tokio::test]
+async fn liveness_does_not_depend_on_the_database() {
+    let (status, body) = get(monitoring_app(false).await, "/healthz").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn readiness_reports_each_check() {
+    let (status, body) = get(monitoring_app(true).await, "/readyz").await;
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "failed");
+    assert_eq!(body["checks"]["database"]["status"], "failed");
+    // Connection details stay in the logs.
+    assert!(matches!(
+        body["checks"]["database"]["reason"].as_str(),
+        Some("error" | "timeout")
+    ));
+    assert_eq!(
+        body["checks"]["database"].as_object().unwrap().len(),
+        3,
+        "{body}"
+    );
+    assert_eq!(body["checks"]["migrations"]["status"], "failed");
+    assert_eq!(body["checks"]["mail"]["status"], "ok");
+    assert!(body["checks"]["mail"].get("reason").is_none());
+}
+
+#[tokio::test]
+async fn mail_is_only_probed_when_configured() {
+    let (_, body) = get(monitoring_app(false).await, "/readyz").await;
+
+    assert!(body["checks"].get("mail").is_none());
+}
@@ -0,0 +1,191 @@
+use backend::config::I18nConfig;
+use backend::i18n::{check_catalogs, CheckPaths, I18n, Issue};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+fn catalog_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("i18n-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("pt-BR")).unwrap();
+    std::fs::write(
+        dir.join("pt.ftl"),
+        "invalid-credentials = E-mail ou senha inválidos\nuser-not-found = Usuário não encontrado",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("pt-BR").join("errors.ftl"),
+        "user-not-found = Usuário não foi encontrado",
+    )
+    .unwrap();
+    std::fs::write(dir.join("tr.ftl"), "email-signature = Sevgiler").unwrap();
+    dir
+}
+
+fn load(dir: &Path, hot_reload: bool) -> I18n {
+    I18n::load(&I18nConfig {
+        dir: Some(dir.display().to_string()),
+        hot_reload,
+    })
+    .unwrap()
+}
+
+#[test]
+fn embedded_catalogs_load_without_a_directory() {
+    let i18n = I18n::new();
+
+    assert_eq!(i18n.available_locales(), vec!["en", "tr"]);
+    assert!(i18n.supports("tr-TR"));
+    assert!(!i18n.supports("pt"));
+    assert_eq!(
+        i18n.get_message("fr", "user-not-found", None),
+        "User not found"
+    );
+}
+
+#[test]
+fn lookups_fall_back_from_region_to_language_to_english() {
+    let dir = catalog_dir();
+    let i18n = load(&dir, false);
+
+    assert_eq!(i18n.available_locales(), vec!["en", "pt", "pt-BR", "tr"]);
+    assert!(i18n.supports("pt-br"));
+    assert!(i18n.supports("pt-PT"));
+
+    assert_eq!(
+        i18n.get_message("pt-BR", "user-not-found", None),
+        "Usuário não foi encontrado"
+    );
+    assert_eq!(
+        i18n.get_message("pt-BR", "invalid-credentials", None),
+        "E-mail ou senha inválidos"
+    );
+    assert_eq!(
+        i18n.get_message("pt-BR", "invalid-token", None),
+        I18n::new().get_message("en", "invalid-token", None)
+    );
+
+    // Files on disk override single messages and keep the rest embedded.
+    assert_eq!(i18n.get_message("tr", "email-signature", None), "Sevgiler");
+    assert_eq!(
+        i18n.get_message("tr", "user-not-found", None),
+        I18n::new().get_message("tr", "user-not-found", None)
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn hot_reload_picks_up_edits() {
+    let dir = catalog_dir();
+    let i18n = load(&dir, true);
+
+    // Make sure the new modification time differs from the original one.
+    std::thread::sleep(Duration::from_millis(1100));
+    std::fs::write(dir.join("pt.ftl"), "user-not-found = Ninguém aqui").unwrap();
+
+    let reloaded = (0..50).any(|_| {
+        std::thread::sleep(Duration::from_millis(100));
+        i18n.get_message("pt", "user-not-found", None) == "Ninguém aqui"
+    });
+    assert!(reloaded);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn shipped_catalogs_are_complete() {
+    let report =
+        check_catalogs(&CheckPaths::for_crate(env!("CARGO_MANIFEST_DIR").as_ref())).unwrap();
+
+    let errors: Vec<String> = report.errors().map(ToString::to_string).collect();
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn checker_reports_drift_against_english() {
+    let dir = std::env::temp_dir().join(format!("i18n-check-{}", Uuid::new_v4()));
+    let (ftl_dir, src_dir, json_dir) = (dir.join("ftl"), dir.join("src"), dir.join("json"));
+    for dir in [&ftl_dir, &src_dir, &json_dir] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+
+    std::fs::write(
+        ftl_dir.join("en.ftl"),
+        "greeting = Hello { $name }\nfarewell = Bye\nstale = Old",
+    )
+    .unwrap();
+    std::fs::write(
+        ftl_dir.join("tr.ftl"),
+        "greeting = Merhaba { $user }\nstale = Eski\nbonus = Ekstra",
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        "fn f() { Message::new(\"greeting\"); Message::new(\"not-translated\"); }\n\
+         const F: &str = \"farewell\";",
+    )
+    .unwrap();
+    std::fs::write(json_dir.join("en.json"), r#"{"a": {"b": "{count} items"}}"#).unwrap();
+    std::fs::write(json_dir.join("tr.json"), r#"{"a": {}}"#).unwrap();
+
+    let report = check_catalogs(&CheckPaths {
+        ftl_dir: Some(ftl_dir),
+        bundled: false,
+        source_dirs: vec![src_dir],
+        frontend_dir: Some(json_dir),
+    })
+    .unwrap();
+
+    let issue = |issue: Issue| assert!(report.issues.contains(&issue), "{:#?}", report.issues);
+    issue(Issue::Missing {
+        file: "tr.ftl".to_string(),
+        key: "farewell".to_string(),
+    });
+    issue(Issue::Extra {
+        file: "tr.ftl".to_string(),
+        key: "bonus".to_string(),
+    });
+    issue(Issue::Variables {
+        file: "tr.ftl".to_string(),
+        key: "greeting".to_string(),
+        expected: vec!["name".to_string()],
+        found: vec!["user".to_string()],
+    });
+    issue(Issue::Untranslated {
+        key: "not-translated".to_string(),
+        location: "lib.rs:1".to_string(),
+    });
+    issue(Issue::Unused {
+        key: "stale".to_string(),
+    });
+    issue(Issue::Missing {
+        file: "tr.json".to_string(),
+        key: "a.b".to_string(),
+    });
+    assert!(report.has_errors());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn deployment_check_layers_overrides_on_the_bundled_catalogs() {
+    let bundled = check_catalogs(&CheckPaths::for_deployment(None)).unwrap();
+    assert!(!bundled.has_errors(), "{:#?}", bundled.issues);
+
+    let dir = std::env::temp_dir().join(format!("i18n-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("tr.ftl"), "welcome = Hoş geldin { $name }").unwrap();
+
+    let report = check_catalogs(&CheckPaths::for_deployment(Some(&dir))).unwrap();
+    assert!(report.issues.iter().any(|issue| matches!(
+        issue,
+        Issue::Variables { key, found, .. } if key == "welcome" && found == &["name"]
+    )));
+    // Without the sources there is nothing to judge usage by.
+    assert!(!report
+        .issues
+        .iter()
+        .any(|issue| matches!(issue, Issue::Unused { .. } | Issue::Untranslated { .. })));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,90 @@
+use axum::body::Body;
+use axum::http::{header, Request};
+use axum::{middleware, routing::get, Router};
+use backend::i18n::{parse_accept_language, I18n, Locale, LocaleSource};
+use backend::middleware::locale_middleware;
+use tower::ServiceExt;
+
+fn negotiate(uri: &str, headers: &[(header::HeaderName, &str)]) -> Locale {
+    let mut request = Request::builder().uri(uri);
+    for (name, value) in headers {
+        request = request.header(name, *value);
+    }
+    let (parts, _) = request.body(()).unwrap().into_parts();
+
+    Locale::negotiate(&I18n::new(), &parts)
+}
+
+#[test]
+fn accept_language_is_ordered_by_quality() {
+    assert_eq!(
+        parse_accept_language("de;q=0.5, tr-TR, en;q=0.8, *;q=0.1, fr;q=0"),
+        vec!["tr-TR", "en", "de"]
+    );
+    assert_eq!(parse_accept_language("es;q=abc, ja"), vec!["ja"]);
+}
+
+#[test]
+fn negotiation_prefers_query_then_cookie_then_header() {
+    let accept = (header::ACCEPT_LANGUAGE, "de, tr;q=0.9, en;q=0.8");
+    let cookie = (header::COOKIE, "theme=dark; locale=en");
+
+    let locale = negotiate("/x", std::slice::from_ref(&accept));
+    assert_eq!(locale.as_str(), "tr");
+    assert_eq!(locale.source, LocaleSource::AcceptLanguage);
+
+    let locale = negotiate("/x", &[accept.clone(), cookie.clone()]);
+    assert_eq!(locale.as_str(), "en");
+    assert_eq!(locale.source, LocaleSource::Cookie);
+
+    let locale = negotiate("/x?token=1&lang=tr-TR", &[accept, cookie]);
+    assert_eq!(locale.as_str(), "tr");
+    assert_eq!(locale.source, LocaleSource::Query);
+
+    // Unsupported choices are skipped.
+    let locale = negotiate("/x?lang=ja", &[(header::ACCEPT_LANGUAGE, "ko, fr")]);
+    assert_eq!(locale, Locale::default());
+}
+
+#[test]
+fn stored_user_locale_beats_the_browser_only() {
+    let i18n = I18n::new();
+
+    let from_header = negotiate("/x", &[(header::ACCEPT_LANGUAGE, "en")]);
+    let locale = from_header.with_user_locale(&i18n, "tr");
+    assert_eq!(locale.as_str(), "tr");
+    assert_eq!(locale.source, LocaleSource::User);
+
+    let from_query = negotiate("/x?lang=en", &[]);
+    assert_eq!(from_query.with_user_locale(&i18n, "tr").as_str(), "en");
+}
+
+#[tokio::test]
+async fn middleware_sets_content_language() {
+    let app = Router::new()
+        .route(
+            "/echo",
+            get(|locale: Locale| async move { locale.to_string() }),
+        )
+        .layer(middleware::from_fn_with_state(
+            I18n::new(),
+            locale_middleware,
+        ));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/echo")
+                .header(header::ACCEPT_LANGUAGE, "tr-TR,tr;q=0.9,en;q=0.8")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.headers()[header::CONTENT_LANGUAGE], "tr");
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"tr");
+}
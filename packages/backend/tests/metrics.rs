@@ -0,0 +1,88 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::{middleware, routing::get, Router};
+use backend::config::MetricsConfig;
+use backend::middleware::http_metrics_middleware;
+use backend::routes::monitoring::create_monitoring_routes;
+use backend::telemetry::{prometheus, record_login};
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state};
+use tower::ServiceExt;
+
+async fn send(app: Router, uri: &str, token: Option<&str>) -> (StatusCode, String) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), 1 << 20)
+        .await
+        .unwrap();
+
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn monitoring_app(token: Option<&str>) -> Router {
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let mut config = test_config(&idp);
+    config.metrics = MetricsConfig {
+        token: token.map(str::to_string),
+    };
+
+    let api = Router::new()
+        .nest(
+            "/api/v1",
+            Router::new().route("/series/:id", get(|| async { "ok" })),
+        )
+        .route_layer(middleware::from_fn(http_metrics_middleware));
+
+    api.merge(create_monitoring_routes(&test_state(config)))
+}
+
+#[tokio::test]
+async fn metrics_require_the_configured_token() {
+    let app = monitoring_app(Some("scrape-secret")).await;
+
+    let (status, _) = send(app.clone(), "/metrics", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(app.clone(), "/metrics", Some("scrape-secreT")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = send(app, "/metrics", Some("scrape-secret")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("db_pool_max_connections"));
+}
+
+#[tokio::test]
+async fn metrics_are_not_served_without_a_token() {
+    let app = monitoring_app(None).await;
+
+    let (status, _) = send(app, "/metrics", Some("anything")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_template() {
+    let app = monitoring_app(Some("scrape-secret")).await;
+    prometheus();
+
+    send(app.clone(), "/api/v1/series/1", None).await;
+    send(app.clone(), "/api/v1/series/2", None).await;
+    record_login("password", "failure");
+
+    let (_, body) = send(app, "/metrics", Some("scrape-secret")).await;
+    assert!(body.contains(
+        r#"http_requests_total{method="GET",route="/api/v1/series/:id",status="200"} 2"#
+    ));
+    assert!(body.contains(r#"http_request_duration_seconds_bucket{method="GET",route="/api/v1/series/:id",status="200",le="0.005"}"#));
+    assert!(body.contains(r#"auth_logins_total{provider="password",outcome="failure"}"#));
+    assert!(!body.contains("/api/v1/series/1"));
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use backend::error::AppError;
+use backend::services::{JwksSource, OAuthService, OidcVerifier};
+use common::mock_idp::{MockIdp, MockScenario, MOCK_NONCE};
+use common::{test_config, test_state, TEST_CLIENT_ID};
+
+async fn oauth_service(scenario: MockScenario) -> (MockIdp, OAuthService) {
+    let idp = MockIdp::start(scenario).await;
+    let config = test_config(&idp);
+    let service = test_state(config).oauth_service;
+
+    (idp, service)
+}
+
+#[tokio::test]
+async fn google_success_yields_verified_profile() {
+    let (_idp, service) = oauth_service(MockScenario::Success).await;
+
+    let profile = service
+        .fetch_google_profile("auth-code", MOCK_NONCE, "en")
+        .await
+        .unwrap();
+
+    assert_eq!(profile.provider, "google");
+    assert_eq!(profile.provider_id, "1234567890");
+    assert_eq!(profile.email, "reader@example.com");
+    assert!(profile.email_verified);
+    assert_eq!(profile.username, "Test");
+}
+
+#[tokio::test]
+async fn google_rejects_token_for_another_nonce() {
+    let (_idp, service) = oauth_service(MockScenario::Success).await;
+
+    let result = service
+        .fetch_google_profile("auth-code", "other-nonce", "en")
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn google_missing_email_fails() {
+    let (_idp, service) = oauth_service(MockScenario::MissingEmail).await;
+
+    let result = service
+        .fetch_google_profile("auth-code", MOCK_NONCE, "en")
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn google_token_exchange_failure_fails() {
+    let (_idp, service) = oauth_service(MockScenario::TokenExchangeFailure).await;
+
+    let result = service
+        .fetch_google_profile("auth-code", MOCK_NONCE, "en")
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn discord_success_yields_profile() {
+    let (idp, service) = oauth_service(MockScenario::Success).await;
+
+    let profile = service
+        .fetch_discord_profile("auth-code", "tr")
+        .await
+        .unwrap();
+
+    assert_eq!(profile.provider, "discord");
+    assert_eq!(profile.provider_id, "80351110224678912");
+    assert_eq!(profile.email, "reader@example.com");
+    assert!(profile.email_verified);
+    assert_eq!(profile.locale.as_deref(), Some("tr"));
+    assert_eq!(
+        profile.avatar_url,
+        Some(idp.url("/cdn/avatars/80351110224678912/8342729096ea3675442027381ff50dfe.png"))
+    );
+}
+
+#[tokio::test]
+async fn discord_missing_email_fails() {
+    let (_idp, service) = oauth_service(MockScenario::MissingEmail).await;
+
+    assert!(service
+        .fetch_discord_profile("auth-code", "en")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn discord_token_exchange_failure_fails() {
+    let (_idp, service) = oauth_service(MockScenario::TokenExchangeFailure).await;
+
+    assert!(service
+        .fetch_discord_profile("auth-code", "en")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn discord_userinfo_error_status_fails() {
+    let (_idp, service) = oauth_service(MockScenario::UserinfoFailure).await;
+
+    assert!(service
+        .fetch_discord_profile("auth-code", "en")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn unconfigured_providers_are_disabled() {
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let mut config = test_config(&idp);
+    config.discord = None;
+    config.storage = None;
+    let service = test_state(config).oauth_service;
+
+    assert!(service.google_enabled());
+    assert!(!service.discord_enabled());
+
+    let err = service
+        .fetch_discord_profile("auth-code", "en")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+}
+
+#[tokio::test]
+async fn remote_jwks_is_cached_between_verifications() {
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let verifier = OidcVerifier::new(
+        vec![idp.issuer().to_string()],
+        TEST_CLIENT_ID.to_string(),
+        JwksSource::Remote {
+            url: idp.url("/jwks"),
+            http_client: reqwest::Client::new(),
+        },
+    );
+
+    for _ in 0..3 {
+        let token = idp.id_token("nonce-1");
+        verifier.verify(&token, "nonce-1").await.unwrap();
+    }
+
+    assert_eq!(idp.jwks_requests(), 1);
+}
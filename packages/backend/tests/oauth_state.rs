@@ -0,0 +1,67 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Request, Response, StatusCode};
+use axum::Router;
+use backend::create_app_with_state;
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state_with, TestDatabase};
+use tower::ServiceExt;
+
+async fn get(app: &Router, uri: &str, cookie: Option<&str>) -> Response<Body> {
+    let mut request = Request::builder().uri(uri);
+    if let Some(cookie) = cookie {
+        request = request.header(header::COOKIE, cookie);
+    }
+
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn header(response: &Response<Body>, name: header::HeaderName) -> &str {
+    response.headers().get(name).unwrap().to_str().unwrap()
+}
+
+fn query(url: &str, name: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .unwrap()
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+#[tokio::test]
+async fn callbacks_are_only_accepted_from_the_browser_that_started_the_flow() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let mut config = test_config(&idp);
+    config.storage = None;
+    let app = create_app_with_state(test_state_with(config, &db));
+
+    let authorize = get(&app, "/api/v1/auth/discord", None).await;
+    assert_eq!(authorize.status(), StatusCode::TEMPORARY_REDIRECT);
+    let set_cookie = header(&authorize, header::SET_COOKIE);
+    assert!(set_cookie.contains("HttpOnly"));
+    assert!(set_cookie.contains("SameSite=Lax"));
+    let cookie = set_cookie.split(';').next().unwrap().to_string();
+    let state = query(header(&authorize, header::LOCATION), "state").unwrap();
+    assert!(!cookie.contains(&state));
+
+    let callback = format!("/api/v1/auth/discord/callback?code=auth-code&state={state}");
+
+    // A callback link opened in another browser carries no binding.
+    for other in [None, Some("oauth_binding=forged")] {
+        let response = get(&app, &callback, other).await;
+        let location = header(&response, header::LOCATION);
+        assert_eq!(query(location, "error").as_deref(), Some("oauth-failed"));
+    }
+
+    let response = get(&app, &callback, Some(&format!("theme=dark; {cookie}"))).await;
+    let location = header(&response, header::LOCATION);
+    assert!(query(location, "code").is_some());
+    assert!(header(&response, header::SET_COOKIE).contains("Max-Age=0"));
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use backend::services::{JwksSource, OidcVerifier};
+use common::{id_token_claims, sign_id_token, test_jwks, TEST_CLIENT_ID, TEST_ISSUER, TEST_KEY_ID};
+
+fn verifier() -> OidcVerifier {
+    OidcVerifier::new(
+        vec![TEST_ISSUER.to_string()],
+        TEST_CLIENT_ID.to_string(),
+        JwksSource::Static(test_jwks()),
+    )
+}
+
+#[tokio::test]
+async fn accepts_valid_id_token() {
+    let token = sign_id_token(&id_token_claims("nonce-1"), TEST_KEY_ID);
+
+    let claims = verifier().verify(&token, "nonce-1").await.unwrap();
+
+    assert_eq!(claims.sub, "1234567890");
+    assert_eq!(claims.email.as_deref(), Some("reader@example.com"));
+    assert!(claims.email_verified);
+}
+
+#[tokio::test]
+async fn accepts_string_email_verified_claim() {
+    let mut claims = id_token_claims("nonce-1");
+    claims["email_verified"] = "true".into();
+    let token = sign_id_token(&claims, TEST_KEY_ID);
+
+    assert!(verifier().verify(&token, "nonce-1").await.is_ok());
+}
+
+#[tokio::test]
+async fn rejects_nonce_mismatch() {
+    let token = sign_id_token(&id_token_claims("nonce-1"), TEST_KEY_ID);
+
+    assert!(verifier().verify(&token, "nonce-2").await.is_err());
+}
+
+#[tokio::test]
+async fn rejects_wrong_audience() {
+    let mut claims = id_token_claims("nonce-1");
+    claims["aud"] = "another-client".into();
+    let token = sign_id_token(&claims, TEST_KEY_ID);
+
+    assert!(verifier().verify(&token, "nonce-1").await.is_err());
+}
+
+#[tokio::test]
+async fn rejects_wrong_issuer() {
+    let mut claims = id_token_claims("nonce-1");
+    claims["iss"] = "https://evil.test".into();
+    let token = sign_id_token(&claims, TEST_KEY_ID);
+
+    assert!(verifier().verify(&token, "nonce-1").await.is_err());
+}
+
+#[tokio::test]
+async fn rejects_expired_token() {
+    let mut claims = id_token_claims("nonce-1");
+    claims["exp"] = (chrono::Utc::now().timestamp() - 3600).into();
+    let token = sign_id_token(&claims, TEST_KEY_ID);
+
+    assert!(verifier().verify(&token, "nonce-1").await.is_err());
+}
+
+#[tokio::test]
+async fn rejects_unverified_email() {
+    let mut claims = id_token_claims("nonce-1");
+    claims["email_verified"] = false.into();
+    let token = sign_id_token(&claims, TEST_KEY_ID);
+
+    assert!(verifier().verify(&token, "nonce-1").await.is_err());
+}
+
+#[tokio::test]
+async fn rejects_unknown_key_id() {
+    let token = sign_id_token(&id_token_claims("nonce-1"), "rotated-key");
+
+    assert!(verifier().verify(&token, "nonce-1").await.is_err());
+}
+
+#[tokio::test]
+async fn rejects_tampered_signature() {
+    let token = sign_id_token(&id_token_claims("nonce-1"), TEST_KEY_ID);
+    let (unsigned, signature) = token.rsplit_once('.').unwrap();
+    let tampered = format!("{unsigned}.{}", signature.chars().rev().collect::<String>());
+
+    assert!(verifier().verify(&tampered, "nonce-1").await.is_err());
+}
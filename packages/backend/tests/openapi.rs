@@ -0,0 +1,115 @@
+use backend::openapi::ApiDoc;
+use std::collections::BTreeSet;
+use std::path::Path;
+use utoipa::OpenApi;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+/// Every `(method, path)` routed by the modules nested in `routes/mod.rs`,
+/// read from the router source so new routes can't skip the spec.
+fn routed_operations() -> BTreeSet<(String, String)> {
+    let routes_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/routes");
+    let mod_rs = std::fs::read_to_string(routes_dir.join("mod.rs")).unwrap();
+
+    let mut operations = BTreeSet::new();
+    for (start, _) in mod_rs.match_indices(".nest(") {
+        let args = balanced_args(&mod_rs[start + ".nest".len()..]);
+        let prefix = args.split('"').nth(1).expect("nest prefix literal");
+        let module = args.split("::").next().unwrap().rsplit(' ').next().unwrap();
+        let source = std::fs::read_to_string(routes_dir.join(format!("{module}.rs"))).unwrap();
+
+        for (start, _) in source.match_indices(".route(") {
+            let args = balanced_args(&source[start + ".route".len()..]);
+            let path = args.split('"').nth(1).expect("route path literal");
+            let path = format!("/api/v1{prefix}{}", openapi_path(path));
+
+            for method in METHODS {
+                let called = args
+                    .match_indices(&format!("{method}("))
+                    .any(|(i, _)| !args[..i].ends_with(|c: char| c.is_alphanumeric() || c == '_'));
+                if called {
+                    operations.insert((method.to_string(), path.clone()));
+                }
+            }
+        }
+    }
+
+    operations
+}
+
+/// Rewrites axum's `:param` segments as OpenAPI `{param}`.
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{name}}}"),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The text between the parenthesis `source` starts with and its match.
+fn balanced_args(source: &str) -> &str {
+    let mut depth = 0;
+    for (i, c) in source.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &source[1..i];
+                }
+            }
+            _ => {}
+        }
+    }
+    panic!("unbalanced route call");
+}
+
+fn documented_operations() -> BTreeSet<(String, String)> {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+    spec["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| (method.clone(), path.clone()))
+        })
+        .collect()
+}
+
+#[test]
+fn every_route_is_documented() {
+    let routed = routed_operations();
+    assert!(routed.contains(&("get".to_string(), "/api/v1/admin/emails/{id}".to_string())));
+
+    let documented = documented_operations();
+    let missing: Vec<_> = routed.difference(&documented).collect();
+    let stale: Vec<_> = documented.difference(&routed).collect();
+
+    assert!(
+        missing.is_empty(),
+        "routes missing from the spec: {missing:?}"
+    );
+    assert!(stale.is_empty(), "spec paths with no route: {stale:?}");
+}
+
+#[test]
+fn spec_is_openapi_3_1_with_bearer_auth() {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+    assert_eq!(spec["openapi"], "3.1.0");
+    assert_eq!(
+        spec["components"]["securitySchemes"]["bearer_auth"]["scheme"],
+        "bearer"
+    );
+    assert_eq!(
+        spec["paths"]["/api/v1/auth/me"]["get"]["security"][0]["bearer_auth"],
+        serde_json::json!([])
+    );
+    assert!(spec["components"]["schemas"]["ProblemDetails"].is_object());
+}
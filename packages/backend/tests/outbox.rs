@@ -0,0 +1,259 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use backend::create_app_with_state;
+use backend::models::{EmailCategory, EmailStatus, OutboxEmail, OutgoingEmail};
+use backend::services::{retry_delay, EmailOutbox, MAX_ATTEMPTS};
+use chrono::{Duration, Utc};
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state_with, TestDatabase};
+use serde_json::Value;
+use std::collections::HashSet;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+async fn enqueue(db: &TestDatabase, count: usize) -> Vec<Uuid> {
+    let outbox = EmailOutbox::new((**db).clone());
+    let mut tx = db.pool().begin().await.unwrap();
+    let mut ids = Vec::new();
+    for n in 0..count {
+        let email = OutgoingEmail {
+            to: format!("reader{n}@example.com"),
+            subject: "Hello".to_string(),
+            html_body: "<p>Hello</p>".to_string(),
+            text_body: None,
+            category: EmailCategory::Security,
+            unsubscribe_url: None,
+        };
+        ids.push(outbox.enqueue(&mut tx, &email).await.unwrap());
+    }
+    tx.commit().await.unwrap();
+
+    ids
+}
+
+/// Makes every pending message due now, as if its backoff had elapsed.
+async fn make_due(db: &TestDatabase) {
+    sqlx::query("UPDATE email_outbox SET next_attempt_at = NOW() WHERE status = 'pending'")
+        .execute(db.pool())
+        .await
+        .unwrap();
+}
+
+#[test]
+fn retries_back_off_exponentially_up_to_six_hours() {
+    let expected = [
+        30, 60, 120, 240, 480, 960, 1920, 3840, 7680, 15360, 21600, 21600,
+    ];
+    for (attempt, seconds) in (1..).zip(expected) {
+        assert_eq!(
+            retry_delay(attempt),
+            Duration::seconds(seconds),
+            "attempt {attempt}"
+        );
+    }
+
+    assert_eq!(retry_delay(0), Duration::seconds(30));
+    assert_eq!(retry_delay(i32::MAX), Duration::hours(6));
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_rescheduled_then_dead_lettered() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let outbox = EmailOutbox::new((*db).clone());
+    let [id] = enqueue(&db, 1).await[..] else {
+        unreachable!()
+    };
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        make_due(&db).await;
+        let claimed = outbox.claim_due().await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].attempts, attempt);
+        assert_eq!(claimed[0].status, EmailStatus::Sending);
+
+        let before = Utc::now();
+        let status = outbox
+            .mark_failed(&claimed[0], "550 mailbox unavailable")
+            .await
+            .unwrap();
+        let email = outbox.find_by_id(id).await.unwrap().unwrap();
+        assert_eq!(email.last_error.as_deref(), Some("550 mailbox unavailable"));
+
+        if attempt < MAX_ATTEMPTS {
+            assert_eq!(status, EmailStatus::Pending);
+            let delay = email.next_attempt_at - before;
+            assert!(delay >= retry_delay(attempt) - Duration::seconds(1));
+            assert!(delay <= retry_delay(attempt) + Duration::seconds(1));
+
+            // Not due again until the backoff has passed.
+            assert!(outbox.claim_due().await.unwrap().is_empty());
+        } else {
+            assert_eq!(status, EmailStatus::Failed);
+            assert_eq!(email.status, EmailStatus::Failed);
+        }
+    }
+
+    make_due(&db).await;
+    assert!(outbox.claim_due().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn messages_that_never_finish_are_dead_lettered() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let outbox = EmailOutbox::new((*db).clone());
+    let [id] = enqueue(&db, 1).await[..] else {
+        unreachable!()
+    };
+
+    // Each claim ends with the worker dying before it records an outcome.
+    for attempt in 1..=MAX_ATTEMPTS {
+        let claimed = outbox.claim_due().await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].attempts, attempt);
+
+        assert!(outbox.claim_due().await.unwrap().is_empty());
+        sqlx::query("UPDATE email_outbox SET locked_until = NOW() - INTERVAL '1 second'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+    }
+
+    assert!(outbox.claim_due().await.unwrap().is_empty());
+    let email = outbox.find_by_id(id).await.unwrap().unwrap();
+    assert_eq!(email.status, EmailStatus::Failed);
+    assert_eq!(email.attempts, MAX_ATTEMPTS);
+    assert!(email.last_error.is_some());
+}
+
+#[tokio::test]
+async fn locked_messages_are_skipped_rather_than_waited_for() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let outbox = EmailOutbox::new((*db).clone());
+    let ids = enqueue(&db, 2).await;
+
+    // Another worker is in the middle of claiming the first message.
+    let mut other = db.pool().begin().await.unwrap();
+    sqlx::query("SELECT id FROM email_outbox WHERE id = $1 FOR UPDATE")
+        .bind(ids[0])
+        .execute(&mut *other)
+        .await
+        .unwrap();
+
+    let claimed = tokio::time::timeout(std::time::Duration::from_secs(5), outbox.claim_due())
+        .await
+        .expect("claiming does not block on locked rows")
+        .unwrap();
+    assert_eq!(
+        claimed.iter().map(|email| email.id).collect::<Vec<_>>(),
+        vec![ids[1]]
+    );
+
+    other.rollback().await.unwrap();
+    let claimed = outbox.claim_due().await.unwrap();
+    assert_eq!(
+        claimed.iter().map(|email| email.id).collect::<Vec<_>>(),
+        vec![ids[0]]
+    );
+}
+
+#[tokio::test]
+async fn concurrent_workers_never_claim_the_same_message() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let ids = enqueue(&db, 100).await;
+
+    let drain = |outbox: EmailOutbox| async move {
+        let mut claimed = Vec::new();
+        loop {
+            let batch = outbox.claim_due().await.unwrap();
+            if batch.is_empty() {
+                return claimed;
+            }
+            claimed.extend(batch.into_iter().map(|email: OutboxEmail| email.id));
+        }
+    };
+    let (first, second) = tokio::join!(
+        tokio::spawn(drain(EmailOutbox::new((*db).clone()))),
+        tokio::spawn(drain(EmailOutbox::new((*db).clone()))),
+    );
+    let (first, second) = (first.unwrap(), second.unwrap());
+
+    let unique: HashSet<Uuid> = first.iter().chain(&second).copied().collect();
+    assert_eq!(first.len() + second.len(), ids.len());
+    assert_eq!(unique, ids.into_iter().collect());
+}
+
+#[tokio::test]
+async fn admins_can_requeue_dead_letters() {
+    let Some(db) = TestDatabase::create().await else {
+        return;
+    };
+    let ids = enqueue(&db, 2).await;
+    let (dead, pending) = (ids[0], ids[1]);
+    sqlx::query(
+        "UPDATE email_outbox SET status = 'failed', attempts = $2, last_error = 'timeout' WHERE id = $1",
+    )
+    .bind(dead)
+    .bind(MAX_ATTEMPTS)
+    .execute(db.pool())
+    .await
+    .unwrap();
+
+    let admin_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO users (email, username, role, is_verified) VALUES ('admin@example.com', 'admin', 'admin', TRUE) RETURNING id",
+    )
+    .fetch_one(db.pool())
+    .await
+    .unwrap();
+
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let state = test_state_with(test_config(&idp), &db);
+    let token = state
+        .jwt_service
+        .generate_token(admin_id, "admin@example.com")
+        .unwrap();
+    let app = create_app_with_state(state);
+
+    let retry = |id: Uuid| {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/v1/admin/emails/{id}/retry"))
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap();
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 64 * 1024)
+                .await
+                .unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap())
+        }
+    };
+
+    let (status, body) = retry(dead).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "pending");
+    assert_eq!(body["attempts"], 0);
+
+    let claimed = EmailOutbox::new((*db).clone()).claim_due().await.unwrap();
+    assert!(claimed.iter().any(|email| email.id == dead));
+
+    let (status, body) = retry(pending).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "email.not_retryable");
+
+    let (status, body) = retry(Uuid::new_v4()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "email.not_found");
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Request, Response, StatusCode};
+use axum::Router;
+use backend::config::{RateLimitKey, RateLimitPolicy, RateLimitPolicyName};
+use backend::{create_app_with_state, AppState};
+use common::mock_idp::{MockIdp, MockScenario};
+use common::{test_config, test_state};
+use serde_json::Value;
+use std::net::SocketAddr;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// `/auth/providers` is limited by `read-default` and needs no database.
+const LIMITED: &str = "/api/v1/auth/providers";
+
+async fn state(requests: u32, key: RateLimitKey) -> AppState {
+    let idp = MockIdp::start(MockScenario::Success).await;
+    let mut config = test_config(&idp);
+    config.rate_limit.policies.insert(
+        RateLimitPolicyName::ReadDefault,
+        RateLimitPolicy {
+            requests,
+            per_secs: 60,
+            key,
+        },
+    );
+
+    test_state(config)
+}
+
+async fn get(app: &Router, ip: [u8; 4], token: Option<&str>) -> Response<Body> {
+    let mut request = Request::builder().uri(LIMITED);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let mut request = request.body(Body::empty()).unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from((ip, 40000))));
+
+    app.clone().oneshot(request).await.unwrap()
+}
+
+fn header<'a>(response: &'a Response<Body>, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn bursts_beyond_the_policy_are_rejected() {
+    let app = create_app_with_state(state(2, RateLimitKey::User).await);
+
+    let first = get(&app, [10, 0, 0, 1], None).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(header(&first, "ratelimit-limit"), Some("2"));
+    assert_eq!(header(&first, "ratelimit-remaining"), Some("1"));
+    assert_eq!(header(&first, "ratelimit-reset"), Some("30"));
+    assert_eq!(header(&first, "ratelimit-policy"), Some("2;w=60"));
+
+    let second = get(&app, [10, 0, 0, 1], None).await;
+    assert_eq!(second.status(), StatusCode::OK);
+    assert_eq!(header(&second, "ratelimit-remaining"), Some("0"));
+
+    let rejected = get(&app, [10, 0, 0, 1], None).await;
+    assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&rejected, "retry-after"), Some("30"));
+    assert_eq!(header(&rejected, "ratelimit-remaining"), Some("0"));
+
+    let body = axum::body::to_bytes(rejected.into_body(), 4096)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["code"], "request.rate_limited");
+    assert_eq!(body["detail"], "Too many requests. Try again in 30 seconds");
+}
+
+#[tokio::test]
+async fn clients_and_users_have_their_own_buckets() {
+    let state = state(1, RateLimitKey::User).await;
+    let alice = state
+        .jwt_service
+        .generate_token(Uuid::new_v4(), "alice@example.com")
+        .unwrap();
+    let bob = state
+        .jwt_service
+        .generate_token(Uuid::new_v4(), "bob@example.com")
+        .unwrap();
+    let app = create_app_with_state(state);
+
+    assert_eq!(
+        get(&app, [10, 0, 0, 1], None).await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        get(&app, [10, 0, 0, 1], None).await.status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    assert_eq!(
+        get(&app, [10, 0, 0, 2], None).await.status(),
+        StatusCode::OK
+    );
+
+    // Signed-in users are keyed by account, whatever their address.
+    assert_eq!(
+        get(&app, [10, 0, 0, 1], Some(&alice)).await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        get(&app, [10, 0, 0, 3], Some(&alice)).await.status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    assert_eq!(
+        get(&app, [10, 0, 0, 1], Some(&bob)).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn exempt_users_are_not_limited() {
+    let state = state(1, RateLimitKey::User).await;
+    let user_id = Uuid::new_v4();
+    let token = state
+        .jwt_service
+        .generate_token(user_id, "partner@example.com")
+        .unwrap();
+    state.rate_limiter.exemptions().remember(user_id, true);
+    let app = create_app_with_state(state);
+
+    for _ in 0..3 {
+        let response = get(&app, [10, 0, 0, 1], Some(&token)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(header(&response, "ratelimit-limit").is_none());
+    }
+}
+
+#[tokio::test]
+async fn unverified_tokens_share_the_address_bucket() {
+    let state = state(1, RateLimitKey::Token).await;
+    let token = state
+        .jwt_service
+        .generate_token(Uuid::new_v4(), "alice@example.com")
+        .unwrap();
+    let app = create_app_with_state(state);
+
+    // Each made-up token is keyed by the client address, not by itself.
+    assert_eq!(
+        get(&app, [10, 0, 0, 1], Some("forged-1")).await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        get(&app, [10, 0, 0, 1], Some("forged-2")).await.status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // A valid token has a bucket of its own.
+    assert_eq!(
+        get(&app, [10, 0, 0, 1], Some(&token)).await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        get(&app, [10, 0, 0, 2], Some(&token)).await.status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
@@ -0,0 +1,126 @@
+use axum::body::Body;
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::{middleware, routing::get, Router};
+use backend::error::{AppError, Message};
+use backend::middleware::{request_id_middleware, PropagateRequestId, RequestId};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+/// Serves an endpoint that answers with the `X-Request-Id` it received.
+async fn start_upstream() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/echo", listener.local_addr().unwrap());
+
+    let app = Router::new().route(
+        "/echo",
+        get(|headers: HeaderMap| async move {
+            headers
+                .get("x-request-id")
+                .map(|id| id.to_str().unwrap().to_string())
+                .unwrap_or_default()
+        }),
+    );
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    url
+}
+
+fn app(upstream: String) -> Router {
+    Router::new()
+        .route("/ok", get(|| async { "ok" }))
+        .route(
+            "/missing",
+            get(|| async { AppError::NotFound(Message::new("user-not-found")) }),
+        )
+        .route(
+            "/upstream",
+            get(|| async move {
+                reqwest::Client::new()
+                    .get(&upstream)
+                    .propagate_request_id()
+                    .send()
+                    .await
+                    .unwrap()
+                    .text()
+                    .await
+                    .unwrap()
+            }),
+        )
+        .layer(middleware::from_fn(request_id_middleware))
+}
+
+async fn get_with(app: Router, uri: &str, request_id: Option<&str>) -> axum::response::Response {
+    let mut request = Request::builder().uri(uri);
+    if let Some(id) = request_id {
+        request = request.header("x-request-id", id);
+    }
+
+    app.oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn response_id(response: &axum::response::Response) -> &str {
+    response.headers()["x-request-id"].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn responses_echo_the_callers_request_id() {
+    let app = app(start_upstream().await);
+
+    let response = get_with(app.clone(), "/ok", Some("login-7f3a")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response_id(&response), "login-7f3a");
+
+    let response = get_with(app, "/missing", Some("login-7f3a")).await;
+    assert_eq!(response_id(&response), "login-7f3a");
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["request_id"], "login-7f3a");
+}
+
+#[tokio::test]
+async fn missing_or_unusable_ids_are_replaced() {
+    let app = app(start_upstream().await);
+
+    let response = get_with(app.clone(), "/ok", None).await;
+    let generated = response_id(&response).to_string();
+    assert!(uuid::Uuid::parse_str(&generated).is_ok());
+
+    let too_long = "x".repeat(129);
+    let response = get_with(app.clone(), "/ok", Some(&too_long)).await;
+    assert_ne!(response_id(&response), too_long);
+
+    let response = get_with(app, "/ok", Some("has space")).await;
+    assert_ne!(response_id(&response), "has space");
+    assert_ne!(response_id(&response), generated);
+}
+
+#[tokio::test]
+async fn outbound_calls_carry_the_request_id() {
+    let upstream = start_upstream().await;
+
+    let response = get_with(app(upstream.clone()), "/upstream", Some("oauth-1")).await;
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(body, "oauth-1");
+
+    // Outside a request there is nothing to forward.
+    assert!(RequestId::current().is_none());
+    let forwarded = reqwest::Client::new()
+        .get(&upstream)
+        .propagate_request_id()
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(forwarded, "");
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use backend::services::{EmailOutboxWorker, WeeklyDigestScheduler};
+use backend::utils::EmailService;
+use backend::Database;
+use common::mail_config;
+use sqlx::postgres::PgPoolOptions;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// A database nobody listens on, so every query fails quickly and the
+/// workers go straight to waiting for their next poll.
+fn unreachable_database() -> Database {
+    Database {
+        pool: PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(100))
+            .connect_lazy("postgres://postgres@127.0.0.1:1/backend_test")
+            .unwrap(),
+    }
+}
+
+fn email_service() -> EmailService {
+    EmailService::new(&mail_config()).unwrap()
+}
+
+#[tokio::test]
+async fn workers_stop_when_cancelled() {
+    let shutdown = CancellationToken::new();
+    let db = unreachable_database();
+
+    let outbox = EmailOutboxWorker::new(db.clone(), email_service()).spawn(shutdown.clone());
+    let digests = WeeklyDigestScheduler::new(db, email_service(), "https://app.test".to_string())
+        .spawn(shutdown.clone());
+
+    // Let both fail their first poll and start waiting for the next one.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!outbox.is_finished());
+    assert!(!digests.is_finished());
+
+    shutdown.cancel();
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        outbox.await.unwrap();
+        digests.await.unwrap();
+    })
+    .await
+    .expect("workers stop well before their next poll");
+}
@@ -16,6 +16,8 @@ export const useApi = () => {
     if (tokenCookie.value) {
       config.headers.Authorization = `Bearer ${tokenCookie.value}`
     }
+    // Explicit choice in the UI; the backend prefers it over the stored locale
+    config.params = { lang: useNuxtApp().$i18n.locale.value, ...config.params }
     return config
   })
 
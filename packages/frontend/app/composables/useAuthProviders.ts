@@ -0,0 +1,19 @@
+import type { AuthProviders } from '~/types/auth'
+
+/** OAuth providers the backend has configured; none until it has answered. */
+export const useAuthProviders = () => {
+  const { apiCall } = useApi()
+  const providers = useState<AuthProviders>('auth-providers', () => ({ google: false, discord: false }))
+
+  onMounted(async () => {
+    try {
+      providers.value = await apiCall<AuthProviders>('/auth/providers')
+    } catch (error) {
+      console.error('Failed to load sign-in providers:', error)
+    }
+  })
+
+  const anyEnabled = computed(() => providers.value.google || providers.value.discord)
+
+  return { providers, anyEnabled }
+}
@@ -1,11 +1,13 @@
 import { defineStore } from 'pinia'
-import type { User, AuthResponse, LoginRequest, RegisterRequest } from '~/types/auth'
+import type { User, AuthResponse, LoginRequest, PendingLink, RegisterRequest } from '~/types/auth'
+import type { ProblemDetails } from '~/types/api'
 
 export const useAuthStore = defineStore('auth', () => {
   const user = ref<User | null>(null)
   const token = ref<string | null>(null)
   const isLoading = ref(false)
   const error = ref<string | null>(null)
+  const fieldErrors = ref<Record<string, string>>({})
 
   const isAuthenticated = computed(() => !!token.value && !!user.value)
   const isAdmin = computed(() => user.value?.role === 'admin')
@@ -13,6 +15,14 @@ export const useAuthStore = defineStore('auth', () => {
 
   const { apiCall } = useApi()
 
+  const setError = (err: any, fallback: string) => {
+    const problem: ProblemDetails | undefined = err.response?.data
+    error.value = problem?.detail || fallback
+    fieldErrors.value = Object.fromEntries(
+      (problem?.errors ?? []).map((fieldError) => [fieldError.field, fieldError.detail])
+    )
+  }
+
   const setAuth = (authData: AuthResponse) => {
     user.value = authData.user
     token.value = authData.token
@@ -32,6 +42,7 @@ export const useAuthStore = defineStore('auth', () => {
     user.value = null
     token.value = null
     error.value = null
+    fieldErrors.value = {}
     
     const tokenCookie = useCookie<string | null>('auth-token')
     tokenCookie.value = null
@@ -43,6 +54,7 @@ export const useAuthStore = defineStore('auth', () => {
     try {
       isLoading.value = true
       error.value = null
+      fieldErrors.value = {}
       
       const response = await apiCall<AuthResponse>('/auth/login', {
         method: 'POST',
@@ -54,7 +66,7 @@ export const useAuthStore = defineStore('auth', () => {
       
       return response
     } catch (err: any) {
-      error.value = err.data?.message || 'Login failed'
+      setError(err, 'Login failed')
       throw err
     } finally {
       isLoading.value = false
@@ -65,6 +77,7 @@ export const useAuthStore = defineStore('auth', () => {
     try {
       isLoading.value = true
       error.value = null
+      fieldErrors.value = {}
       
       const response = await apiCall<{ user: User }>('/auth/register', {
         method: 'POST',
@@ -73,7 +86,7 @@ export const useAuthStore = defineStore('auth', () => {
       
       return response
     } catch (err: any) {
-      error.value = err.data?.message || 'Registration failed'
+      setError(err, 'Registration failed')
       throw err
     } finally {
       isLoading.value = false
@@ -117,6 +130,7 @@ export const useAuthStore = defineStore('auth', () => {
     try {
       isLoading.value = true
       error.value = null
+      fieldErrors.value = {}
       
       await apiCall('/auth/verify-email', {
         method: 'POST',
@@ -125,7 +139,7 @@ export const useAuthStore = defineStore('auth', () => {
       
       return true
     } catch (err: any) {
-      error.value = err.data?.message || 'Email verification failed'
+      setError(err, 'Email verification failed')
       throw err
     } finally {
       isLoading.value = false
@@ -136,6 +150,7 @@ export const useAuthStore = defineStore('auth', () => {
     try {
       isLoading.value = true
       error.value = null
+      fieldErrors.value = {}
       
       await apiCall('/auth/resend-verification', {
         method: 'POST',
@@ -144,7 +159,7 @@ export const useAuthStore = defineStore('auth', () => {
       
       return true
     } catch (err: any) {
-      error.value = err.data?.message || 'Failed to resend verification email'
+      setError(err, 'Failed to resend verification email')
       throw err
     } finally {
       isLoading.value = false
@@ -155,6 +170,7 @@ export const useAuthStore = defineStore('auth', () => {
     try {
       isLoading.value = true
       error.value = null
+      fieldErrors.value = {}
       
       await apiCall('/auth/forgot-password', {
         method: 'POST',
@@ -163,7 +179,7 @@ export const useAuthStore = defineStore('auth', () => {
       
       return true
     } catch (err: any) {
-      error.value = err.data?.message || 'Failed to send password reset email'
+      setError(err, 'Failed to send password reset email')
       throw err
     } finally {
       isLoading.value = false
@@ -174,6 +190,7 @@ export const useAuthStore = defineStore('auth', () => {
     try {
       isLoading.value = true
       error.value = null
+      fieldErrors.value = {}
       
       await apiCall('/auth/reset-password', {
         method: 'POST',
@@ -182,7 +199,108 @@ export const useAuthStore = defineStore('auth', () => {
       
       return true
     } catch (err: any) {
-      error.value = err.data?.message || 'Password reset failed'
+      setError(err, 'Password reset failed')
+      throw err
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  const exchangeOAuthCode = async (code: string) => {
+    try {
+      isLoading.value = true
+      error.value = null
+      fieldErrors.value = {}
+
+      const response = await apiCall<AuthResponse>('/auth/oauth/exchange', {
+        method: 'POST',
+        data: { code }
+      })
+
+      setAuth(response)
+      return response
+    } catch (err: any) {
+      setError(err, 'Login failed')
+      throw err
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  const exchangeLinkCode = async (code: string) => {
+    try {
+      isLoading.value = true
+      error.value = null
+      fieldErrors.value = {}
+
+      return await apiCall<PendingLink>('/auth/link/exchange', {
+        method: 'POST',
+        data: { code }
+      })
+    } catch (err: any) {
+      setError(err, 'Account linking failed')
+      throw err
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  const linkWithPassword = async (linkToken: string, password: string) => {
+    try {
+      isLoading.value = true
+      error.value = null
+      fieldErrors.value = {}
+
+      const response = await apiCall<AuthResponse>('/auth/link/password', {
+        method: 'POST',
+        data: { token: linkToken, password }
+      })
+
+      setAuth(response)
+      return response
+    } catch (err: any) {
+      setError(err, 'Account linking failed')
+      throw err
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  const sendLinkConfirmation = async (linkToken: string) => {
+    try {
+      isLoading.value = true
+      error.value = null
+      fieldErrors.value = {}
+
+      await apiCall('/auth/link/email', {
+        method: 'POST',
+        data: { token: linkToken }
+      })
+
+      return true
+    } catch (err: any) {
+      setError(err, 'Failed to send confirmation email')
+      throw err
+    } finally {
+      isLoading.value = false
+    }
+  }
+
+  const confirmLinkEmail = async (confirmToken: string) => {
+    try {
+      isLoading.value = true
+      error.value = null
+      fieldErrors.value = {}
+
+      const response = await apiCall<AuthResponse>('/auth/link/confirm', {
+        method: 'POST',
+        data: { token: confirmToken }
+      })
+
+      setAuth(response)
+      return response
+    } catch (err: any) {
+      setError(err, 'Account linking failed')
       throw err
     } finally {
       isLoading.value = false
@@ -209,6 +327,7 @@ export const useAuthStore = defineStore('auth', () => {
     token,
     isLoading,
     error,
+    fieldErrors,
     isAuthenticated,
     isAdmin,
     isModerator,
@@ -221,6 +340,11 @@ export const useAuthStore = defineStore('auth', () => {
     resendVerification,
     forgotPassword,
     resetPassword,
+    exchangeOAuthCode,
+    exchangeLinkCode,
+    linkWithPassword,
+    sendLinkConfirmation,
+    confirmLinkEmail,
     initializeAuth,
     clearAuth
   }
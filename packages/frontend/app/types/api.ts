@@ -0,0 +1,16 @@
+export interface FieldProblem {
+  field: string
+  code: string
+  detail: string
+}
+
+// Error body returned by the API, see packages/backend/docs/errors.md
+export interface ProblemDetails {
+  type: string
+  title: string
+  status: number
+  code: string
+  detail: string
+  errors?: FieldProblem[]
+  request_id?: string
+}
@@ -10,6 +10,8 @@ export interface User {
   is_verified: boolean
   provider: string
   locale: string
+  timezone: string
+  digest_day: number
   created_at: string
 }
 
@@ -31,6 +33,11 @@ export interface AuthResponse {
   token: string
 }
 
+export interface PendingLink {
+  token: string
+  email: string
+}
+
 export interface ForgotPasswordRequest {
   email: string
 }
@@ -42,4 +49,8 @@ export interface ResetPasswordRequest {
 
 export interface VerifyEmailRequest {
   token: string
-}
\ No newline at end of file
+}
+export interface AuthProviders {
+  google: boolean
+  discord: boolean
+}